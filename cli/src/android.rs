@@ -257,9 +257,9 @@ impl UiElement {
         } else if !self.content_desc.is_empty() {
             self.content_desc.clone()
         } else if !self.resource_id.is_empty() {
-            self.resource_id.split('/').last().unwrap_or("").to_string()
+            self.resource_id.split('/').next_back().unwrap_or("").to_string()
         } else {
-            self.class.split('.').last().unwrap_or("").to_string()
+            self.class.split('.').next_back().unwrap_or("").to_string()
         }
     }
 
@@ -522,6 +522,22 @@ pub fn list_devices() -> Result<Vec<Device>> {
     Ok(devices)
 }
 
+/// Resolve the serial to drive: the given one, or the first running emulator
+pub fn get_emulator_serial(serial: Option<&str>) -> Result<String> {
+    if let Some(serial) = serial {
+        return Ok(serial.to_string());
+    }
+
+    let devices = list_devices()?;
+    first_running_emulator(&devices)
+        .map(|d| d.serial.clone())
+        .ok_or_else(|| anyhow::anyhow!("No running Android emulator found. Start one with `emulator -avd <name>`"))
+}
+
+fn first_running_emulator(devices: &[Device]) -> Option<&Device> {
+    devices.iter().find(|d| d.serial.starts_with("emulator-") && d.state == "device")
+}
+
 /// Print devices list
 pub fn print_devices() -> Result<()> {
     let devices = list_devices()?;
@@ -545,7 +561,7 @@ pub fn list_apps(filter: Option<&str>, device: Option<&str>) -> Result<()> {
         .lines()
         .filter_map(|line| line.strip_prefix("package:"))
         .filter(|pkg| {
-            filter.map_or(true, |f| pkg.to_lowercase().contains(&f.to_lowercase()))
+            filter.is_none_or(|f| pkg.to_lowercase().contains(&f.to_lowercase()))
         })
         .map(|s| s.to_string())
         .collect();
@@ -756,25 +772,29 @@ pub fn screen_power(on: bool, device: Option<&str>) -> Result<()> {
 }
 
 /// Get screen resolution
+///
+/// Returns the logical resolution: an `Override size` set via `wm size`
+/// takes precedence over the panel's physical size.
 pub fn get_screen_size(device: Option<&str>) -> Result<(u32, u32)> {
     let output = adb_exec(device, &["shell", "wm", "size"], None)?;
     let out = String::from_utf8_lossy(&output.stdout);
 
-    // Parse "Physical size: 1080x2400"
-    for line in out.lines() {
-        if line.contains("Physical size:") || line.contains("Override size:") {
-            if let Some(size) = line.split(':').nth(1) {
-                let parts: Vec<&str> = size.trim().split('x').collect();
-                if parts.len() == 2 {
-                    let w: u32 = parts[0].parse().unwrap_or(1080);
-                    let h: u32 = parts[1].parse().unwrap_or(1920);
-                    return Ok((w, h));
-                }
-            }
-        }
-    }
+    Ok(parse_wm_size(&out).unwrap_or((1080, 1920))) // Default fallback
+}
+
+/// Parse `wm size` output ("Physical size: 1080x2400", optionally followed
+/// by "Override size: 720x1600")
+fn parse_wm_size(out: &str) -> Option<(u32, u32)> {
+    let parse_line = |prefix: &str| {
+        out.lines()
+            .find_map(|line| line.trim().strip_prefix(prefix))
+            .and_then(|size| {
+                let (w, h) = size.trim().split_once('x')?;
+                Some((w.parse().ok()?, h.parse().ok()?))
+            })
+    };
 
-    Ok((1080, 1920)) // Default fallback
+    parse_line("Override size:").or_else(|| parse_line("Physical size:"))
 }
 
 /// Analyze screen and return structured element categories
@@ -844,7 +864,7 @@ pub fn find_and_tap(description: &str, min_confidence: u32, device: Option<&str>
         let mut score: u32 = 0;
         let text_lower = elem.text.to_lowercase();
         let content_lower = elem.content_desc.to_lowercase();
-        let res_lower = elem.resource_id.to_lowercase().replace('_', " ").replace('/', " ");
+        let res_lower = elem.resource_id.to_lowercase().replace(['_', '/'], " ");
 
         // Exact text match
         if text_lower == desc_lower {
@@ -982,6 +1002,33 @@ mod tests {
         assert_eq!(elem.label(), "my_button");
     }
 
+    #[test]
+    fn test_parse_wm_size_prefers_override() {
+        assert_eq!(parse_wm_size("Physical size: 1080x2400\n"), Some((1080, 2400)));
+        assert_eq!(
+            parse_wm_size("Physical size: 1080x2400\nOverride size: 720x1600\n"),
+            Some((720, 1600))
+        );
+        assert_eq!(parse_wm_size("error: no devices"), None);
+    }
+
+    #[test]
+    fn test_first_running_emulator() {
+        let device = |serial: &str, state: &str| Device {
+            serial: serial.to_string(),
+            state: state.to_string(),
+            model: None,
+        };
+        let devices = vec![
+            device("R58M123ABC", "device"),
+            device("emulator-5554", "offline"),
+            device("emulator-5556", "device"),
+        ];
+
+        assert_eq!(first_running_emulator(&devices).unwrap().serial, "emulator-5556");
+        assert!(first_running_emulator(&devices[..2]).is_none());
+    }
+
     #[test]
     fn test_regexes_compile() {
         // Ensure all regexes compile without panic
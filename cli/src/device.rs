@@ -0,0 +1,120 @@
+//! Unified device handle dispatching to the iOS or Android backend
+
+use anyhow::Result;
+
+#[cfg(feature = "android")]
+use crate::android;
+use crate::ios;
+
+/// iOS Simulator target
+#[derive(Debug, Clone, Default)]
+pub struct IosConfig {
+    /// Simulator name or UDID; `None` uses the booted simulator
+    pub simulator: Option<String>,
+}
+
+/// Android emulator/device target
+#[cfg(feature = "android")]
+#[derive(Debug, Clone, Default)]
+pub struct AndroidConfig {
+    /// ADB serial; `None` uses the first running emulator
+    pub serial: Option<String>,
+}
+
+/// A device backed by either simctl or adb
+#[derive(Debug, Clone)]
+pub enum Device {
+    Ios(IosConfig),
+    #[cfg(feature = "android")]
+    Android(AndroidConfig),
+}
+
+impl Device {
+    /// Take screenshot and return PNG bytes
+    pub fn screenshot(&self) -> Result<Vec<u8>> {
+        match self {
+            Device::Ios(c) => ios::screenshot(c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::screenshot(Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Tap at device coordinates
+    pub fn tap(&self, x: i32, y: i32) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::tap(x, y, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::tap(x, y, Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Swipe between two points over `duration` ms
+    pub fn swipe(&self, x1: i32, y1: i32, x2: i32, y2: i32, duration: u32) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::swipe(x1, y1, x2, y2, duration, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::swipe(x1, y1, x2, y2, duration, Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Type text into the focused field
+    pub fn input_text(&self, text: &str) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::input_text(text, false, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::input_text(text, Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Launch an app by bundle ID / package name
+    pub fn launch_app(&self, app_id: &str) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::launch_app(app_id, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::launch_app(app_id, Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Stop an app by bundle ID / package name
+    pub fn stop_app(&self, app_id: &str) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::stop_app(app_id, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::stop_app(app_id, Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Install an .app bundle or .apk
+    pub fn install_app(&self, path: &str) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::install_app(path, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::install_app(path, Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Print installed apps, optionally filtered
+    pub fn list_apps(&self, filter: Option<&str>) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::print_apps(filter, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::list_apps(filter, Some(&c.resolve_serial()?)),
+        }
+    }
+
+    /// Print recent log lines, optionally filtered
+    pub fn get_logs(&self, filter: Option<&str>, lines: usize) -> Result<()> {
+        match self {
+            Device::Ios(c) => ios::print_logs(filter, lines, None, None, c.simulator.as_deref()),
+            #[cfg(feature = "android")]
+            Device::Android(c) => android::get_logs(filter, lines, Some(&c.resolve_serial()?)),
+        }
+    }
+}
+
+#[cfg(feature = "android")]
+impl AndroidConfig {
+    fn resolve_serial(&self) -> Result<String> {
+        android::get_emulator_serial(self.serial.as_deref())
+    }
+}
@@ -0,0 +1,693 @@
+//! Recording HTTP proxy that captures traffic as a HAR 1.2 archive
+//!
+//! Plain HTTP requests are forwarded and recorded in full; request bodies may be
+//! sized by `Content-Length` or sent chunked, in which case they are relayed with
+//! their framing untouched. HTTPS goes through `CONNECT` tunnels, which are
+//! recorded as a single entry per tunnel (the encrypted payload is passed
+//! through untouched).
+//!
+//! The proxy is a small HTTP/1.1 parser on std `TcpListener` rather than hyper
+//! or tiny-http on purpose: it only relays one exchange per connection on a
+//! thread, and this keeps the CLI free of an async runtime and extra deps.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context, bail};
+use serde::Serialize;
+
+/// Idle timeout for client and origin sockets
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `finish` waits for in-flight exchanges, both before and after
+/// cutting off the connections that are still open
+const FINISH_GRACE: Duration = Duration::from_secs(2);
+
+/// Headers that apply to a single connection and must not be forwarded
+const HOP_BY_HOP_HEADERS: &[&str] = &["connection", "proxy-connection", "keep-alive", "proxy-authorization"];
+
+// ============== HAR 1.2 Types ==============
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HarNameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub headers: Vec<HarNameValue>,
+    pub query_string: Vec<HarNameValue>,
+    pub cookies: Vec<HarNameValue>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    pub size: i64,
+    pub mime_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub headers: Vec<HarNameValue>,
+    pub cookies: Vec<HarNameValue>,
+    pub content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarTimings {
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    pub started_date_time: String,
+    /// Total elapsed milliseconds
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    pub cache: serde_json::Value,
+    pub timings: HarTimings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    pub entries: Vec<HarEntry>,
+}
+
+/// Top-level HAR document (`{"log": {...}}`)
+#[derive(Debug, Clone, Serialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+/// Totals reported when a capture stops
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HarSummary {
+    pub total_requests: usize,
+    /// "METHOD url" of the entry with the longest total time
+    pub slowest_endpoint: Option<String>,
+    pub slowest_ms: f64,
+    /// Header and body bytes in both directions
+    pub total_bytes: u64,
+}
+
+impl Har {
+    pub fn new(entries: Vec<HarEntry>) -> Self {
+        Self {
+            log: HarLog {
+                version: "1.2".to_string(),
+                creator: HarCreator {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries,
+            },
+        }
+    }
+
+    pub fn summary(&self) -> HarSummary {
+        let entries = &self.log.entries;
+        let slowest = entries.iter().max_by(|a, b| a.time.total_cmp(&b.time));
+        let total_bytes = entries.iter()
+            .map(|e| {
+                [e.request.headers_size, e.request.body_size, e.response.headers_size, e.response.body_size]
+                    .iter()
+                    .map(|&n| n.max(0) as u64)
+                    .sum::<u64>()
+            })
+            .sum();
+
+        HarSummary {
+            total_requests: entries.len(),
+            slowest_endpoint: slowest.map(|e| format!("{} {}", e.request.method, e.request.url)),
+            slowest_ms: slowest.map_or(0.0, |e| e.time),
+            total_bytes,
+        }
+    }
+}
+
+// ============== Proxy ==============
+
+/// Running proxy on 127.0.0.1; call `finish` to stop it and collect the archive
+pub struct HarProxy {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    entries: Arc<Mutex<Vec<HarEntry>>>,
+    accept_thread: JoinHandle<()>,
+}
+
+impl HarProxy {
+    /// Bind to an ephemeral localhost port and start accepting connections
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").context("Failed to bind HAR proxy")?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let entries = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_thread = {
+            let stop = Arc::clone(&stop);
+            let entries = Arc::clone(&entries);
+            std::thread::spawn(move || accept_loop(listener, &stop, &entries))
+        };
+
+        Ok(Self { addr, stop, entries, accept_thread })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop accepting, give in-flight exchanges a short grace period and return
+    /// everything recorded. Connections still open after that (keep-alive
+    /// tunnels, websockets) are shut down, so this never blocks indefinitely.
+    pub fn finish(self) -> Result<Har> {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the blocking accept() so the loop observes the stop flag
+        let _ = TcpStream::connect(self.addr);
+        self.accept_thread.join().map_err(|_| anyhow::anyhow!("HAR proxy thread panicked"))?;
+
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap());
+        Ok(Har::new(entries))
+    }
+}
+
+/// Sockets of in-flight exchanges, so `finish` can unblock workers stuck in I/O
+#[derive(Default)]
+struct Connections {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<u64, TcpStream>>,
+}
+
+/// Registration in `Connections`, removed again when dropped
+struct TrackedConnection {
+    connections: Arc<Connections>,
+    id: u64,
+}
+
+impl Connections {
+    fn track(self: &Arc<Self>, stream: &TcpStream) -> Result<TrackedConnection> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.open.lock().unwrap().insert(id, stream.try_clone()?);
+        Ok(TrackedConnection { connections: Arc::clone(self), id })
+    }
+
+    fn shutdown_all(&self) {
+        for stream in self.open.lock().unwrap().values() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for TrackedConnection {
+    fn drop(&mut self) {
+        self.connections.open.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Poll until every worker has finished or `timeout` passes
+fn wait_for_workers(workers: &[JoinHandle<()>], timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while workers.iter().any(|w| !w.is_finished()) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn accept_loop(listener: TcpListener, stop: &AtomicBool, entries: &Arc<Mutex<Vec<HarEntry>>>) {
+    let connections = Arc::new(Connections::default());
+    let mut workers = Vec::new();
+
+    for stream in listener.incoming() {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else { continue };
+        let entries = Arc::clone(entries);
+        let connections = Arc::clone(&connections);
+        workers.push(std::thread::spawn(move || {
+            if let Err(e) = handle_client(stream, &entries, &connections) {
+                eprintln!("HAR proxy: {}", e);
+            }
+        }));
+    }
+
+    wait_for_workers(&workers, FINISH_GRACE);
+    connections.shutdown_all();
+    // Workers that still have not finished are detached; their entries are lost
+    wait_for_workers(&workers, FINISH_GRACE);
+}
+
+/// A client connection whose request head has been read
+struct ClientRequest {
+    client: TcpStream,
+    reader: BufReader<TcpStream>,
+    method: String,
+    target: String,
+    version: String,
+    headers: Vec<HarNameValue>,
+    head_size: usize,
+    started: String,
+    timer: Instant,
+    connections: Arc<Connections>,
+}
+
+fn handle_client(client: TcpStream, entries: &Mutex<Vec<HarEntry>>, connections: &Arc<Connections>) -> Result<()> {
+    client.set_read_timeout(Some(IO_TIMEOUT))?;
+    let _tracked = connections.track(&client)?;
+    let mut reader = BufReader::new(client.try_clone()?);
+
+    let Some((start_line, headers, head_size)) = read_head(&mut reader)? else {
+        return Ok(());
+    };
+    let (method, target, version) = parse_request_line(&start_line)?;
+    let started = chrono::Utc::now().to_rfc3339();
+    let timer = Instant::now();
+
+    let connections = Arc::clone(connections);
+    let request = ClientRequest { client, reader, method, target, version, headers, head_size, started, timer, connections };
+    let entry = if request.method.eq_ignore_ascii_case("CONNECT") {
+        tunnel(request)?
+    } else {
+        forward(request)?
+    };
+
+    entries.lock().unwrap().push(entry);
+    Ok(())
+}
+
+/// Forward a plain HTTP request to its origin and relay the response
+fn forward(request: ClientRequest) -> Result<HarEntry> {
+    let ClientRequest { mut client, mut reader, method, target, version, headers, head_size, started, timer, connections } = request;
+    let (body, body_len) = read_body(&mut reader, &headers).context("Failed to read request body")?;
+
+    let url = url::Url::parse(&target)
+        .with_context(|| format!("Expected an absolute URL in proxy request, got '{}'", target))?;
+    let host = url.host_str().context("Request URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let mut origin = TcpStream::connect((host, port))
+        .with_context(|| format!("Failed to connect to {}:{}", host, port))?;
+    origin.set_read_timeout(Some(IO_TIMEOUT))?;
+    let _tracked = connections.track(&origin)?;
+
+    let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+    let mut head = format!("{} {} {}\r\n", method, path, version);
+    for h in headers.iter().filter(|h| !HOP_BY_HOP_HEADERS.contains(&h.name.to_lowercase().as_str())) {
+        head.push_str(&format!("{}: {}\r\n", h.name, h.value));
+    }
+    // One exchange per origin connection, so the response ends at EOF
+    head.push_str("Connection: close\r\n\r\n");
+    origin.write_all(head.as_bytes())?;
+    origin.write_all(&body)?;
+    let send_ms = ms(timer.elapsed());
+
+    let mut raw = Vec::new();
+    origin.read_to_end(&mut raw).context("Failed to read origin response")?;
+    let wait_ms = ms(timer.elapsed()) - send_ms;
+
+    client.write_all(&raw)?;
+    let _ = client.shutdown(Shutdown::Both);
+
+    let response = parse_response(&raw).context("Origin sent a malformed response")?;
+    let query_string = url.query_pairs()
+        .map(|(name, value)| HarNameValue { name: name.into_owned(), value: value.into_owned() })
+        .collect();
+
+    Ok(HarEntry {
+        started_date_time: started,
+        time: ms(timer.elapsed()),
+        request: HarRequest {
+            method,
+            url: url.to_string(),
+            http_version: version,
+            headers,
+            query_string,
+            cookies: Vec::new(),
+            headers_size: head_size as i64,
+            body_size: body_len as i64,
+        },
+        response,
+        cache: serde_json::json!({}),
+        timings: HarTimings { send: send_ms, wait: wait_ms, receive: 0.0 },
+    })
+}
+
+/// Relay an opaque CONNECT tunnel, recording only its size and duration
+fn tunnel(request: ClientRequest) -> Result<HarEntry> {
+    let ClientRequest { mut client, mut reader, target, version, headers, head_size, started, timer, connections, .. } = request;
+    let mut origin = TcpStream::connect(&target)
+        .with_context(|| format!("Failed to connect to {}", target))?;
+    origin.set_read_timeout(Some(IO_TIMEOUT))?;
+    let _tracked = connections.track(&origin)?;
+
+    const ESTABLISHED: &[u8] = b"HTTP/1.1 200 Connection Established\r\n\r\n";
+    client.write_all(ESTABLISHED)?;
+
+    let mut origin_writer = origin.try_clone()?;
+    let upstream = std::thread::spawn(move || {
+        let sent = std::io::copy(&mut reader, &mut origin_writer).unwrap_or(0);
+        let _ = origin_writer.shutdown(Shutdown::Write);
+        sent
+    });
+    let received = std::io::copy(&mut origin, &mut client).unwrap_or(0);
+    let _ = client.shutdown(Shutdown::Both);
+    let sent = upstream.join().unwrap_or(0);
+
+    let elapsed = ms(timer.elapsed());
+    Ok(HarEntry {
+        started_date_time: started,
+        time: elapsed,
+        request: HarRequest {
+            method: "CONNECT".to_string(),
+            url: format!("https://{}", target),
+            http_version: version,
+            headers,
+            query_string: Vec::new(),
+            cookies: Vec::new(),
+            headers_size: head_size as i64,
+            body_size: sent as i64,
+        },
+        response: HarResponse {
+            status: 200,
+            status_text: "Connection Established".to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            cookies: Vec::new(),
+            content: HarContent { size: received as i64, mime_type: String::new() },
+            redirect_url: String::new(),
+            headers_size: ESTABLISHED.len() as i64,
+            body_size: received as i64,
+        },
+        cache: serde_json::json!({}),
+        timings: HarTimings { send: 0.0, wait: 0.0, receive: elapsed },
+    })
+}
+
+// ============== HTTP Parsing ==============
+
+/// Read a start line and headers; `None` if the peer closed before sending
+/// anything. Also returns the byte size of the head.
+fn read_head(reader: &mut impl BufRead) -> Result<Option<(String, Vec<HarNameValue>, usize)>> {
+    let mut start_line = String::new();
+    let mut size = reader.read_line(&mut start_line)?;
+    if size == 0 {
+        return Ok(None);
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line)?;
+        size += n;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if n == 0 || line.is_empty() {
+            break;
+        }
+        if let Some(h) = parse_header_line(line) {
+            headers.push(h);
+        }
+    }
+
+    Ok(Some((start_line.trim_end().to_string(), headers, size)))
+}
+
+/// Read a request body framed by `Content-Length` or `Transfer-Encoding: chunked`.
+/// Returns the bytes as received, chunk framing and trailers included so they can be
+/// relayed as-is, and the decoded body size.
+fn read_body(reader: &mut impl BufRead, headers: &[HarNameValue]) -> Result<(Vec<u8>, usize)> {
+    let chunked = header_value(headers, "transfer-encoding")
+        .is_some_and(|v| v.split(',').any(|coding| coding.trim().eq_ignore_ascii_case("chunked")));
+    if !chunked {
+        let len: usize = header_value(headers, "content-length")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body)?;
+        return Ok((body, len));
+    }
+
+    let mut raw = Vec::new();
+    let mut size = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("Chunked body ended before the last chunk");
+        }
+        raw.extend_from_slice(line.as_bytes());
+        let hex = line.trim_end().split(';').next().unwrap_or("").trim();
+        let chunk = usize::from_str_radix(hex, 16)
+            .with_context(|| format!("Invalid chunk size '{}'", hex))?;
+        if chunk == 0 {
+            break;
+        }
+        // Chunk data is followed by CRLF
+        let mut data = vec![0; chunk + 2];
+        reader.read_exact(&mut data)?;
+        raw.extend_from_slice(&data);
+        size += chunk;
+    }
+    // Optional trailer fields, then the empty line that ends the body
+    loop {
+        let mut line = String::new();
+        let n = reader.read_line(&mut line)?;
+        raw.extend_from_slice(line.as_bytes());
+        if n == 0 || line.trim_end_matches(['\r', '\n']).is_empty() {
+            break;
+        }
+    }
+    Ok((raw, size))
+}
+
+fn parse_header_line(line: &str) -> Option<HarNameValue> {
+    let (name, value) = line.split_once(':')?;
+    Some(HarNameValue { name: name.trim().to_string(), value: value.trim().to_string() })
+}
+
+/// Split "GET http://host/path HTTP/1.1" into method, target and version
+fn parse_request_line(line: &str) -> Result<(String, String, String)> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) => {
+            Ok((method.to_string(), target.to_string(), version.to_string()))
+        }
+        _ => bail!("Malformed request line: '{}'", line),
+    }
+}
+
+fn header_value<'a>(headers: &'a [HarNameValue], name: &str) -> Option<&'a str> {
+    headers.iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+/// Parse a complete raw HTTP response into its HAR representation
+fn parse_response(raw: &[u8]) -> Option<HarResponse> {
+    let head_end = raw.windows(4).position(|w| w == b"\r\n\r\n")? + 4;
+    let head = String::from_utf8_lossy(&raw[..head_end]);
+    let mut lines = head.split("\r\n");
+
+    let status_line = lines.next()?;
+    let mut parts = status_line.splitn(3, ' ');
+    let http_version = parts.next()?.to_string();
+    let status: u16 = parts.next()?.parse().ok()?;
+    let status_text = parts.next().unwrap_or("").to_string();
+
+    let headers: Vec<HarNameValue> = lines.filter_map(parse_header_line).collect();
+    let body_size = (raw.len() - head_end) as i64;
+
+    Some(HarResponse {
+        status,
+        status_text,
+        http_version,
+        content: HarContent {
+            size: body_size,
+            mime_type: header_value(&headers, "content-type").unwrap_or("").to_string(),
+        },
+        redirect_url: header_value(&headers, "location").unwrap_or("").to_string(),
+        headers,
+        cookies: Vec::new(),
+        headers_size: head_end as i64,
+        body_size,
+    })
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let raw = b"HTTP/1.1 302 Found\r\nLocation: /login\r\nContent-Type: text/html\r\n\r\n<a>x</a>";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status, 302);
+        assert_eq!(response.status_text, "Found");
+        assert_eq!(response.redirect_url, "/login");
+        assert_eq!(response.content.mime_type, "text/html");
+        assert_eq!(response.body_size, 8);
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_none());
+    }
+
+    #[test]
+    fn test_read_head() {
+        let mut input = "GET http://a.test/x?q=1 HTTP/1.1\r\nHost: a.test\r\nX-Y: z\r\n\r\nbody".as_bytes();
+        let (line, headers, size) = read_head(&mut input).unwrap().unwrap();
+        assert_eq!(parse_request_line(&line).unwrap().1, "http://a.test/x?q=1");
+        assert_eq!(header_value(&headers, "x-y"), Some("z"));
+        assert_eq!(size, 58);
+        assert!(read_head(&mut "".as_bytes()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_body() {
+        let chunked = vec![HarNameValue { name: "Transfer-Encoding".into(), value: "chunked".into() }];
+        let raw = b"5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Sum: 1\r\n\r\nnext";
+        let mut reader = &raw[..];
+        let (body, size) = read_body(&mut reader, &chunked).unwrap();
+        assert_eq!(body, &raw[..raw.len() - 4]);
+        assert_eq!(size, 11);
+        assert_eq!(reader, b"next");
+
+        assert!(read_body(&mut &b"5\r\nhello\r\n"[..], &chunked).is_err());
+        assert!(read_body(&mut &b"zz\r\n"[..], &chunked).is_err());
+
+        let sized = vec![HarNameValue { name: "Content-Length".into(), value: "3".into() }];
+        assert_eq!(read_body(&mut &b"abcdef"[..], &sized).unwrap(), (b"abc".to_vec(), 3));
+        assert_eq!(read_body(&mut &b"abc"[..], &[]).unwrap(), (Vec::new(), 0));
+    }
+
+    #[test]
+    fn test_proxy_forwards_chunked_body() {
+        const BODY: &[u8] = b"4\r\n{\"a\"\r\n3\r\n:1}\r\n0\r\n\r\n";
+        let origin = TcpListener::bind("127.0.0.1:0").unwrap();
+        let origin_addr = origin.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = origin.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let (line, headers, _) = read_head(&mut reader).unwrap().unwrap();
+            assert_eq!(line, "POST /upload HTTP/1.1");
+            assert_eq!(header_value(&headers, "transfer-encoding"), Some("chunked"));
+            let (body, size) = read_body(&mut reader, &headers).unwrap();
+            assert_eq!(body, BODY);
+            assert_eq!(size, 7);
+            let mut stream = stream;
+            stream.write_all(b"HTTP/1.1 201 Created\r\n\r\n").unwrap();
+        });
+
+        let proxy = HarProxy::start().unwrap();
+        let mut client = TcpStream::connect(proxy.addr()).unwrap();
+        write!(
+            client,
+            "POST http://{}/upload HTTP/1.1\r\nHost: {}\r\nTransfer-Encoding: chunked\r\n\r\n",
+            origin_addr, origin_addr
+        ).unwrap();
+        client.write_all(BODY).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 201"));
+        server.join().unwrap();
+
+        let har = proxy.finish().unwrap();
+        assert_eq!(har.log.entries.len(), 1);
+        assert_eq!(har.log.entries[0].request.body_size, 7);
+        assert_eq!(har.log.entries[0].response.status, 201);
+    }
+
+    #[test]
+    fn test_proxy_records_exchange() {
+        let origin = TcpListener::bind("127.0.0.1:0").unwrap();
+        let origin_addr = origin.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = origin.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let (line, headers, _) = read_head(&mut reader).unwrap().unwrap();
+            assert_eq!(line, "GET /items?page=2 HTTP/1.1");
+            assert!(header_value(&headers, "proxy-connection").is_none());
+            let mut stream = stream;
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n[1,2]").unwrap();
+        });
+
+        let proxy = HarProxy::start().unwrap();
+        let mut client = TcpStream::connect(proxy.addr()).unwrap();
+        write!(
+            client,
+            "GET http://{}/items?page=2 HTTP/1.1\r\nHost: {}\r\nProxy-Connection: keep-alive\r\n\r\n",
+            origin_addr, origin_addr
+        ).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.ends_with("[1,2]"));
+        server.join().unwrap();
+
+        let har = proxy.finish().unwrap();
+        assert_eq!(har.log.entries.len(), 1);
+        let entry = &har.log.entries[0];
+        assert_eq!(entry.response.status, 200);
+        assert_eq!(entry.request.query_string, vec![HarNameValue { name: "page".into(), value: "2".into() }]);
+
+        let summary = har.summary();
+        assert_eq!(summary.total_requests, 1);
+        assert_eq!(summary.slowest_endpoint.unwrap(), format!("GET http://{}/items?page=2", origin_addr));
+        assert!(summary.total_bytes > 5);
+
+        let json = serde_json::to_value(&har).unwrap();
+        assert_eq!(json["log"]["version"], "1.2");
+        assert!(json["log"]["entries"][0]["response"]["redirectURL"].is_string());
+    }
+
+    #[test]
+    fn test_finish_cuts_off_open_tunnel() {
+        let origin = TcpListener::bind("127.0.0.1:0").unwrap();
+        let origin_addr = origin.local_addr().unwrap();
+        // Accept and hold the connection open without ever sending anything
+        let server = std::thread::spawn(move || origin.accept().unwrap());
+
+        let proxy = HarProxy::start().unwrap();
+        let mut client = TcpStream::connect(proxy.addr()).unwrap();
+        write!(client, "CONNECT {} HTTP/1.1\r\nHost: {}\r\n\r\n", origin_addr, origin_addr).unwrap();
+        let mut established = [0u8; 12];
+        client.read_exact(&mut established).unwrap();
+        assert_eq!(&established, b"HTTP/1.1 200");
+        let _held = server.join().unwrap();
+
+        let started = Instant::now();
+        let har = proxy.finish().unwrap();
+        assert!(started.elapsed() < IO_TIMEOUT);
+        assert_eq!(har.log.entries.len(), 1);
+        assert_eq!(har.log.entries[0].request.method, "CONNECT");
+    }
+}
@@ -1,16 +1,75 @@
 //! iOS Simulator automation via simctl
 
+use std::path::{Component, Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use anyhow::{Result, Context, bail};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+/// Error paired with an optional hint on how to fix it
+/// Displays as the error message followed by "Suggestion: <text>"
+#[derive(Debug)]
+pub struct Suggestion {
+    pub error: anyhow::Error,
+    pub suggestion: Option<String>,
+}
+
+impl Suggestion {
+    pub fn new(error: anyhow::Error, suggestion: impl Into<String>) -> Self {
+        Self { error, suggestion: Some(suggestion.into()) }
+    }
+}
+
+impl std::fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\nSuggestion: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Suggestion {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Wrap an error with a suggested fix
+fn with_suggestion(error: anyhow::Error, suggestion: &str) -> anyhow::Error {
+    Suggestion::new(error, suggestion).into()
+}
+
+/// Typed iOS failures callers may want to match on (via `downcast_ref`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum IosError {
+    /// `simctl get_app_container` could not find the container (app not installed, unknown group)
+    ContainerNotFound { bundle_id: String, container: String, message: String },
+    /// The installed Xcode's simctl lacks a command
+    FeatureNotSupported { feature: String, requirement: String, workaround: String },
+}
+
+impl std::fmt::Display for IosError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IosError::ContainerNotFound { bundle_id, container, message } => {
+                write!(f, "No {} container for {}: {}", container, bundle_id, message)
+            }
+            IosError::FeatureNotSupported { feature, requirement, workaround } => {
+                write!(f, "{} is not supported by this Xcode (requires {}); workaround: {}", feature, requirement, workaround)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IosError {}
 
-/// Get simulator UDID (booted or by name)
+/// Get simulator UDID (booted, or by name or UDID)
 fn get_simulator_udid(simulator: Option<&str>) -> Result<String> {
     if let Some(name) = simulator {
-        let output = Command::new("xcrun")
-            .args(["simctl", "list", "devices", "-j"])
-            .output()
-            .context("Failed to list simulators")?;
+        let output = simctl_exec(&["list", "devices", "-j"])?;
 
         let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
 
@@ -18,7 +77,7 @@ fn get_simulator_udid(simulator: Option<&str>) -> Result<String> {
             for (_runtime, device_list) in devices {
                 if let Some(devices) = device_list.as_array() {
                     for device in devices {
-                        if device["name"].as_str() == Some(name) {
+                        if device["name"].as_str() == Some(name) || device["udid"].as_str() == Some(name) {
                             if let Some(udid) = device["udid"].as_str() {
                                 return Ok(udid.to_string());
                             }
@@ -27,19 +86,57 @@ fn get_simulator_udid(simulator: Option<&str>) -> Result<String> {
                 }
             }
         }
-        bail!("Simulator '{}' not found", name);
+        Err(with_suggestion(
+            anyhow::anyhow!("Simulator '{}' not found", name),
+            "Run `claude-in-mobile devices ios` to see available devices",
+        ))
     } else {
         Ok("booted".to_string())
     }
 }
 
+/// Build a simctl command; every argument is passed as a separate argv entry (no shell)
+fn simctl_command(args: &[&str]) -> Command {
+    let mut cmd = Command::new("xcrun");
+    cmd.arg("simctl").args(args);
+    cmd
+}
+
 /// Execute simctl command
-fn simctl_exec(args: &[&str]) -> Result<std::process::Output> {
-    Command::new("xcrun")
-        .arg("simctl")
+pub(crate) fn simctl_exec(args: &[&str]) -> Result<std::process::Output> {
+    simctl_output(&mut simctl_command(args))
+}
+
+/// Run a prepared simctl command, explaining a missing xcrun
+fn simctl_output(cmd: &mut Command) -> Result<std::process::Output> {
+    cmd.output().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            with_suggestion(
+                anyhow::anyhow!("simctl not found (xcrun is not installed)"),
+                "Install Xcode Command Line Tools: xcode-select --install",
+            )
+        } else {
+            anyhow::Error::new(e).context("Failed to execute simctl command")
+        }
+    })
+}
+
+/// Run a program and write `input` to its stdin (safe - data never reaches a shell)
+fn pipe_to_command(program: &str, args: &[&str], input: &str) -> Result<std::process::Output> {
+    use std::io::Write;
+
+    let mut child = Command::new(program)
         .args(args)
-        .output()
-        .context("Failed to execute simctl command")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    child.wait_with_output()
+        .with_context(|| format!("Failed to wait for {}", program))
 }
 
 /// Get Simulator window content area position (top-left of the simulated screen)
@@ -70,59 +167,420 @@ end tell
         .collect();
 
     if parts.len() != 4 {
+        check_screen_capture_permission()?;
         bail!("Failed to parse window geometry: {}", text);
     }
 
     Ok((parts[0], parts[1], parts[2], parts[3]))
 }
 
+/// Mapping from simulator pixel space to host screen space
+#[derive(Debug, Clone, Copy)]
+struct ScreenTransform {
+    origin_x: f64,
+    origin_y: f64,
+    scale: f64,
+}
+
+impl ScreenTransform {
+    /// Derive the mapping from window geometry (x, y, w, h) and simulator resolution
+    fn new(window: (f64, f64, f64, f64), sim_w: f64, sim_h: f64) -> Self {
+        let (wx, wy, ww, wh) = window;
+
+        // The simulator window has a bezel/chrome area around the screen content
+        // The content area takes most of the window
+        // Approximate: toolbar ~44px at top, small padding
+        let toolbar_h = 44.0;
+        let content_h = wh - toolbar_h;
+        let scale_x = ww / sim_w;
+        let scale_y = content_h / sim_h;
+        let scale = scale_x.min(scale_y);
+
+        let content_w = sim_w * scale;
+        let actual_content_h = sim_h * scale;
+        let offset_x = (ww - content_w) / 2.0;
+        let offset_y = toolbar_h + (content_h - actual_content_h) / 2.0;
+
+        Self { origin_x: wx + offset_x, origin_y: wy + offset_y, scale }
+    }
+
+    fn apply(&self, sim_x: i32, sim_y: i32) -> (i32, i32) {
+        let screen_x = self.origin_x + (sim_x as f64) * self.scale;
+        let screen_y = self.origin_y + (sim_y as f64) * self.scale;
+        (screen_x as i32, screen_y as i32)
+    }
+
+    /// Inverse of `apply`: host screen point to simulator pixel
+    fn invert(&self, screen_x: i32, screen_y: i32) -> (i32, i32) {
+        let sim_x = (screen_x as f64 - self.origin_x) / self.scale;
+        let sim_y = (screen_y as f64 - self.origin_y) / self.scale;
+        (sim_x.round() as i32, sim_y.round() as i32)
+    }
+}
+
+/// Get simulator screen resolution in pixels (width, height)
+pub(crate) fn screen_dimensions(simulator: Option<&str>) -> Result<(u32, u32)> {
+    let data = screenshot(simulator)?;
+    let img = image::load_from_memory(&data)?;
+    Ok((img.width(), img.height()))
+}
+
+/// Native screen of a device type, from its profile.plist
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ScreenProfile {
+    /// Portrait width in pixels
+    width: u32,
+    /// Portrait height in pixels
+    height: u32,
+    /// Pixels per point
+    scale: f64,
+}
+
+/// Screen profiles already looked up, keyed by simulator UDID
+static SCREEN_PROFILES: std::sync::OnceLock<Mutex<std::collections::HashMap<String, ScreenProfile>>> =
+    std::sync::OnceLock::new();
+
+/// Read mainScreenWidth/Height/Scale from a device type's profile.plist dictionary
+fn parse_screen_profile(profile: &plist::Value) -> Option<ScreenProfile> {
+    let dict = profile.as_dictionary()?;
+    let number = |key: &str| match dict.get(key)? {
+        plist::Value::Integer(i) => i.as_signed().map(|v| v as f64),
+        plist::Value::Real(f) => Some(*f),
+        _ => None,
+    };
+    Some(ScreenProfile {
+        width: number("mainScreenWidth")? as u32,
+        height: number("mainScreenHeight")? as u32,
+        scale: number("mainScreenScale").unwrap_or(1.0),
+    })
+}
+
+/// Look up (and cache) the screen profile of the simulator's device type
+fn screen_profile(simulator: Option<&str>) -> Result<ScreenProfile> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+    let cache = SCREEN_PROFILES.get_or_init(Default::default);
+    if let Some(profile) = cache.lock().unwrap().get(&udid) {
+        return Ok(*profile);
+    }
+
+    let (info, _) = parse_simulator_info(&simctl_list_json("devices")?, &udid)
+        .ok_or_else(|| anyhow::anyhow!("Simulator '{}' not found", udid))?;
+    let identifier = format!("com.apple.CoreSimulator.SimDeviceType.{}", info.device_type);
+    let bundle = simctl_list_json("devicetypes")?["devicetypes"].as_array()
+        .and_then(|types| types.iter().find(|t| t["identifier"].as_str() == Some(identifier.as_str())))
+        .and_then(|t| t["bundlePath"].as_str().map(str::to_string))
+        .ok_or_else(|| anyhow::anyhow!("Device type {} not found", identifier))?;
+
+    let profile_path = Path::new(&bundle).join("Contents/Resources/profile.plist");
+    let profile = plist::Value::from_file(&profile_path)
+        .ok()
+        .and_then(|v| parse_screen_profile(&v))
+        .ok_or_else(|| anyhow::anyhow!("No screen size in {}", profile_path.display()))?;
+
+    cache.lock().unwrap().insert(udid, profile);
+    Ok(profile)
+}
+
+/// Native (portrait) screen resolution in pixels, without taking a screenshot.
+/// Read from the device type's profile and cached per simulator.
+pub fn screen_size(simulator: Option<&str>) -> Result<(u32, u32)> {
+    let profile = screen_profile(simulator)?;
+    Ok((profile.width, profile.height))
+}
+
+/// Portrait screen size in points (pixels divided by the screen scale)
+pub fn logical_screen_size(simulator: Option<&str>) -> Result<(u32, u32)> {
+    let profile = screen_profile(simulator)?;
+    Ok((
+        (profile.width as f64 / profile.scale).round() as u32,
+        (profile.height as f64 / profile.scale).round() as u32,
+    ))
+}
+
+/// Swap portrait dimensions when the window's content area is landscape
+fn oriented_to_window((width, height): (u32, u32), window: (f64, f64, f64, f64)) -> (u32, u32) {
+    let (_, _, ww, wh) = window;
+    // Same toolbar allowance as ScreenTransform::new
+    if ww > wh - 44.0 { (width.max(height), width.min(height)) } else { (width.min(height), width.max(height)) }
+}
+
+/// Build the simulator-to-screen mapping for the current window position
+fn screen_transform(simulator: Option<&str>) -> Result<ScreenTransform> {
+    let window = get_simulator_window_geometry()?;
+    let (sim_w, sim_h) = match screen_size(simulator) {
+        Ok(size) => oriented_to_window(size, window),
+        // Profiles can be missing for third-party device types; measure a screenshot instead
+        Err(_) => screen_dimensions(simulator)?,
+    };
+    Ok(ScreenTransform::new(window, sim_w as f64, sim_h as f64))
+}
+
 /// Convert simulator coordinates to screen coordinates
 /// sim_x, sim_y are in simulator pixel space (e.g. 1206x2622)
 /// Returns screen coordinates for AppleScript click
-fn sim_to_screen_coords(sim_x: i32, sim_y: i32, simulator: Option<&str>) -> Result<(i32, i32)> {
-    let (wx, wy, ww, wh) = get_simulator_window_geometry()?;
+pub(crate) fn sim_to_screen_coords(sim_x: i32, sim_y: i32, simulator: Option<&str>) -> Result<(i32, i32)> {
+    Ok(screen_transform(simulator)?.apply(sim_x, sim_y))
+}
 
-    // Get simulator resolution from screenshot
-    let data = screenshot(simulator)?;
-    let img = image::load_from_memory(&data)?;
-    let sim_w = img.width() as f64;
-    let sim_h = img.height() as f64;
+/// Check whether cliclick is installed (needed for real press/drag/release events)
+pub(crate) fn has_cliclick() -> bool {
+    Command::new("which")
+        .arg("cliclick")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Linearly interpolate `steps` points from `from` to `to` (excluding `from`, including `to`)
+fn interpolate_points(from: (i32, i32), to: (i32, i32), steps: u32) -> Vec<(i32, i32)> {
+    let steps = steps.max(1);
+    (1..=steps)
+        .map(|i| {
+            let t = i as f64 / steps as f64;
+            let x = from.0 as f64 + (to.0 - from.0) as f64 * t;
+            let y = from.1 as f64 + (to.1 - from.1) as f64 * t;
+            (x.round() as i32, y.round() as i32)
+        })
+        .collect()
+}
+
+/// Encoding for screenshot output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    /// JPEG with quality 1-100
+    Jpeg { quality: u8 },
+}
+
+impl OutputFormat {
+    /// File extension (with leading dot) for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => ".png",
+            OutputFormat::Jpeg { .. } => ".jpg",
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let OutputFormat::Jpeg { quality } = self {
+            if !(1..=100).contains(quality) {
+                bail!("Invalid JPEG quality {}: must be 1-100", quality);
+            }
+        }
+        Ok(())
+    }
+}
 
-    // The simulator window has a bezel/chrome area around the screen content
-    // The content area takes most of the window
-    // Approximate: toolbar ~44px at top, small padding
-    let toolbar_h = 44.0;
-    let content_h = wh - toolbar_h;
-    let scale_x = ww / sim_w;
-    let scale_y = content_h / sim_h;
-    let scale = scale_x.min(scale_y);
+/// Capture the simulator screen (always PNG) into `path` via simctl
+fn capture_screenshot_file(udid: &str, path: &str) -> Result<()> {
+    let output = simctl_exec(&["io", udid, "screenshot", "--type=png", path])?;
 
-    let content_w = sim_w * scale;
-    let actual_content_h = sim_h * scale;
-    let offset_x = (ww - content_w) / 2.0;
-    let offset_y = toolbar_h + (content_h - actual_content_h) / 2.0;
+    if !output.status.success() {
+        check_screen_capture_permission()?;
+        bail!("simctl screenshot failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
 
-    let screen_x = wx + offset_x + (sim_x as f64) * scale;
-    let screen_y = wy + offset_y + (sim_y as f64) * scale;
+    let empty = std::fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+    if empty {
+        check_screen_capture_permission()?;
+        bail!("simctl screenshot produced an empty image");
+    }
+    Ok(())
+}
 
-    Ok((screen_x as i32, screen_y as i32))
+/// Re-encode an image as JPEG into any writer
+fn write_jpeg<W: std::io::Write>(img: &image::DynamicImage, quality: u8, writer: W) -> Result<()> {
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(writer, quality);
+    img.to_rgb8().write_with_encoder(encoder).context("Failed to encode JPEG")?;
+    Ok(())
 }
 
 /// Take screenshot and return PNG bytes
 pub fn screenshot(simulator: Option<&str>) -> Result<Vec<u8>> {
+    screenshot_with_format(OutputFormat::Png, simulator)
+}
+
+/// Take screenshot and return bytes encoded in `format`
+pub fn screenshot_with_format(format: OutputFormat, simulator: Option<&str>) -> Result<Vec<u8>> {
+    format.validate()?;
+    let udid = get_simulator_udid(simulator)?;
+    // Named for the requested format; simctl still writes PNG, so decoding goes by content
+    let temp = temp_file("ios_screenshot_", format.extension())?;
+    let temp_path = temp.path().to_string_lossy().to_string();
+
+    capture_screenshot_file(&udid, &temp_path)?;
+    let data = std::fs::read(&temp_path).context("Failed to read screenshot")?;
+
+    match format {
+        OutputFormat::Png => Ok(data),
+        OutputFormat::Jpeg { quality } => {
+            let img = image::load_from_memory(&data).context("Failed to decode screenshot")?;
+            let mut jpeg = Vec::new();
+            write_jpeg(&img, quality, &mut jpeg)?;
+            Ok(jpeg)
+        }
+    }
+}
+
+/// Save a screenshot straight to `path`; PNG is written by simctl, JPEG is streamed to the file
+pub fn screenshot_to_file(path: &str, format: OutputFormat, simulator: Option<&str>) -> Result<()> {
+    format.validate()?;
     let udid = get_simulator_udid(simulator)?;
-    let temp_path = "/tmp/ios_screenshot.png";
 
-    let output = simctl_exec(&["io", &udid, "screenshot", temp_path])?;
+    match format {
+        OutputFormat::Png => capture_screenshot_file(&udid, path)?,
+        OutputFormat::Jpeg { quality } => {
+            let temp = temp_file("ios_screenshot_", format.extension())?;
+            let temp_path = temp.path().to_string_lossy().to_string();
+            capture_screenshot_file(&udid, &temp_path)?;
+
+            // The .jpg temp file holds simctl's PNG, so sniff the format instead of trusting the name
+            let img = image::ImageReader::open(&temp_path)
+                .and_then(|reader| reader.with_guessed_format())
+                .context("Failed to read screenshot")?
+                .decode()
+                .context("Failed to decode screenshot")?;
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path))?;
+            write_jpeg(&img, quality, std::io::BufWriter::new(file))?;
+        }
+    }
+    Ok(())
+}
 
-    if !output.status.success() {
-        bail!("simctl screenshot failed: {}", String::from_utf8_lossy(&output.stderr));
+/// Encode an image as PNG bytes
+fn encode_png(img: &image::DynamicImage) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut data), image::ImageFormat::Png)
+        .context("Failed to encode PNG")?;
+    Ok(data)
+}
+
+/// Crop PNG bytes to a rectangle, failing if it does not fit inside the image
+fn crop_png(data: &[u8], x: i32, y: i32, width: i32, height: i32) -> Result<Vec<u8>> {
+    let img = image::load_from_memory(data).context("Failed to decode screenshot")?;
+    let (img_w, img_h) = (img.width() as i64, img.height() as i64);
+
+    if width <= 0 || height <= 0 {
+        bail!("Invalid region size {}x{}: width and height must be positive", width, height);
+    }
+    if x < 0 || y < 0 || x as i64 + width as i64 > img_w || y as i64 + height as i64 > img_h {
+        bail!(
+            "Region ({}, {}, {}x{}) extends outside the simulator screen ({}x{})",
+            x, y, width, height, img_w, img_h
+        );
     }
 
-    let data = std::fs::read(temp_path).context("Failed to read screenshot")?;
-    std::fs::remove_file(temp_path).ok();
+    encode_png(&img.crop_imm(x as u32, y as u32, width as u32, height as u32))
+}
 
-    Ok(data)
+/// Screenshot cropped to a rectangle in simulator pixel space
+pub fn screenshot_region(x: i32, y: i32, width: i32, height: i32, simulator: Option<&str>) -> Result<Vec<u8>> {
+    let data = screenshot(simulator)?;
+    crop_png(&data, x, y, width, height)
+}
+
+/// Screenshot cropped to an element's bounding box
+/// Element bounds come from the accessibility tree in host screen points and are mapped back to pixels
+pub fn screenshot_element(element: &UiElement, simulator: Option<&str>) -> Result<Vec<u8>> {
+    let transform = screen_transform(simulator)?;
+    let (x1, y1) = transform.invert(element.x, element.y);
+    let (x2, y2) = transform.invert(element.x + element.width, element.y + element.height);
+    screenshot_region(x1, y1, x2 - x1, y2 - y1, simulator)
+}
+
+/// Outline color for an element in `annotated_screenshot`: buttons blue,
+/// static text green, text fields red, everything else gray
+fn role_color(role: &str) -> image::Rgba<u8> {
+    match role {
+        "AXButton" | "AXLink" => image::Rgba([0, 122, 255, 255]),
+        "AXStaticText" => image::Rgba([52, 199, 89, 255]),
+        "AXTextField" | "AXTextArea" | "AXSecureTextField" | "AXSearchField" => image::Rgba([255, 59, 48, 255]),
+        _ => image::Rgba([142, 142, 147, 255]),
+    }
+}
+
+/// Rectangle (x, y, width, height) in screenshot pixels
+type PixelRect = (i32, i32, i32, i32);
+
+/// Draw each (element, pixel rect) onto `img` as a role-colored outline with its label above it
+fn draw_element_bounds(img: &mut image::RgbaImage, boxes: &[(&UiElement, PixelRect)]) -> Result<()> {
+    use imageproc::drawing::{draw_hollow_rect_mut, draw_text_mut};
+
+    let font = ab_glyph::FontArc::try_from_slice(include_bytes!("../assets/DejaVuSans.ttf"))
+        .context("Failed to load font")?;
+    let scale = ab_glyph::PxScale::from(24.0);
+
+    for (elem, (x, y, w, h)) in boxes {
+        if *w <= 0 || *h <= 0 {
+            continue;
+        }
+        let color = role_color(&elem.role);
+        // Two nested outlines keep the border visible on busy backgrounds
+        draw_hollow_rect_mut(img, imageproc::rect::Rect::at(*x, *y).of_size(*w as u32, *h as u32), color);
+        if *w > 2 && *h > 2 {
+            draw_hollow_rect_mut(img, imageproc::rect::Rect::at(x + 1, y + 1).of_size(*w as u32 - 2, *h as u32 - 2), color);
+        }
+
+        let label: String = element_label(elem).chars().take(32).collect();
+        if !label.is_empty() {
+            draw_text_mut(img, color, *x, (y - 24).max(0), scale, &font, &label);
+        }
+    }
+    Ok(())
+}
+
+/// Screenshot with every accessibility element's bounds outlined and labelled,
+/// color-coded by role (see `role_color`); returns PNG bytes
+pub fn annotated_screenshot(simulator: Option<&str>) -> Result<Vec<u8>> {
+    let png = screenshot(simulator)?;
+    let mut img = image::load_from_memory(&png).context("Failed to decode screenshot")?.to_rgba8();
+    let elements = get_accessibility_elements()?;
+
+    // Element bounds are host screen points; map them back to screenshot pixels
+    let transform = ScreenTransform::new(get_simulator_window_geometry()?, img.width() as f64, img.height() as f64);
+    let boxes: Vec<_> = elements.iter().map(|elem| {
+        let (x1, y1) = transform.invert(elem.x, elem.y);
+        let (x2, y2) = transform.invert(elem.x + elem.width, elem.y + elem.height);
+        (elem, (x1, y1, x2 - x1, y2 - y1))
+    }).collect();
+
+    draw_element_bounds(&mut img, &boxes)?;
+    encode_png(&image::DynamicImage::ImageRgba8(img))
+}
+
+/// Create a uniquely named temp file (removed on drop) so concurrent calls never share a path
+fn temp_file(prefix: &str, suffix: &str) -> Result<tempfile::NamedTempFile> {
+    tempfile::Builder::new()
+        .prefix(prefix)
+        .suffix(suffix)
+        .tempfile()
+        .context("Failed to create temp file")
+}
+
+/// Bring Simulator to the front and verify it is the frontmost app
+///
+/// AppleScript clicks and keystrokes go to whichever app is frontmost, so
+/// without this they can silently land in another window.
+pub fn ensure_simulator_focused(_simulator: Option<&str>) -> Result<()> {
+    let script = r#"tell application "Simulator" to activate
+delay 0.3
+tell application "System Events" to return name of first application process whose frontmost is true"#;
+
+    let output = Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .context("Failed to activate Simulator via AppleScript")?;
+
+    let front = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || front != "Simulator" {
+        let front = if front.is_empty() { "unknown".to_string() } else { front };
+        return Err(with_suggestion(
+            anyhow::anyhow!("Simulator is not the frontmost application (frontmost: {})", front),
+            "Open Simulator.app and grant Accessibility access to your terminal in System Settings > Privacy & Security",
+        ));
+    }
+    Ok(())
 }
 
 /// Long press at coordinates via AppleScript mouse events
@@ -131,11 +589,10 @@ pub fn long_press(x: i32, y: i32, duration: u32, simulator: Option<&str>) -> Res
 
     let (sx, sy) = sim_to_screen_coords(x, y, simulator)?;
     let delay_sec = duration as f64 / 1000.0;
+    ensure_simulator_focused(simulator)?;
 
     let script = format!(
-        r#"tell application "Simulator" to activate
-delay 0.2
-tell application "System Events"
+        r#"tell application "System Events"
     set p to {{{}, {}}}
     -- mouse down, hold, mouse up
     click at p
@@ -147,25 +604,76 @@ end tell"#,
     let _ = Command::new("osascript")
         .args(["-e", &script])
         .output();
-
-    println!("Long pressed at ({}, {}) for {}ms", x, y, duration);
     Ok(())
 }
 
+/// URL schemes that simctl openurl cannot meaningfully handle
+const UNSUPPORTED_URL_SCHEMES: &[&str] = &["javascript", "data", "file", "blob"];
+
+/// Parse and validate a URL before handing it to simctl
+/// Rejects unsupported schemes, raw whitespace/control characters and broken percent-escapes
+pub fn validate_url(url: &str) -> Result<url::Url> {
+    if let Some(c) = url.chars().find(|c| c.is_whitespace() || c.is_control()) {
+        bail!("Invalid URL '{}': contains unencoded character {:?} (percent-encode it)", url, c);
+    }
+
+    let bytes = url.as_bytes();
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'%' {
+            let valid = bytes.len() > i + 2
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit();
+            if !valid {
+                bail!("Invalid URL '{}': malformed percent-encoding at position {}", url, i);
+            }
+        }
+    }
+
+    let parsed = url::Url::parse(url)
+        .with_context(|| format!("Invalid URL '{}'", url))?;
+
+    if UNSUPPORTED_URL_SCHEMES.contains(&parsed.scheme()) {
+        bail!("Invalid URL '{}': scheme '{}' is not supported by simctl openurl", url, parsed.scheme());
+    }
+
+    Ok(parsed)
+}
+
 /// Open URL in simulator (safe - no shell injection)
 pub fn open_url(url: &str, simulator: Option<&str>) -> Result<()> {
+    let parsed = validate_url(url)?;
     let udid = get_simulator_udid(simulator)?;
 
-    let output = simctl_exec(&["openurl", &udid, url])?;
+    let output = simctl_exec(&["openurl", &udid, parsed.as_str()])?;
 
     if !output.status.success() {
         bail!("Failed to open URL: {}", String::from_utf8_lossy(&output.stderr));
     }
-
-    println!("Opened URL: {}", url);
     Ok(())
 }
 
+/// Minimal URL (`scheme://`) for probing whether `scheme` has a registered handler
+fn scheme_probe_url(scheme: &str) -> Result<url::Url> {
+    let valid = scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    if !valid {
+        bail!("Invalid URL scheme '{}': must start with a letter and contain only letters, digits, '+', '-' or '.'", scheme);
+    }
+    validate_url(&format!("{}://", scheme))
+}
+
+/// Whether some app handles `scheme` URLs, e.g. to assert a custom scheme is registered
+///
+/// simctl rejects URLs nobody handles, so an accepted `openurl` counts as handled.
+/// The handler is opened as a side effect.
+pub fn test_url_scheme(scheme: &str, simulator: Option<&str>) -> Result<bool> {
+    let url = scheme_probe_url(scheme)?;
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["openurl", &udid, url.as_str()])?;
+    Ok(output.status.success())
+}
+
 /// Execute shell command in simulator (safe - uses spawn)
 pub fn shell(command: &str, simulator: Option<&str>) -> Result<String> {
     let udid = get_simulator_udid(simulator)?;
@@ -183,7 +691,6 @@ pub fn shell(command: &str, simulator: Option<&str>) -> Result<String> {
         eprintln!("{}", stderr);
     }
 
-    print!("{}", stdout);
     Ok(stdout)
 }
 
@@ -192,11 +699,10 @@ pub fn tap(x: i32, y: i32, simulator: Option<&str>) -> Result<()> {
     let _udid = get_simulator_udid(simulator)?;
 
     let (sx, sy) = sim_to_screen_coords(x, y, simulator)?;
+    ensure_simulator_focused(simulator)?;
 
     let script = format!(
-        r#"tell application "Simulator" to activate
-delay 0.2
-tell application "System Events"
+        r#"tell application "System Events"
     click at {{{}, {}}}
 end tell"#,
         sx, sy
@@ -210,11 +716,132 @@ end tell"#,
     if !output.status.success() {
         eprintln!("Warning: AppleScript tap may not work without accessibility permissions");
     }
+    Ok(())
+}
+
+/// iOS double-tap recognition threshold; a sensible default for `double_tap`
+pub const DEFAULT_DOUBLE_TAP_INTERVAL_MS: u32 = 100;
+
+/// AppleScript for two clicks at a screen point; interval 0 uses the native `double click at` form
+fn double_tap_script(sx: i32, sy: i32, interval_ms: u32) -> String {
+    let clicks = if interval_ms == 0 {
+        format!("    double click at {{{}, {}}}", sx, sy)
+    } else {
+        format!(
+            "    click at {{{sx}, {sy}}}\n    delay {}\n    click at {{{sx}, {sy}}}",
+            interval_ms as f64 / 1000.0
+        )
+    };
+    format!("tell application \"System Events\"\n{}\nend tell", clicks)
+}
+
+/// Double tap at coordinates; `interval_ms` is the gap between taps (see DEFAULT_DOUBLE_TAP_INTERVAL_MS)
+pub fn double_tap(x: i32, y: i32, interval_ms: u32, simulator: Option<&str>) -> Result<()> {
+    let _udid = get_simulator_udid(simulator)?;
+
+    let (sx, sy) = sim_to_screen_coords(x, y, simulator)?;
+    let script = double_tap_script(sx, sy, interval_ms);
+    ensure_simulator_focused(simulator)?;
+
+    let output = Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .context("Failed to double tap via AppleScript")?;
+
+    if !output.status.success() {
+        eprintln!("Warning: AppleScript tap may not work without accessibility permissions");
+    }
+    Ok(())
+}
+
+/// Most taps in any iOS gesture
+const MAX_MULTI_TAP: u32 = 5;
+
+/// AppleScript for `count` clicks at a screen point, `interval_ms` apart
+fn multi_tap_script(sx: i32, sy: i32, count: u32, interval_ms: u32) -> String {
+    let click = format!("    click at {{{}, {}}}", sx, sy);
+    let delay = format!("\n    delay {}\n", interval_ms as f64 / 1000.0);
+    let clicks = vec![click; count as usize].join(&delay);
+    format!("tell application \"System Events\"\n{}\nend tell", clicks)
+}
+
+/// Tap `count` times (1–5) at coordinates, e.g. 3 to select a paragraph;
+/// `interval_ms` is the gap between taps. Two taps go through `double_tap`.
+pub fn multi_tap(x: i32, y: i32, count: u32, interval_ms: u32, simulator: Option<&str>) -> Result<()> {
+    if !(1..=MAX_MULTI_TAP).contains(&count) {
+        bail!("Invalid tap count {}: must be between 1 and {}", count, MAX_MULTI_TAP);
+    }
+    if count == 2 {
+        return double_tap(x, y, interval_ms, simulator);
+    }
+    let _udid = get_simulator_udid(simulator)?;
+
+    let (sx, sy) = sim_to_screen_coords(x, y, simulator)?;
+    let script = multi_tap_script(sx, sy, count, interval_ms);
+    ensure_simulator_focused(simulator)?;
+
+    let output = Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .context("Failed to multi-tap via AppleScript")?;
 
-    println!("Tapped at ({}, {})", x, y);
+    if !output.status.success() {
+        eprintln!("Warning: AppleScript tap may not work without accessibility permissions");
+    }
     Ok(())
 }
 
+/// Semantic direction of a swipe, derived from its displacement vector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+    DiagonalUpLeft,
+    DiagonalUpRight,
+    DiagonalDownLeft,
+    DiagonalDownRight,
+}
+
+impl std::fmt::Display for SwipeDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SwipeDirection::Up => "up",
+            SwipeDirection::Down => "down",
+            SwipeDirection::Left => "left",
+            SwipeDirection::Right => "right",
+            SwipeDirection::DiagonalUpLeft => "diagonally up-left",
+            SwipeDirection::DiagonalUpRight => "diagonally up-right",
+            SwipeDirection::DiagonalDownLeft => "diagonally down-left",
+            SwipeDirection::DiagonalDownRight => "diagonally down-right",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Classify a swipe by its dominant axis; it is diagonal when the secondary axis
+/// moves at least half as far as the dominant one. Zero-length swipes count as Right.
+pub fn classify_swipe(x1: i32, y1: i32, x2: i32, y2: i32) -> SwipeDirection {
+    let dx = (x2 - x1) as i64;
+    let dy = (y2 - y1) as i64;
+    let (adx, ady) = (dx.abs(), dy.abs());
+
+    let horizontal = adx >= ady;
+    let diagonal = if horizontal { ady * 2 >= adx && ady > 0 } else { adx * 2 >= ady && adx > 0 };
+
+    match (diagonal, horizontal, dx >= 0, dy >= 0) {
+        (true, _, false, false) => SwipeDirection::DiagonalUpLeft,
+        (true, _, true, false) => SwipeDirection::DiagonalUpRight,
+        (true, _, false, true) => SwipeDirection::DiagonalDownLeft,
+        (true, _, true, true) => SwipeDirection::DiagonalDownRight,
+        (false, true, true, _) => SwipeDirection::Right,
+        (false, true, false, _) => SwipeDirection::Left,
+        (false, false, _, true) => SwipeDirection::Down,
+        (false, false, _, false) => SwipeDirection::Up,
+    }
+}
+
 /// Swipe gesture via AppleScript drag
 pub fn swipe(x1: i32, y1: i32, x2: i32, y2: i32, duration: u32, simulator: Option<&str>) -> Result<()> {
     let _udid = get_simulator_udid(simulator)?;
@@ -222,16 +849,10 @@ pub fn swipe(x1: i32, y1: i32, x2: i32, y2: i32, duration: u32, simulator: Optio
     let (sx1, sy1) = sim_to_screen_coords(x1, y1, simulator)?;
     let (sx2, sy2) = sim_to_screen_coords(x2, y2, simulator)?;
     let dur_sec = (duration as f64 / 1000.0).max(0.1);
+    ensure_simulator_focused(simulator)?;
 
     // Use cliclick if available for reliable drag, otherwise AppleScript
-    let cliclick = Command::new("which").arg("cliclick").output();
-    if cliclick.is_ok() && cliclick.unwrap().status.success() {
-        let script = format!(
-            r#"tell application "Simulator" to activate
-delay 0.2"#
-        );
-        let _ = Command::new("osascript").args(["-e", &script]).output();
-
+    if has_cliclick() {
         let _ = Command::new("cliclick")
             .args([
                 &format!("dd:{},{}", sx1, sy1),
@@ -241,9 +862,7 @@ delay 0.2"#
             .output();
     } else {
         let script = format!(
-            r#"tell application "Simulator" to activate
-delay 0.2
-tell application "System Events"
+            r#"tell application "System Events"
     -- Click start point, drag to end point
     click at {{{sx1}, {sy1}}}
     delay {dur_sec}
@@ -252,80 +871,462 @@ end tell"#,
         );
         let _ = Command::new("osascript").args(["-e", &script]).output();
     }
-
-    println!("Swiped from ({}, {}) to ({}, {})", x1, y1, x2, y2);
     Ok(())
 }
 
-/// Input text (safe - uses simctl directly)
-pub fn input_text(text: &str, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+/// Direction to scroll content; `Down` reveals what is below the fold
+/// (the finger moves up)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
 
-    // Try simctl io type first
-    let output = simctl_exec(&["io", &udid, "type", text]);
+/// Fraction of the screen a single scroll swipe covers
+const SCROLL_SWIPE_FRACTION: f64 = 0.8;
+
+/// Swipe endpoints (x1, y1, x2, y2) for one scroll step on a `width`x`height` screen
+fn scroll_swipe(direction: ScrollDirection, width: u32, height: u32) -> (i32, i32, i32, i32) {
+    let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+    let half_dx = width as f64 * SCROLL_SWIPE_FRACTION / 2.0;
+    let half_dy = height as f64 * SCROLL_SWIPE_FRACTION / 2.0;
+
+    let (x1, y1, x2, y2) = match direction {
+        ScrollDirection::Down => (cx, cy + half_dy, cx, cy - half_dy),
+        ScrollDirection::Up => (cx, cy - half_dy, cx, cy + half_dy),
+        ScrollDirection::Right => (cx + half_dx, cy, cx - half_dx, cy),
+        ScrollDirection::Left => (cx - half_dx, cy, cx + half_dx, cy),
+    };
+    (x1.round() as i32, y1.round() as i32, x2.round() as i32, y2.round() as i32)
+}
 
-    if let Ok(out) = output {
-        if out.status.success() {
-            println!("Input text: {}", text);
-            return Ok(());
+/// Scroll in `direction` until an element matching `query` is on screen,
+/// swiping at most `max_swipes` times; returns its screen coordinates
+pub fn scroll_to_element(query: &str, direction: ScrollDirection, max_swipes: u32, simulator: Option<&str>) -> Result<(i32, i32)> {
+    let (width, height) = screen_dimensions(simulator)?;
+    let (x1, y1, x2, y2) = scroll_swipe(direction, width, height);
+
+    for attempt in 0..=max_swipes {
+        if let Some(elem) = match_element(&get_accessibility_elements()?, query) {
+            let (x, y) = element_center(elem);
+            return Ok((x, y));
+        }
+        if attempt < max_swipes {
+            swipe(x1, y1, x2, y2, 300, simulator)?;
+            // Let scroll deceleration settle before reading the tree again
+            std::thread::sleep(std::time::Duration::from_millis(500));
         }
     }
 
-    // Fallback: use pbcopy + paste (safe, no shell injection)
-    let temp_path = "/tmp/ios_input_text.txt";
-    std::fs::write(temp_path, text)?;
+    bail!("Element '{}' not found after {} swipe(s) {:?}", query, max_swipes, direction)
+}
 
-    Command::new("sh")
-        .args(["-c", &format!("cat '{}' | pbcopy", temp_path)])
-        .output()?;
+/// Duration of the pull-to-refresh drag
+const REFRESH_SWIPE_MS: u32 = 300;
 
-    std::fs::remove_file(temp_path).ok();
+/// Pull-to-refresh swipe (x1, y1, x2, y2) within the content rect (x, y, width, height):
+/// starts 30% down and pulls down by 40% of its height
+fn refresh_swipe((x, y, width, height): (i32, i32, i32, i32)) -> (i32, i32, i32, i32) {
+    let cx = x + width / 2;
+    let start_y = y + (height as f64 * 0.3).round() as i32;
+    let end_y = start_y + (height as f64 * 0.4).round() as i32;
+    (cx, start_y, cx, end_y)
+}
 
-    // Simulate Cmd+V paste
-    let script = r#"tell application "System Events"
-        keystroke "v" using command down
-    end tell"#;
+/// Pull to refresh, over the whole screen or within `container_rect`
+/// (x, y, width, height) to target a specific scroll view
+pub fn swipe_to_refresh(container_rect: Option<(i32, i32, i32, i32)>, simulator: Option<&str>) -> Result<()> {
+    let rect = match container_rect {
+        Some(rect) => rect,
+        None => {
+            let (width, height) = screen_dimensions(simulator)?;
+            (0, 0, width as i32, height as i32)
+        }
+    };
+    let (x1, y1, x2, y2) = refresh_swipe(rect);
+    swipe(x1, y1, x2, y2, REFRESH_SWIPE_MS, simulator)
+}
 
-    Command::new("osascript")
-        .args(["-e", script])
-        .output()?;
+/// System overlay pulled down from the top edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TopEdgePanel {
+    NotificationCenter,
+    ControlCenter,
+}
 
-    println!("Input text (via paste): {}", text);
-    Ok(())
+/// Swipe (x1, y1, x2, y2) that opens `panel` on a `width`x`height` screen, or closes it
+/// when `dismiss` is set: Notification Center is pulled from the top center, Control
+/// Center from the top-right corner
+fn top_edge_swipe(panel: TopEdgePanel, dismiss: bool, width: u32, height: u32) -> (i32, i32, i32, i32) {
+    let x_frac = match panel {
+        TopEdgePanel::NotificationCenter => 0.5,
+        TopEdgePanel::ControlCenter => 0.9,
+    };
+    let x = (width as f64 * x_frac).round() as i32;
+    // Start a hair below the edge so the touch lands on screen
+    let top = (height as f64 * 0.005).round().max(1.0) as i32;
+    let bottom = (height as f64 * 0.6).round() as i32;
+    if dismiss { (x, bottom, x, top) } else { (x, top, x, bottom) }
 }
 
-/// Press a key/button
-pub fn press_key(key: &str, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+fn swipe_top_edge(panel: TopEdgePanel, dismiss: bool, simulator: Option<&str>) -> Result<()> {
+    let (width, height) = screen_dimensions(simulator)?;
+    let (x1, y1, x2, y2) = top_edge_swipe(panel, dismiss, width, height);
+    swipe(x1, y1, x2, y2, 300, simulator)
+}
 
-    match key.to_lowercase().as_str() {
-        "home" => {
-            // Simulator shortcut: Cmd+Shift+H
-            let script = r#"tell application "Simulator" to activate
-            delay 0.3
-            tell application "System Events" to key code 4 using {command down, shift down}"#;
-            let output = Command::new("osascript")
-                .args(["-e", script])
-                .output()
-                .context("Failed to press Home via AppleScript")?;
-            if !output.status.success() {
-                let _ = simctl_exec(&["spawn", &udid, "notifyutil", "-p", "com.apple.springboard.home"]);
-            }
-        }
+/// Open Notification Center (swipe down from the top center)
+pub fn open_notification_center(simulator: Option<&str>) -> Result<()> {
+    swipe_top_edge(TopEdgePanel::NotificationCenter, false, simulator)
+}
+
+/// Close Notification Center (swipe back up)
+pub fn dismiss_notification_center(simulator: Option<&str>) -> Result<()> {
+    swipe_top_edge(TopEdgePanel::NotificationCenter, true, simulator)
+}
+
+/// Open Control Center (swipe down from the top-right corner, as on Face ID devices)
+pub fn open_control_center(simulator: Option<&str>) -> Result<()> {
+    swipe_top_edge(TopEdgePanel::ControlCenter, false, simulator)
+}
+
+/// Close Control Center (swipe back up)
+pub fn dismiss_control_center(simulator: Option<&str>) -> Result<()> {
+    swipe_top_edge(TopEdgePanel::ControlCenter, true, simulator)
+}
+
+/// Screen edge an edge swipe starts from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Distance from the edge, in simulator pixels, where edge swipes start (inside the system gesture zone)
+const EDGE_INSET_PX: i32 = 3;
+
+/// Swipe (x1, y1, x2, y2) starting at `edge` and travelling `distance_pct` of the
+/// screen dimension along that axis, centered on the other axis
+fn edge_swipe(edge: Edge, distance_pct: f64, width: u32, height: u32) -> Result<(i32, i32, i32, i32)> {
+    if !(distance_pct > 0.0 && distance_pct <= 1.0) {
+        bail!("Invalid edge swipe distance {}: must be between 0.0 (exclusive) and 1.0", distance_pct);
+    }
+    let (w, h) = (width as i32, height as i32);
+    let dx = (width as f64 * distance_pct).round() as i32;
+    let dy = (height as f64 * distance_pct).round() as i32;
+    let (cx, cy) = (w / 2, h / 2);
+
+    Ok(match edge {
+        Edge::Left => (EDGE_INSET_PX, cy, (EDGE_INSET_PX + dx).min(w - 1), cy),
+        Edge::Right => (w - 1 - EDGE_INSET_PX, cy, (w - 1 - EDGE_INSET_PX - dx).max(0), cy),
+        Edge::Top => (cx, EDGE_INSET_PX, cx, (EDGE_INSET_PX + dy).min(h - 1)),
+        Edge::Bottom => (cx, h - 1 - EDGE_INSET_PX, cx, (h - 1 - EDGE_INSET_PX - dy).max(0)),
+    })
+}
+
+/// Swipe in from a screen edge, e.g. `Edge::Left` for back navigation or
+/// `Edge::Bottom` to go home. `distance_pct` (0.0–1.0) is relative to the
+/// screen dimension; a short `duration_ms` reads as a fling, a long one as a drag.
+pub fn swipe_from_edge(edge: Edge, distance_pct: f64, duration_ms: u32, simulator: Option<&str>) -> Result<()> {
+    let (width, height) = screen_dimensions(simulator)?;
+    let (x1, y1, x2, y2) = edge_swipe(edge, distance_pct, width, height)?;
+    swipe(x1, y1, x2, y2, duration_ms, simulator)
+}
+
+/// Starting half-distance between the two pinch fingers, in simulator pixels
+const PINCH_BASE_RADIUS: f64 = 200.0;
+
+/// Number of intermediate drag events for multi-touch gestures
+const GESTURE_STEPS: u32 = 10;
+
+/// Start and end point of a single finger during a gesture
+type FingerPath = ((i32, i32), (i32, i32));
+
+/// Compute finger start/end points for a horizontal pinch centered at (cx, cy)
+/// Returns (finger1, finger2)
+fn pinch_points(cx: i32, cy: i32, scale: f64) -> (FingerPath, FingerPath) {
+    let start = PINCH_BASE_RADIUS;
+    let end = PINCH_BASE_RADIUS * scale;
+    let at = |r: f64| ((cx as f64 + r).round() as i32, cy);
+    let mirrored = |r: f64| ((cx as f64 - r).round() as i32, cy);
+    ((at(start), at(end)), (mirrored(start), mirrored(end)))
+}
+
+/// Build the cliclick event list for a two-finger gesture
+/// Simulator renders the second finger by mirroring the Option-dragged pointer, so the pair
+/// is first re-centered on `center` with Option+Shift, then finger one is dragged along `path`
+fn two_finger_cliclick_args(screen_center: (i32, i32), center: (i32, i32), path: &[(i32, i32)], step_wait_ms: u32) -> Vec<String> {
+    let mut args = vec![
+        format!("m:{},{}", screen_center.0, screen_center.1),
+        "kd:alt,shift".to_string(),
+        format!("m:{},{}", center.0, center.1),
+        "ku:shift".to_string(),
+    ];
+    if let Some((first, rest)) = path.split_first() {
+        args.push(format!("m:{},{}", first.0, first.1));
+        args.push(format!("dd:{},{}", first.0, first.1));
+        for p in rest {
+            args.push(format!("w:{}", step_wait_ms));
+            args.push(format!("dm:{},{}", p.0, p.1));
+        }
+        let last = path.last().unwrap_or(first);
+        args.push(format!("du:{},{}", last.0, last.1));
+    }
+    args.push("ku:alt".to_string());
+    args
+}
+
+/// Two-finger pinch centered at (cx, cy); scale > 1.0 zooms in, scale < 1.0 zooms out
+/// Requires cliclick (brew install cliclick) since AppleScript cannot hold the mouse button
+pub fn pinch(cx: i32, cy: i32, scale: f64, duration: u32, simulator: Option<&str>) -> Result<()> {
+    if !scale.is_finite() || scale <= 0.0 {
+        bail!("Invalid pinch scale {}: must be a positive, non-zero number", scale);
+    }
+    let _udid = get_simulator_udid(simulator)?;
+
+    if !has_cliclick() {
+        bail!("pinch requires cliclick for multi-touch drag events (brew install cliclick)");
+    }
+
+    let transform = screen_transform(simulator)?;
+    let (sim_w, sim_h) = screen_dimensions(simulator)?;
+    let screen_center = transform.apply(sim_w as i32 / 2, sim_h as i32 / 2);
+
+    // Finger two is mirrored across (cx, cy) by Simulator itself
+    let ((f1_start, f1_end), _) = pinch_points(cx, cy, scale);
+
+    let mut path = vec![transform.apply(f1_start.0, f1_start.1)];
+    path.extend(
+        interpolate_points(f1_start, f1_end, GESTURE_STEPS)
+            .into_iter()
+            .map(|(x, y)| transform.apply(x, y)),
+    );
+
+    let args = two_finger_cliclick_args(
+        screen_center,
+        transform.apply(cx, cy),
+        &path,
+        duration / GESTURE_STEPS,
+    );
+
+    ensure_simulator_focused(simulator)?;
+
+    let output = Command::new("cliclick")
+        .args(&args)
+        .output()
+        .context("Failed to run cliclick for pinch")?;
+    if !output.status.success() {
+        bail!("cliclick pinch failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Angle increment between rotate gesture samples, in degrees
+const ROTATE_STEP_DEGREES: f64 = 5.0;
+
+/// Sample both finger positions on a circle of `radius` around (cx, cy) while sweeping
+/// `degrees` (positive = clockwise on screen). Finger one starts at 3 o'clock, finger two at 9 o'clock.
+/// Returns one (finger1, finger2) pair per step, including the starting position.
+fn rotate_points(cx: i32, cy: i32, degrees: f64, radius: i32, steps: u32) -> Vec<((i32, i32), (i32, i32))> {
+    let steps = steps.max(1);
+    let r = radius as f64;
+    (0..=steps)
+        .map(|i| {
+            // Screen Y grows downward, so increasing the angle is clockwise
+            let angle = (degrees * i as f64 / steps as f64).to_radians();
+            let dx = r * angle.cos();
+            let dy = r * angle.sin();
+            (
+                ((cx as f64 + dx).round() as i32, (cy as f64 + dy).round() as i32),
+                ((cx as f64 - dx).round() as i32, (cy as f64 - dy).round() as i32),
+            )
+        })
+        .collect()
+}
+
+/// Two-finger rotate around (cx, cy); positive degrees rotate clockwise
+pub fn rotate(cx: i32, cy: i32, degrees: f64, radius: i32, duration: u32, simulator: Option<&str>) -> Result<()> {
+    if !degrees.is_finite() {
+        bail!("Invalid rotation {}: degrees must be a finite number", degrees);
+    }
+    if radius <= 0 {
+        bail!("Invalid rotate radius {}: must be positive", radius);
+    }
+    let _udid = get_simulator_udid(simulator)?;
+
+    let steps = ((degrees.abs() / ROTATE_STEP_DEGREES).ceil() as u32).max(1);
+    let transform = screen_transform(simulator)?;
+    let (sim_w, sim_h) = screen_dimensions(simulator)?;
+
+    // Both fingers are converted at every step; Simulator draws finger two itself
+    // (mirrored across the center), so only finger one's path is sent as events
+    let screen_steps: Vec<((i32, i32), (i32, i32))> = rotate_points(cx, cy, degrees, radius, steps)
+        .into_iter()
+        .map(|(f1, f2)| (transform.apply(f1.0, f1.1), transform.apply(f2.0, f2.1)))
+        .collect();
+    let path: Vec<(i32, i32)> = screen_steps.iter().map(|(f1, _)| *f1).collect();
+    let step_wait_ms = duration / steps;
+
+    ensure_simulator_focused(simulator)?;
+
+    if has_cliclick() {
+        let args = two_finger_cliclick_args(
+            transform.apply(sim_w as i32 / 2, sim_h as i32 / 2),
+            transform.apply(cx, cy),
+            &path,
+            step_wait_ms,
+        );
+        let output = Command::new("cliclick")
+            .args(&args)
+            .output()
+            .context("Failed to run cliclick for rotate")?;
+        if !output.status.success() {
+            bail!("cliclick rotate failed: {}", String::from_utf8_lossy(&output.stderr));
+        }
+    } else {
+        // Fallback: Option-held click chain along finger one's arc
+        let delay_sec = step_wait_ms as f64 / 1000.0;
+        let clicks: Vec<String> = path
+            .iter()
+            .map(|(x, y)| format!("    click at {{{}, {}}}\n    delay {}", x, y, delay_sec))
+            .collect();
+        let script = format!(
+            "tell application \"System Events\"\n    key down option\n{}\n    key up option\nend tell",
+            clicks.join("\n")
+        );
+        let _ = Command::new("osascript").args(["-e", &script]).output();
+    }
+    Ok(())
+}
+
+/// Convert fractional screen coordinates (0.0–1.0) to pixels on a `width`x`height` screen
+fn percent_to_pixels(x_pct: f64, y_pct: f64, width: u32, height: u32) -> Result<(i32, i32)> {
+    for (axis, pct) in [("x", x_pct), ("y", y_pct)] {
+        if !(0.0..=1.0).contains(&pct) {
+            bail!("Invalid {} percentage {}: must be between 0.0 and 1.0", axis, pct);
+        }
+    }
+    Ok(((x_pct * width as f64).round() as i32, (y_pct * height as f64).round() as i32))
+}
+
+/// Tap at a point given as fractions of the screen size, independent of device resolution
+pub fn tap_percent(x_pct: f64, y_pct: f64, simulator: Option<&str>) -> Result<()> {
+    let (width, height) = screen_dimensions(simulator)?;
+    let (x, y) = percent_to_pixels(x_pct, y_pct, width, height)?;
+    tap(x, y, simulator)
+}
+
+/// Long press at a point given as fractions of the screen size
+pub fn long_press_percent(x_pct: f64, y_pct: f64, duration: u32, simulator: Option<&str>) -> Result<()> {
+    let (width, height) = screen_dimensions(simulator)?;
+    let (x, y) = percent_to_pixels(x_pct, y_pct, width, height)?;
+    long_press(x, y, duration, simulator)
+}
+
+/// Swipe between points given as fractions of the screen size
+pub fn swipe_percent(x1_pct: f64, y1_pct: f64, x2_pct: f64, y2_pct: f64, duration: u32, simulator: Option<&str>) -> Result<()> {
+    let (width, height) = screen_dimensions(simulator)?;
+    let (x1, y1) = percent_to_pixels(x1_pct, y1_pct, width, height)?;
+    let (x2, y2) = percent_to_pixels(x2_pct, y2_pct, width, height)?;
+    swipe(x1, y1, x2, y2, duration, simulator)
+}
+
+/// Pinch around a center given as fractions of the screen size
+pub fn pinch_percent(cx_pct: f64, cy_pct: f64, scale: f64, duration: u32, simulator: Option<&str>) -> Result<()> {
+    let (width, height) = screen_dimensions(simulator)?;
+    let (cx, cy) = percent_to_pixels(cx_pct, cy_pct, width, height)?;
+    pinch(cx, cy, scale, duration, simulator)
+}
+
+/// Input text (safe - uses simctl directly)
+pub fn input_text(text: &str, clear_first: bool, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+    ensure_simulator_focused(simulator)?;
+
+    if clear_first {
+        clear_text_field(simulator)?;
+    }
+
+    // Try simctl io type first
+    let output = simctl_exec(&["io", &udid, "type", text]);
+
+    if let Ok(out) = output {
+        if out.status.success() {
+            return Ok(());
+        }
+    }
+
+    // Fallback: use pbcopy + paste (safe, text goes through stdin)
+    pipe_to_command("pbcopy", &[], text)?;
+
+    // Simulate Cmd+V paste
+    let script = r#"tell application "System Events"
+        keystroke "v" using command down
+    end tell"#;
+
+    Command::new("osascript")
+        .args(["-e", script])
+        .output()?;
+    Ok(())
+}
+
+/// Erase the focused text field: select all (Cmd+A), then delete
+pub fn clear_text_field(simulator: Option<&str>) -> Result<()> {
+    let _udid = get_simulator_udid(simulator)?;
+    ensure_simulator_focused(simulator)?;
+
+    let script = r#"tell application "System Events"
+        keystroke "a" using command down
+        delay 0.1
+        key code 51
+    end tell"#;
+
+    let output = Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .context("Failed to clear text field via AppleScript")?;
+    if !output.status.success() {
+        bail!("Failed to clear text field: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Replace the focused field's contents with `new_text`
+pub fn replace_text(new_text: &str, simulator: Option<&str>) -> Result<()> {
+    input_text(new_text, true, simulator)
+}
+
+/// Press a key/button
+pub fn press_key(key: &str, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+    ensure_simulator_focused(simulator)?;
+
+    match key.to_lowercase().as_str() {
+        "home" => {
+            // Simulator shortcut: Cmd+Shift+H
+            let script = r#"tell application "System Events" to key code 4 using {command down, shift down}"#;
+            let output = Command::new("osascript")
+                .args(["-e", script])
+                .output()
+                .context("Failed to press Home via AppleScript")?;
+            if !output.status.success() {
+                let _ = simctl_exec(&["spawn", &udid, "notifyutil", "-p", "com.apple.springboard.home"]);
+            }
+        }
         "lock" => {
             // Cmd+L
-            let script = r#"tell application "Simulator" to activate
-            delay 0.1
-            tell application "System Events"
+            let script = r#"tell application "System Events"
                 keystroke "l" using {command down}
             end tell"#;
             let _ = Command::new("osascript").args(["-e", script]).output();
         }
         "shake" => {
             // Cmd+Ctrl+Z
-            let script = r#"tell application "Simulator" to activate
-            delay 0.1
-            tell application "System Events"
+            let script = r#"tell application "System Events"
                 keystroke "z" using {command down, control down}
             end tell"#;
             let _ = Command::new("osascript").args(["-e", script]).output();
@@ -333,23 +1334,113 @@ pub fn press_key(key: &str, simulator: Option<&str>) -> Result<()> {
         _ => {
             let output = simctl_exec(&["io", &udid, "key", key]);
             if output.is_err() || !output.as_ref().unwrap().status.success() {
-                let script = format!(
-                    r#"tell application "Simulator" to activate
-                    delay 0.1
+                // Key is passed as argv, never spliced into the script source
+                let script = r#"on run argv
                     tell application "System Events"
-                        keystroke "{}"
-                    end tell"#,
-                    key
-                );
-                let _ = Command::new("osascript").args(["-e", &script]).output();
+                        keystroke (item 1 of argv)
+                    end tell
+                end run"#;
+                let _ = Command::new("osascript").args(["-e", script, key]).output();
             }
         }
     }
+    Ok(())
+}
+
+/// Modifier key held during a `keyboard_shortcut`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Command,
+    Shift,
+    Option,
+    Control,
+}
+
+impl Modifier {
+    /// AppleScript `using` clause term
+    fn applescript(self) -> &'static str {
+        match self {
+            Modifier::Command => "command down",
+            Modifier::Shift => "shift down",
+            Modifier::Option => "option down",
+            Modifier::Control => "control down",
+        }
+    }
+}
+
+/// macOS virtual key code for named keys that `keystroke` cannot type
+fn named_key_code(key: &str) -> Option<u32> {
+    Some(match key.to_lowercase().as_str() {
+        "return" | "enter" => 36,
+        "tab" => 48,
+        "space" => 49,
+        "delete" | "backspace" => 51,
+        "escape" | "esc" => 53,
+        "left" => 123,
+        "right" => 124,
+        "down" => 125,
+        "up" => 126,
+        _ => return None,
+    })
+}
+
+/// AppleScript (and its argv) pressing `key` with `modifiers` held; single
+/// characters are passed as argv rather than spliced into the script
+fn shortcut_script(key: &str, modifiers: &[Modifier]) -> Result<(String, Vec<String>)> {
+    let using = if modifiers.is_empty() {
+        String::new()
+    } else {
+        let terms: Vec<&str> = modifiers.iter().map(|m| m.applescript()).collect();
+        format!(" using {{{}}}", terms.join(", "))
+    };
+
+    if let Some(code) = named_key_code(key) {
+        let script = format!("tell application \"System Events\" to key code {}{}", code, using);
+        return Ok((script, Vec::new()));
+    }
+    if key.chars().count() != 1 {
+        bail!("Unknown key '{}': use a single character or one of return, tab, space, delete, escape, left, right, up, down", key);
+    }
+
+    let script = format!(
+        "on run argv\n    tell application \"System Events\" to keystroke (item 1 of argv){}\nend run",
+        using
+    );
+    Ok((script, vec![key.to_lowercase()]))
+}
+
+/// Press `key` with `modifiers` held, e.g. `keyboard_shortcut("z", &[Modifier::Command, Modifier::Shift], None)` for redo
+pub fn keyboard_shortcut(key: &str, modifiers: &[Modifier], simulator: Option<&str>) -> Result<()> {
+    let _udid = get_simulator_udid(simulator)?;
+    let (script, argv) = shortcut_script(key, modifiers)?;
+    ensure_simulator_focused(simulator)?;
 
-    println!("Pressed key: {}", key);
+    let output = Command::new("osascript")
+        .args(["-e", &script])
+        .args(&argv)
+        .output()
+        .context("Failed to send keyboard shortcut via AppleScript")?;
+    if !output.status.success() {
+        bail!("Failed to send keyboard shortcut: {}", String::from_utf8_lossy(&output.stderr));
+    }
     Ok(())
 }
 
+/// Select all (Cmd+A)
+pub fn select_all(simulator: Option<&str>) -> Result<()> {
+    keyboard_shortcut("a", &[Modifier::Command], simulator)
+}
+
+/// Undo (Cmd+Z)
+pub fn undo(simulator: Option<&str>) -> Result<()> {
+    keyboard_shortcut("z", &[Modifier::Command], simulator)
+}
+
+/// Redo (Cmd+Shift+Z)
+pub fn redo(simulator: Option<&str>) -> Result<()> {
+    keyboard_shortcut("z", &[Modifier::Command, Modifier::Shift], simulator)
+}
+
 /// UI element from accessibility tree
 #[derive(Serialize, Clone)]
 pub struct UiElement {
@@ -361,540 +1452,7189 @@ pub struct UiElement {
     pub value: String,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub description: String,
+    /// macOS AXIdentifier (or `help` text when unset); usually the app's
+    /// `accessibilityIdentifier`, but not guaranteed to equal `XCUIElement.identifier`
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub identifier: String,
     pub x: i32,
     pub y: i32,
     pub width: i32,
     pub height: i32,
 }
 
-/// Get accessibility tree from Simulator window via AppleScript
-fn get_accessibility_elements() -> Result<Vec<UiElement>> {
-    let script = r#"
-tell application "System Events"
-    tell process "Simulator"
-        set win to front window
-        set allElems to entire contents of win
-        set output to ""
-        set idx to 0
-        repeat with elem in allElems
-            try
-                set elemRole to role of elem
-                set elemTitle to ""
-                try
-                    set elemTitle to title of elem
-                end try
-                set elemValue to ""
-                try
-                    set elemValue to value of elem as string
-                end try
-                set elemDesc to ""
-                try
-                    set elemDesc to description of elem
-                end try
-                set elemPos to position of elem
-                set elemSize to size of elem
-                set posX to item 1 of elemPos
-                set posY to item 2 of elemPos
-                set sW to item 1 of elemSize
-                set sH to item 2 of elemSize
-                set output to output & idx & "|" & elemRole & "|" & elemTitle & "|" & elemValue & "|" & elemDesc & "|" & posX & "," & posY & "|" & sW & "x" & sH & linefeed
-                set idx to idx + 1
-            end try
-        end repeat
-        return output
-    end tell
-end tell
-"#;
-    let output = Command::new("osascript")
-        .args(["-e", script])
-        .output()
-        .context("Failed to get accessibility elements")?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut elements = Vec::new();
+impl UiElement {
+    /// Map the macOS AX role to the closest ARIA/AOM role name
+    fn aom_role(&self) -> String {
+        match self.role.as_str() {
+            "AXButton" => "button".to_string(),
+            "AXLink" => "link".to_string(),
+            "AXTextField" | "AXTextArea" => "textbox".to_string(),
+            "AXCheckBox" | "AXToggleButton" => "checkbox".to_string(),
+            "AXRadioButton" => "radio".to_string(),
+            "AXSlider" => "slider".to_string(),
+            "AXComboBox" => "combobox".to_string(),
+            "AXImage" => "img".to_string(),
+            "AXStaticText" => "text".to_string(),
+            "AXGroup" => "group".to_string(),
+            "AXList" => "list".to_string(),
+            "AXCell" => "cell".to_string(),
+            other => other.trim_start_matches("AX").to_lowercase(),
+        }
+    }
 
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split('|').collect();
-        if parts.len() < 7 { continue; }
-
-        let index: usize = parts[0].parse().unwrap_or(0);
-        let role = parts[1].to_string();
-        let title = if parts[2] == "missing value" { String::new() } else { parts[2].to_string() };
-        let value = if parts[3] == "missing value" { String::new() } else { parts[3].to_string() };
-        let description = if parts[4] == "missing value" { String::new() } else { parts[4].to_string() };
-
-        let pos: Vec<i32> = parts[5].split(',').filter_map(|s| s.trim().parse().ok()).collect();
-        let size: Vec<i32> = parts[6].split('x').filter_map(|s| s.trim().parse().ok()).collect();
-
-        if pos.len() == 2 && size.len() == 2 {
-            elements.push(UiElement {
-                index,
-                role,
-                title,
-                value,
-                description,
-                x: pos[0],
-                y: pos[1],
-                width: size[0],
-                height: size[1],
-            });
+    /// Map the macOS AX role to the XCUIElement.ElementType name XCTest uses
+    fn xcui_type(&self) -> String {
+        match self.role.as_str() {
+            "AXTextArea" => "TextView".to_string(),
+            "AXCheckBox" | "AXToggleButton" => "Switch".to_string(),
+            "AXScrollArea" => "ScrollView".to_string(),
+            "AXGroup" | "AXUnknown" => "Other".to_string(),
+            "AXHeading" => "StaticText".to_string(),
+            other => other.trim_start_matches("AX").to_string(),
         }
     }
 
-    Ok(elements)
+    /// Serialize to an Accessibility Object Model style JSON object
+    pub fn to_report_json(&self) -> serde_json::Value {
+        let name = if !self.title.is_empty() { &self.title } else { &self.description };
+        serde_json::json!({
+            "role": self.aom_role(),
+            "name": name,
+            "description": self.description,
+            "value": self.value,
+            "bounds": {
+                "x": self.x,
+                "y": self.y,
+                "width": self.width,
+                "height": self.height,
+            },
+        })
+    }
 }
 
-/// Dump UI hierarchy via Accessibility
-pub fn ui_dump(format: &str, _simulator: Option<&str>) -> Result<()> {
-    let elements = get_accessibility_elements()?;
+/// Wrap elements into a versioned accessibility report for external tools
+pub fn elements_to_report(elements: &[UiElement]) -> serde_json::Value {
+    serde_json::json!({
+        "version": "1.0",
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "device_info": {
+            "platform": "ios",
+            "source": "simulator-accessibility",
+            "element_count": elements.len(),
+        },
+        "elements": elements.iter().map(UiElement::to_report_json).collect::<Vec<_>>(),
+    })
+}
 
-    if elements.is_empty() {
-        println!("No UI elements found. Ensure Simulator is in foreground.");
-        return Ok(());
-    }
+/// Name of the running executable, for permission guidance messages
+fn current_executable_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "claude-in-mobile".to_string())
+}
 
-    if format == "json" {
-        println!("{}", serde_json::to_string_pretty(&elements)?);
-    } else {
-        for elem in &elements {
-            let label = if !elem.title.is_empty() {
-                &elem.title
-            } else if !elem.description.is_empty() {
-                &elem.description
-            } else if !elem.value.is_empty() {
-                &elem.value
-            } else {
-                ""
-            };
-            println!("[{}] {} \"{}\" ({},{} {}x{})",
-                elem.index, elem.role, label,
-                elem.x, elem.y, elem.width, elem.height);
-        }
+/// Run a one-line Swift snippet and report whether it printed "true"
+/// Returns None when the check cannot be performed (e.g. no Swift toolchain)
+fn swift_bool_check(snippet: &str) -> Option<bool> {
+    let output = Command::new("swift").args(["-e", snippet]).output().ok()?;
+    if !output.status.success() {
+        return None;
     }
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
 
+/// Check that the host process is trusted for Accessibility (needed for UI queries and clicks)
+pub fn check_accessibility_permission() -> Result<()> {
+    let trusted = swift_bool_check("import ApplicationServices; print(AXIsProcessTrusted())");
+    if trusted == Some(false) {
+        bail!(
+            "Accessibility permission is not granted.\n\
+             Open System Settings > Privacy & Security > Accessibility and enable access for \
+             '{}' (or the terminal app that runs it), then restart the terminal.",
+            current_executable_name()
+        );
+    }
     Ok(())
 }
 
-#[derive(Serialize)]
-pub struct Simulator {
-    pub name: String,
-    pub udid: String,
-    pub state: String,
-    pub runtime: String,
+/// Check Screen Recording permission (separate from Accessibility on macOS 11+)
+pub fn check_screen_capture_permission() -> Result<()> {
+    let granted = swift_bool_check("import CoreGraphics; print(CGPreflightScreenCaptureAccess())");
+    if granted == Some(false) {
+        bail!(
+            "Screen Recording permission is not granted.\n\
+             Open System Settings > Privacy & Security > Screen Recording and enable access for \
+             '{}' (or the terminal app that runs it), then restart the terminal.",
+            current_executable_name()
+        );
+    }
+    Ok(())
 }
 
-/// List simulators
-pub fn list_devices() -> Result<Vec<Simulator>> {
-    let output = simctl_exec(&["list", "devices", "-j"])?;
+static ACCESSIBILITY_CHECK: std::sync::Once = std::sync::Once::new();
 
-    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
-    let mut simulators = Vec::new();
+/// UiElement together with the elements nested inside it
+#[derive(Serialize, Clone)]
+pub struct UiElementNode {
+    pub element: UiElement,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<UiElementNode>,
+}
 
-    if let Some(devices) = json["devices"].as_object() {
-        for (runtime, device_list) in devices {
-            if let Some(devices) = device_list.as_array() {
-                for device in devices {
-                    let state = device["state"].as_str().unwrap_or("Unknown");
-                    if device["isAvailable"].as_bool().unwrap_or(false) {
-                        simulators.push(Simulator {
-                            name: device["name"].as_str().unwrap_or("Unknown").to_string(),
-                            udid: device["udid"].as_str().unwrap_or("").to_string(),
-                            state: state.to_string(),
-                            runtime: runtime.replace("com.apple.CoreSimulator.SimRuntime.", ""),
-                        });
-                    }
-                }
-            }
-        }
+impl UiElementNode {
+    /// This element followed by all descendants in depth-first order
+    pub fn flatten(&self) -> Vec<UiElement> {
+        let mut out = Vec::new();
+        self.collect_into(&mut out);
+        out
     }
 
-    simulators.sort_by(|a, b| {
-        if a.state == "Booted" && b.state != "Booted" {
-            std::cmp::Ordering::Less
-        } else if a.state != "Booted" && b.state == "Booted" {
-            std::cmp::Ordering::Greater
-        } else {
-            a.name.cmp(&b.name)
+    fn collect_into(&self, out: &mut Vec<UiElement>) {
+        out.push(self.element.clone());
+        for child in &self.children {
+            child.collect_into(out);
         }
-    });
+    }
+}
 
-    Ok(simulators)
+/// Flatten a forest of nodes in depth-first order
+pub fn flatten_tree(nodes: &[UiElementNode]) -> Vec<UiElement> {
+    nodes.iter().flat_map(UiElementNode::flatten).collect()
 }
 
-/// Print devices list
-pub fn print_devices() -> Result<()> {
-    let simulators = list_devices()?;
-    println!("iOS Simulators:");
-    println!("{}", serde_json::to_string_pretty(&simulators)?);
-    Ok(())
+/// Accessibility hierarchy of the Simulator window (top-level elements first)
+pub fn get_ui_tree(_simulator: Option<&str>) -> Result<Vec<UiElementNode>> {
+    get_accessibility_tree()
 }
 
-/// List installed apps
-pub fn list_apps(filter: Option<&str>, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+/// Get accessibility elements from the Simulator window as a flat list
+fn get_accessibility_elements() -> Result<Vec<UiElement>> {
+    Ok(flatten_tree(&get_accessibility_tree()?))
+}
 
-    let output = simctl_exec(&["listapps", &udid])?;
+/// Get accessibility tree from Simulator window via AppleScript
+fn get_accessibility_tree() -> Result<Vec<UiElementNode>> {
+    ACCESSIBILITY_CHECK.call_once(|| {
+        if let Err(e) = check_accessibility_permission() {
+            eprintln!("Warning: {}", e);
+        }
+    });
+
+    // Walks UI elements recursively, prefixing each line with its depth
+    let script = r#"
+property outputText : ""
+property idx : 0
+
+on dumpElement(elem, depth)
+    tell application "System Events"
+        try
+            set elemRole to role of elem
+            set elemTitle to ""
+            try
+                set elemTitle to title of elem
+            end try
+            set elemValue to ""
+            try
+                set elemValue to value of elem as string
+            end try
+            set elemDesc to ""
+            try
+                set elemDesc to description of elem
+            end try
+            set elemId to ""
+            try
+                set elemId to value of attribute "AXIdentifier" of elem
+            end try
+            if elemId is missing value or elemId is "" then
+                try
+                    set elemId to help of elem
+                end try
+            end if
+            set elemPos to position of elem
+            set elemSize to size of elem
+            set posX to item 1 of elemPos
+            set posY to item 2 of elemPos
+            set sW to item 1 of elemSize
+            set sH to item 2 of elemSize
+            set my outputText to (my outputText) & depth & "|" & (my idx) & "|" & elemRole & "|" & elemTitle & "|" & elemValue & "|" & elemDesc & "|" & posX & "," & posY & "|" & sW & "x" & sH & "|" & elemId & linefeed
+            set my idx to (my idx) + 1
+            repeat with child in (UI elements of elem)
+                my dumpElement(child, depth + 1)
+            end repeat
+        end try
+    end tell
+end dumpElement
+
+tell application "System Events"
+    tell process "Simulator"
+        set win to front window
+        repeat with child in (UI elements of win)
+            my dumpElement(child, 0)
+        end repeat
+    end tell
+end tell
+return outputText
+"#;
+    let output = Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .context("Failed to get accessibility elements")?;
+
+    Ok(parse_accessibility_tree(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `depth|idx|role|title|value|description|x,y|WxH|identifier` lines
+/// (depth-first order) back into a tree
+fn parse_accessibility_tree(stdout: &str) -> Vec<UiElementNode> {
+    let mut roots: Vec<UiElementNode> = Vec::new();
+    // Open ancestors of the current line, with their depths
+    let mut stack: Vec<(usize, UiElementNode)> = Vec::new();
+
+    fn close_until(depth: usize, stack: &mut Vec<(usize, UiElementNode)>, roots: &mut Vec<UiElementNode>) {
+        while stack.last().is_some_and(|(d, _)| *d >= depth) {
+            let (_, node) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some((_, parent)) => parent.children.push(node),
+                None => roots.push(node),
+            }
+        }
+    }
+
+    for line in stdout.lines() {
+        let Some((depth, rest)) = line.split_once('|') else { continue };
+        let Ok(depth) = depth.trim().parse::<usize>() else { continue };
+        let Some(element) = parse_element_line(rest) else { continue };
+
+        close_until(depth, &mut stack, &mut roots);
+        stack.push((depth, UiElementNode { element, children: Vec::new() }));
+    }
+    close_until(0, &mut stack, &mut roots);
+
+    roots
+}
+
+/// Parse one `idx|role|title|value|description|x,y|WxH|identifier` record
+fn parse_element_line(line: &str) -> Option<UiElement> {
+    let text = |s: &str| if s == "missing value" { String::new() } else { s.to_string() };
+
+    let parts: Vec<&str> = line.split('|').collect();
+    if parts.len() < 7 { return None; }
+
+    let pos: Vec<i32> = parts[5].split(',').filter_map(|s| s.trim().parse().ok()).collect();
+    let size: Vec<i32> = parts[6].split('x').filter_map(|s| s.trim().parse().ok()).collect();
+    if pos.len() != 2 || size.len() != 2 { return None; }
+
+    Some(UiElement {
+        index: parts[0].parse().unwrap_or(0),
+        role: parts[1].to_string(),
+        title: text(parts[2]),
+        value: text(parts[3]),
+        description: text(parts[4]),
+        identifier: parts.get(7).map(|s| text(s)).unwrap_or_default(),
+        x: pos[0],
+        y: pos[1],
+        width: size[0],
+        height: size[1],
+    })
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Render elements as XCTest UI snapshot XML: one `<XCUIElementSnapshot>` per
+/// element (with `type`, `label`, `value` and `frame` as `{{x, y}, {w, h}}`)
+/// under a root snapshot of the window
+fn elements_to_xcui_xml(elements: &[UiElement]) -> String {
+    // Root frame: bounding box of everything in the window
+    let (x0, y0, x1, y1) = elements.iter()
+        .map(|e| (e.x, e.y, e.x + e.width, e.y + e.height))
+        .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+        .unwrap_or((0, 0, 0, 0));
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<XCUIElementSnapshot type=\"Window\" label=\"Simulator\" value=\"\" frame=\"{{{{{}, {}}}, {{{}, {}}}}}\">\n",
+        x0, y0, x1 - x0, y1 - y0
+    ));
+
+    for elem in elements {
+        let label = if !elem.title.is_empty() { &elem.title } else { &elem.description };
+        xml.push_str(&format!(
+            "  <XCUIElementSnapshot type=\"{}\" label=\"{}\" value=\"{}\" frame=\"{{{{{}, {}}}, {{{}, {}}}}}\"/>\n",
+            xml_escape(&elem.xcui_type()),
+            xml_escape(label),
+            xml_escape(&elem.value),
+            elem.x, elem.y, elem.width, elem.height
+        ));
+    }
+
+    xml.push_str("</XCUIElementSnapshot>\n");
+    xml
+}
+
+/// Export the accessibility tree as XCTest UI snapshot XML
+pub fn export_ui_xml(_simulator: Option<&str>) -> Result<String> {
+    Ok(elements_to_xcui_xml(&get_accessibility_elements()?))
+}
+
+/// Best display label: title, then description, then value
+fn element_label(elem: &UiElement) -> &str {
+    if !elem.title.is_empty() {
+        &elem.title
+    } else if !elem.description.is_empty() {
+        &elem.description
+    } else {
+        &elem.value
+    }
+}
+
+/// Render nodes one per line, indented two spaces per nesting level
+fn format_tree(nodes: &[UiElementNode]) -> String {
+    fn write_node(node: &UiElementNode, depth: usize, out: &mut String) {
+        let e = &node.element;
+        out.push_str(&format!("{}[{}] {} \"{}\" ({},{} {}x{})\n",
+            "  ".repeat(depth), e.index, e.role, element_label(e),
+            e.x, e.y, e.width, e.height));
+        for child in &node.children {
+            write_node(child, depth + 1, out);
+        }
+    }
+
+    let mut out = String::new();
+    for node in nodes {
+        write_node(node, 0, &mut out);
+    }
+    out
+}
+
+/// Dump UI hierarchy via Accessibility in `format` (json, xml, tree, or one line per element);
+/// `None` when no elements are visible
+pub fn ui_dump(format: &str, _simulator: Option<&str>) -> Result<Option<String>> {
+    let tree = get_accessibility_tree()?;
+    let elements = flatten_tree(&tree);
+
+    if elements.is_empty() {
+        return Ok(None);
+    }
+
+    let dump = if format == "tree" {
+        format_tree(&tree)
+    } else if format == "json" {
+        serde_json::to_string_pretty(&elements)? + "\n"
+    } else if format == "xml" {
+        elements_to_xcui_xml(&elements)
+    } else {
+        elements.iter()
+            .map(|elem| format!("[{}] {} \"{}\" ({},{} {}x{})\n",
+                elem.index, elem.role, element_label(elem),
+                elem.x, elem.y, elem.width, elem.height))
+            .collect()
+    };
+
+    Ok(Some(dump))
+}
+
+#[derive(Serialize)]
+pub struct Simulator {
+    pub name: String,
+    pub udid: String,
+    pub state: String,
+    pub runtime: String,
+}
+
+/// List simulators
+pub fn list_devices() -> Result<Vec<Simulator>> {
+    let output = simctl_exec(&["list", "devices", "-j"])?;
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let mut simulators = Vec::new();
+
+    if let Some(devices) = json["devices"].as_object() {
+        for (runtime, device_list) in devices {
+            if let Some(devices) = device_list.as_array() {
+                for device in devices {
+                    let state = device["state"].as_str().unwrap_or("Unknown");
+                    if device["isAvailable"].as_bool().unwrap_or(false) {
+                        simulators.push(Simulator {
+                            name: device["name"].as_str().unwrap_or("Unknown").to_string(),
+                            udid: device["udid"].as_str().unwrap_or("").to_string(),
+                            state: state.to_string(),
+                            runtime: runtime.replace("com.apple.CoreSimulator.SimRuntime.", ""),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    simulators.sort_by(|a, b| {
+        if a.state == "Booted" && b.state != "Booted" {
+            std::cmp::Ordering::Less
+        } else if a.state != "Booted" && b.state == "Booted" {
+            std::cmp::Ordering::Greater
+        } else {
+            a.name.cmp(&b.name)
+        }
+    });
+
+    Ok(simulators)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceType {
+    pub name: String,
+    pub identifier: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Runtime {
+    pub name: String,
+    pub identifier: String,
+    pub version: String,
+    pub is_available: bool,
+}
+
+fn parse_device_types(json: &serde_json::Value) -> Vec<DeviceType> {
+    json["devicetypes"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|t| Some(DeviceType {
+            name: t["name"].as_str()?.to_string(),
+            identifier: t["identifier"].as_str()?.to_string(),
+        }))
+        .collect()
+}
+
+fn parse_runtimes(json: &serde_json::Value) -> Vec<Runtime> {
+    json["runtimes"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| Some(Runtime {
+            name: r["name"].as_str()?.to_string(),
+            identifier: r["identifier"].as_str()?.to_string(),
+            version: r["version"].as_str().unwrap_or("").to_string(),
+            is_available: r["isAvailable"].as_bool().unwrap_or(false),
+        }))
+        .collect()
+}
+
+fn simctl_list_json(kind: &str) -> Result<serde_json::Value> {
+    let output = simctl_exec(&["list", kind, "-j"])?;
+    if !output.status.success() {
+        bail!("Failed to list {}: {}", kind, String::from_utf8_lossy(&output.stderr));
+    }
+    serde_json::from_slice(&output.stdout).with_context(|| format!("Failed to parse simctl {} list", kind))
+}
+
+/// Device types simulators can be created from
+pub fn list_device_types() -> Result<Vec<DeviceType>> {
+    Ok(parse_device_types(&simctl_list_json("devicetypes")?))
+}
+
+/// Installed simulator runtimes
+pub fn list_runtimes() -> Result<Vec<Runtime>> {
+    Ok(parse_runtimes(&simctl_list_json("runtimes")?))
+}
+
+/// Create a simulator and return its UDID
+/// `device_type` and `runtime` accept names ("iPhone 15", "iOS 17.0") or identifiers
+pub fn create_simulator(name: &str, device_type: &str, runtime: &str) -> Result<String> {
+    if name.trim().is_empty() {
+        bail!("Simulator name must not be empty");
+    }
+
+    let output = simctl_exec(&["create", name, device_type, runtime])?;
+    if !output.status.success() {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Failed to create simulator '{}': {}", name, String::from_utf8_lossy(&output.stderr).trim()),
+            "Check available types with `xcrun simctl list devicetypes` and runtimes with `xcrun simctl list runtimes`",
+        ));
+    }
+
+    let udid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(udid)
+}
+
+/// Delete a simulator (the booted one when `simulator` is None)
+pub fn delete_simulator(simulator: Option<&str>) -> Result<()> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+
+    let output = simctl_exec(&["delete", &udid])?;
+    if !output.status.success() {
+        bail!("Failed to delete simulator {}: {}", udid, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+// ============== Device Capabilities ==============
+
+/// Hardware features of a simulated device, for capability gating in CI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CapabilityReport {
+    /// Device type, e.g. "iPhone-15-Pro"
+    pub device_type: String,
+    pub has_face_id: bool,
+    pub has_touch_id: bool,
+    pub has_home_button: bool,
+    pub has_dynamic_island: bool,
+    pub supports_pencil: bool,
+    /// Newest major iOS/iPadOS release the hardware supports
+    pub max_ios_version: String,
+    pub screen_size_inches: f64,
+}
+
+/// Biometric sensor (no simulated device has both)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Biometric {
+    FaceId,
+    TouchId,
+}
+
+/// Known device specs keyed by simulator device type name (prefix of the
+/// full type, so "iPad-Pro-11-inch" covers every generation)
+struct DeviceSpec {
+    device_type: &'static str,
+    biometric: Biometric,
+    home_button: bool,
+    dynamic_island: bool,
+    pencil: bool,
+    max_ios: &'static str,
+    inches: f64,
+}
+
+const fn spec(
+    device_type: &'static str,
+    biometric: Biometric,
+    home_button: bool,
+    dynamic_island: bool,
+    pencil: bool,
+    max_ios: &'static str,
+    inches: f64,
+) -> DeviceSpec {
+    DeviceSpec { device_type, biometric, home_button, dynamic_island, pencil, max_ios, inches }
+}
+
+#[rustfmt::skip]
+const DEVICE_SPECS: &[DeviceSpec] = {
+    use Biometric::*;
+    &[
+        //   type                               biometric home   island pencil max   inches
+        spec("iPhone-8",                         TouchId, true,  false, false, "16", 4.7),
+        spec("iPhone-8-Plus",                    TouchId, true,  false, false, "16", 5.5),
+        spec("iPhone-SE-2nd-generation",         TouchId, true,  false, false, "26", 4.7),
+        spec("iPhone-SE-3rd-generation",         TouchId, true,  false, false, "26", 4.7),
+        spec("iPhone-X",                         FaceId,  false, false, false, "16", 5.8),
+        spec("iPhone-XS",                        FaceId,  false, false, false, "18", 5.8),
+        spec("iPhone-XS-Max",                    FaceId,  false, false, false, "18", 6.5),
+        spec("iPhone-XR",                        FaceId,  false, false, false, "18", 6.1),
+        spec("iPhone-11",                        FaceId,  false, false, false, "26", 6.1),
+        spec("iPhone-11-Pro",                    FaceId,  false, false, false, "26", 5.8),
+        spec("iPhone-11-Pro-Max",                FaceId,  false, false, false, "26", 6.5),
+        spec("iPhone-12-mini",                   FaceId,  false, false, false, "26", 5.4),
+        spec("iPhone-12",                        FaceId,  false, false, false, "26", 6.1),
+        spec("iPhone-12-Pro",                    FaceId,  false, false, false, "26", 6.1),
+        spec("iPhone-12-Pro-Max",                FaceId,  false, false, false, "26", 6.7),
+        spec("iPhone-13-mini",                   FaceId,  false, false, false, "26", 5.4),
+        spec("iPhone-13",                        FaceId,  false, false, false, "26", 6.1),
+        spec("iPhone-13-Pro",                    FaceId,  false, false, false, "26", 6.1),
+        spec("iPhone-13-Pro-Max",                FaceId,  false, false, false, "26", 6.7),
+        spec("iPhone-14",                        FaceId,  false, false, false, "26", 6.1),
+        spec("iPhone-14-Plus",                   FaceId,  false, false, false, "26", 6.7),
+        spec("iPhone-14-Pro",                    FaceId,  false, true,  false, "26", 6.1),
+        spec("iPhone-14-Pro-Max",                FaceId,  false, true,  false, "26", 6.7),
+        spec("iPhone-15",                        FaceId,  false, true,  false, "26", 6.1),
+        spec("iPhone-15-Plus",                   FaceId,  false, true,  false, "26", 6.7),
+        spec("iPhone-15-Pro",                    FaceId,  false, true,  false, "26", 6.1),
+        spec("iPhone-15-Pro-Max",                FaceId,  false, true,  false, "26", 6.7),
+        spec("iPhone-16e",                       FaceId,  false, false, false, "26", 6.1),
+        spec("iPhone-16",                        FaceId,  false, true,  false, "26", 6.1),
+        spec("iPhone-16-Plus",                   FaceId,  false, true,  false, "26", 6.7),
+        spec("iPhone-16-Pro",                    FaceId,  false, true,  false, "26", 6.3),
+        spec("iPhone-16-Pro-Max",                FaceId,  false, true,  false, "26", 6.9),
+        spec("iPad-9th-generation",              TouchId, true,  false, true,  "26", 10.2),
+        spec("iPad-10th-generation",             TouchId, false, false, true,  "26", 10.9),
+        spec("iPad-mini-6th-generation",         TouchId, false, false, true,  "26", 8.3),
+        spec("iPad-Air-5th-generation",          TouchId, false, false, true,  "26", 10.9),
+        spec("iPad-Pro-11-inch",                 FaceId,  false, false, true,  "26", 11.0),
+        spec("iPad-Pro-12-9-inch",               FaceId,  false, false, true,  "26", 12.9),
+    ]
+};
+
+/// Longest table entry that equals `device_type` or prefixes it at a `-` boundary
+fn lookup_device_spec(device_type: &str) -> Option<&'static DeviceSpec> {
+    DEVICE_SPECS.iter()
+        .filter(|spec| match device_type.strip_prefix(spec.device_type) {
+            Some(rest) => rest.is_empty() || rest.starts_with('-'),
+            None => false,
+        })
+        .max_by_key(|spec| spec.device_type.len())
+}
+
+/// Device type (without the CoreSimulator prefix) of a simulator by UDID,
+/// or of the first booted one
+fn simulator_device_type(udid: &str) -> Result<String> {
+    let output = simctl_exec(&["list", "devices", "-j"])?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    let device = json["devices"].as_object()
+        .into_iter()
+        .flat_map(|runtimes| runtimes.values())
+        .filter_map(|list| list.as_array())
+        .flatten()
+        .find(|d| if udid == "booted" {
+            d["state"].as_str() == Some("Booted")
+        } else {
+            d["udid"].as_str() == Some(udid)
+        })
+        .with_context(|| format!("Simulator '{}' not found", udid))?;
+
+    let type_id = device["deviceTypeIdentifier"].as_str()
+        .context("simctl did not report a deviceTypeIdentifier")?;
+    Ok(type_id.trim_start_matches("com.apple.CoreSimulator.SimDeviceType.").to_string())
+}
+
+fn capability_report(device_type: &str) -> Result<CapabilityReport> {
+    let spec = lookup_device_spec(device_type)
+        .with_context(|| format!("No capability data for device type '{}'", device_type))?;
+
+    Ok(CapabilityReport {
+        device_type: device_type.to_string(),
+        has_face_id: spec.biometric == Biometric::FaceId,
+        has_touch_id: spec.biometric == Biometric::TouchId,
+        has_home_button: spec.home_button,
+        has_dynamic_island: spec.dynamic_island,
+        supports_pencil: spec.pencil,
+        max_ios_version: spec.max_ios.to_string(),
+        screen_size_inches: spec.inches,
+    })
+}
+
+/// Hardware capabilities of the simulator, from its device type
+pub fn device_capability_report(simulator: Option<&str>) -> Result<CapabilityReport> {
+    let udid = get_simulator_udid(simulator)?;
+    capability_report(&simulator_device_type(&udid)?)
+}
+
+// ============== Biometrics ==============
+
+const BIOMETRIC_ENROLLMENT_NOTIFICATION: &str = "com.apple.BiometricKit.enrollmentChanged";
+
+impl Biometric {
+    pub fn name(self) -> &'static str {
+        match self {
+            Biometric::FaceId => "Face ID",
+            Biometric::TouchId => "Touch ID",
+        }
+    }
+
+    /// Simulated sensor key in BiometricKit's notification names
+    fn sensor(self) -> &'static str {
+        match self {
+            Biometric::FaceId => "pearl",
+            Biometric::TouchId => "fingerTouch",
+        }
+    }
+}
+
+/// `notifyutil` arguments for a biometric event; `None` toggles enrollment on
+fn biometric_notify_args(biometric: Biometric, outcome: Option<&str>) -> Vec<String> {
+    match outcome {
+        None => vec![
+            "notifyutil".to_string(),
+            "-s".to_string(), BIOMETRIC_ENROLLMENT_NOTIFICATION.to_string(), "1".to_string(),
+            "-p".to_string(), BIOMETRIC_ENROLLMENT_NOTIFICATION.to_string(),
+        ],
+        Some(outcome) => vec![
+            "notifyutil".to_string(),
+            "-p".to_string(),
+            format!("com.apple.BiometricKit_Sim.{}.{}", biometric.sensor(), outcome),
+        ],
+    }
+}
+
+/// Send a biometric event to the simulator. simctl has no biometric subcommand, so this posts
+/// the BiometricKit notifications that Simulator's Features > Face ID/Touch ID menu sends.
+/// The modality used is logged to stderr and returned.
+fn send_biometric(outcome: Option<&str>, simulator: Option<&str>) -> Result<Biometric> {
+    let udid = get_simulator_udid(simulator)?;
+    let device_type = simulator_device_type(&udid)?;
+    let biometric = match lookup_device_spec(&device_type) {
+        Some(spec) => spec.biometric,
+        None => {
+            eprintln!("Warning: unknown device type '{}', assuming Face ID", device_type);
+            Biometric::FaceId
+        }
+    };
+
+    let notify = biometric_notify_args(biometric, outcome);
+    let mut args = vec!["spawn", udid.as_str()];
+    args.extend(notify.iter().map(String::as_str));
+    let output = simctl_exec(&args)?;
+    if !output.status.success() {
+        bail!("Failed to send {} event: {}", biometric.name(), String::from_utf8_lossy(&output.stderr));
+    }
+    eprintln!("{}: {} sent", biometric.name(), outcome.unwrap_or("enrollment"));
+    Ok(biometric)
+}
+
+/// Enroll Face ID / Touch ID on the simulator; returns which one the device has
+pub fn biometric_enroll(simulator: Option<&str>) -> Result<Biometric> {
+    send_biometric(None, simulator)
+}
+
+/// Present a matching face/finger to a pending biometric prompt; returns the modality used
+pub fn biometric_match(simulator: Option<&str>) -> Result<Biometric> {
+    send_biometric(Some("match"), simulator)
+}
+
+/// Present a non-matching face/finger to a pending biometric prompt; returns the modality used
+pub fn biometric_no_match(simulator: Option<&str>) -> Result<Biometric> {
+    send_biometric(Some("nomatch"), simulator)
+}
+
+// ============== Phone Calls ==============
+
+/// Darwin notification SpringBoard observes to present the incoming call UI
+const INCOMING_CALL_NOTIFICATION: &str = "com.apple.springboard.simulateincomingcall";
+
+/// Whether a device type key (e.g. "iPhone-15-Pro") can show the phone call UI
+fn is_phone_capable(device_type: &str) -> bool {
+    device_type.starts_with("iPhone")
+}
+
+/// Present the incoming call UI to test how an app handles the interruption.
+/// Requires an iPhone simulator; iPad device types are rejected.
+pub fn simulate_incoming_call(simulator: Option<&str>) -> Result<()> {
+    let info = get_system_info(simulator)?;
+    if !is_phone_capable(&info.device_type) {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Incoming calls need a phone-capable simulator, '{}' is a {}", info.name, info.device_type),
+            "Boot an iPhone simulator and pass it with --simulator",
+        ));
+    }
+
+    let output = simctl_exec(&["spawn", &info.udid, "notifyutil", "-p", INCOMING_CALL_NOTIFICATION])?;
+    if !output.status.success() {
+        bail!("Failed to simulate incoming call: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Answer the incoming call by tapping its Accept button
+pub fn accept_call(simulator: Option<&str>) -> Result<()> {
+    tap_element("Accept", simulator)
+}
+
+/// Reject the incoming call by tapping its Decline button
+pub fn decline_call(simulator: Option<&str>) -> Result<()> {
+    tap_element("Decline", simulator)
+}
+
+// ============== iPad Multitasking ==============
+
+/// Window arrangement for iPad multitasking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultitaskingLayout {
+    FullScreen,
+    /// Split View with both apps at half width
+    HalfHalf,
+    /// Split View with the primary app at one third
+    OneThirdTwoThird,
+    SlideOver,
+}
+
+/// Vertical position of the ••• multitasking button, as a fraction of screen height
+const MULTITASKING_BUTTON_Y: f64 = 0.02;
+
+/// Fail unless the simulator is an iPad
+fn require_ipad(simulator: Option<&str>) -> Result<()> {
+    let info = get_system_info(simulator)?;
+    if !info.device_type.starts_with("iPad") {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Multitasking needs an iPad simulator, '{}' is a {}", info.name, info.device_type),
+            "Boot an iPad simulator and pass it with --simulator",
+        ));
+    }
+    Ok(())
+}
+
+/// Split View divider drag (x1, y1, x2, y2) from the centered divider to `layout`'s position;
+/// `None` when the layout needs no divider move
+fn divider_drag(layout: MultitaskingLayout, width: u32, height: u32) -> Option<(i32, i32, i32, i32)> {
+    let (cx, cy) = (width as i32 / 2, height as i32 / 2);
+    match layout {
+        MultitaskingLayout::OneThirdTwoThird => Some((cx, cy, width as i32 / 3, cy)),
+        _ => None,
+    }
+}
+
+/// Open the ••• menu at the top of the foreground app and choose `item`
+/// (iPadOS 15+; steadier than dragging Dock icons, whose positions vary)
+fn choose_multitasking_item(item: &str, simulator: Option<&str>) -> Result<()> {
+    let (width, height) = screen_dimensions(simulator)?;
+    tap((width / 2) as i32, (height as f64 * MULTITASKING_BUTTON_Y).round() as i32, simulator)?;
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    tap_element(item, simulator)
+}
+
+/// Put the foreground app into Split View; iPadOS then asks for the second app
+pub fn invoke_split_view(simulator: Option<&str>) -> Result<()> {
+    require_ipad(simulator)?;
+    choose_multitasking_item("Split View", simulator)
+}
+
+/// Bring in the Slide Over window by swiping in from the right edge
+pub fn invoke_slide_over(simulator: Option<&str>) -> Result<()> {
+    require_ipad(simulator)?;
+    swipe_from_edge(Edge::Right, 0.25, 300, simulator)
+}
+
+/// Home Screen label of an installed app, for picking it as the second Split View app
+fn split_view_app_label(bundle_id: &str, simulator: Option<&str>) -> Result<String> {
+    let app = list_apps(None, simulator)?
+        .into_iter()
+        .find(|app| app.bundle_id == bundle_id)
+        .ok_or_else(|| anyhow::anyhow!("App '{}' is not installed", bundle_id))?;
+    if app.display_name.is_empty() {
+        bail!("App '{}' has no display name to find it by on the Home Screen", bundle_id);
+    }
+    Ok(app.display_name)
+}
+
+/// Arrange the foreground app in `layout`
+///
+/// Split View layouts need `secondary_app` (a bundle ID): after "Split View" iPadOS
+/// shows the Home Screen, and the divider only exists once that app has been picked
+/// there. It is ignored for the other layouts.
+pub fn set_multitasking_layout(layout: MultitaskingLayout, secondary_app: Option<&str>, simulator: Option<&str>) -> Result<()> {
+    require_ipad(simulator)?;
+
+    let split = matches!(layout, MultitaskingLayout::HalfHalf | MultitaskingLayout::OneThirdTwoThird);
+    let secondary_label = match (split, secondary_app) {
+        (true, Some(bundle_id)) => Some(split_view_app_label(bundle_id, simulator)?),
+        (true, None) => bail!("{:?} needs a secondary app to share the screen with", layout),
+        (false, _) => None,
+    };
+
+    let item = match layout {
+        MultitaskingLayout::FullScreen => "Full Screen",
+        MultitaskingLayout::HalfHalf | MultitaskingLayout::OneThirdTwoThird => "Split View",
+        MultitaskingLayout::SlideOver => "Slide Over",
+    };
+    choose_multitasking_item(item, simulator)?;
+
+    if let Some(label) = secondary_label {
+        // Wait for the Home Screen app picker, then choose the second app
+        std::thread::sleep(std::time::Duration::from_millis(800));
+        tap_element(&label, simulator)
+            .with_context(|| format!("Could not pick '{}' as the second Split View app", label))?;
+
+        let (width, height) = screen_dimensions(simulator)?;
+        if let Some((x1, y1, x2, y2)) = divider_drag(layout, width, height) {
+            // Give the split animation time to settle before grabbing the divider
+            std::thread::sleep(std::time::Duration::from_millis(800));
+            swipe(x1, y1, x2, y2, 500, simulator)?;
+        }
+    }
+    Ok(())
+}
+
+// ============== Apps ==============
+
+/// An installed app, as reported by `simctl listapps`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct AppInfo {
+    pub bundle_id: String,
+    /// CFBundleDisplayName, falling back to CFBundleName (empty when neither is set)
+    pub display_name: String,
+    /// CFBundleVersion (build number)
+    pub version: String,
+    /// CFBundleShortVersionString (marketing version)
+    pub short_version: String,
+    pub bundle_path: String,
+    pub data_container_path: Option<String>,
+}
+
+/// "file:///path/to/My%20App.app/" → "/path/to/My App.app"; plain paths pass through
+fn file_url_to_path(url: &str) -> String {
+    let decoded = url.starts_with("file://")
+        .then(|| url::Url::parse(url).ok()?.to_file_path().ok())
+        .flatten()
+        .map(|path| path.to_string_lossy().into_owned());
+    let path = decoded.as_deref().unwrap_or(url);
+    if path.len() > 1 { path.trim_end_matches('/').to_string() } else { path.to_string() }
+}
+
+/// String form of a plist string or number; ASCII plists read unquoted
+/// values such as `CFBundleVersion = 318;` as integers
+fn plist_scalar_string(value: &plist::Value) -> Option<String> {
+    match value {
+        plist::Value::String(s) => Some(s.clone()),
+        plist::Value::Integer(i) => Some(i.to_string()),
+        plist::Value::Real(f) => Some(f.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse `simctl listapps` output (an old-style ASCII plist keyed by bundle ID)
+fn parse_installed_apps(stdout: &str) -> Result<Vec<AppInfo>> {
+    let value = plist::Value::from_reader_ascii(stdout.as_bytes())
+        .context("Failed to parse simctl listapps output")?;
+    let dict = value.into_dictionary()
+        .ok_or_else(|| anyhow::anyhow!("simctl listapps output is not a dictionary"))?;
+
+    let apps = dict.into_iter().map(|(bundle_id, app)| {
+        let field = |key: &str| app.as_dictionary()
+            .and_then(|d| d.get(key))
+            .and_then(plist_scalar_string);
+
+        AppInfo {
+            display_name: field("CFBundleDisplayName").or_else(|| field("CFBundleName")).unwrap_or_default(),
+            version: field("CFBundleVersion").unwrap_or_default(),
+            short_version: field("CFBundleShortVersionString").unwrap_or_default(),
+            bundle_path: field("Bundle").or_else(|| field("Path")).map(|p| file_url_to_path(&p)).unwrap_or_default(),
+            data_container_path: field("DataContainer").map(|p| file_url_to_path(&p)),
+            bundle_id,
+        }
+    }).collect();
+
+    Ok(apps)
+}
+
+/// Installed apps, filtered case-insensitively on bundle ID or display
+/// name and sorted by bundle ID
+pub fn list_apps(filter: Option<&str>, simulator: Option<&str>) -> Result<Vec<AppInfo>> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["listapps", &udid])?;
+
+    if !output.status.success() {
+        bail!("simctl listapps failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let mut apps = parse_installed_apps(&String::from_utf8_lossy(&output.stdout))?;
+
+    if let Some(f) = filter {
+        let f_lower = f.to_lowercase();
+        apps.retain(|app| {
+            app.bundle_id.to_lowercase().contains(&f_lower)
+                || app.display_name.to_lowercase().contains(&f_lower)
+        });
+    }
+
+    apps.sort();
+    apps.dedup();
+    Ok(apps)
+}
+
+/// Installed apps as a heading plus one line per app
+pub fn format_app_list(apps: &[AppInfo]) -> String {
+    let mut out = format!("Installed apps ({}):\n", apps.len());
+    for app in apps {
+        if app.display_name.is_empty() {
+            out.push_str(&format!("  {}\n", app.bundle_id));
+        } else {
+            out.push_str(&format!("  {} ({})\n", app.bundle_id, app.display_name));
+        }
+    }
+    out
+}
+
+/// Print installed apps
+pub fn print_apps(filter: Option<&str>, simulator: Option<&str>) -> Result<()> {
+    print!("{}", format_app_list(&list_apps(filter, simulator)?));
+    Ok(())
+}
+
+/// Numeric app version (MAJOR.MINOR[.PATCH]) that compares component-wise, so 10.0 > 9.0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct AppVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl std::str::FromStr for AppVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        if !(2..=3).contains(&parts.len()) {
+            bail!("Invalid version '{}': expected MAJOR.MINOR or MAJOR.MINOR.PATCH", s);
+        }
+        let num = |p: &str| -> Result<u64> {
+            p.parse().with_context(|| format!("Invalid version '{}': '{}' is not a number", s, p))
+        };
+        Ok(AppVersion {
+            major: num(parts[0])?,
+            minor: num(parts[1])?,
+            patch: parts.get(2).map(|p| num(p)).transpose()?.unwrap_or(0),
+        })
+    }
+}
+
+impl std::fmt::Display for AppVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Version fields of an installed app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppVersionInfo {
+    /// CFBundleVersion (build number)
+    pub bundle_version: String,
+    /// CFBundleShortVersionString (marketing version)
+    pub short_version: String,
+    /// MinimumOSVersion from the app's Info.plist (empty when unavailable)
+    pub minimum_os_version: String,
+}
+
+/// Version info and bundle path from `simctl appinfo` output (an ASCII plist)
+fn parse_app_version_info(stdout: &str) -> Result<(AppVersionInfo, Option<String>)> {
+    let value = plist::Value::from_reader_ascii(stdout.as_bytes())
+        .context("Failed to parse simctl appinfo output")?;
+    let dict = value.as_dictionary()
+        .ok_or_else(|| anyhow::anyhow!("simctl appinfo output is not a dictionary"))?;
+    let field = |key: &str| dict.get(key).and_then(plist_scalar_string);
+
+    let info = AppVersionInfo {
+        bundle_version: field("CFBundleVersion").unwrap_or_default(),
+        short_version: field("CFBundleShortVersionString").unwrap_or_default(),
+        minimum_os_version: field("MinimumOSVersion").unwrap_or_default(),
+    };
+    Ok((info, field("Path").or_else(|| field("Bundle")).map(|p| file_url_to_path(&p))))
+}
+
+/// Installed app's build, marketing and minimum OS versions
+pub fn get_app_version(bundle_id: &str, simulator: Option<&str>) -> Result<AppVersionInfo> {
+    let udid = get_simulator_udid(simulator)?;
+    let output = simctl_exec(&["appinfo", &udid, bundle_id])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // appinfo prints `(null)` rather than failing for unknown bundle IDs on some Xcodes
+    if !output.status.success() || !stdout.trim_start().starts_with('{') {
+        bail!("App {} is not installed: {}", bundle_id, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let (mut info, bundle_path) = parse_app_version_info(&stdout)?;
+    if info.minimum_os_version.is_empty() {
+        // appinfo only echoes a few Info.plist keys; read the rest from the bundle
+        if let Some(path) = bundle_path {
+            info.minimum_os_version = plist::Value::from_file(Path::new(&path).join("Info.plist"))
+                .ok()
+                .and_then(|v| v.as_dictionary()?.get("MinimumOSVersion")?.as_string().map(str::to_string))
+                .unwrap_or_default();
+        }
+    }
+    Ok(info)
+}
+
+/// Fail with a descriptive error if the installed app's marketing version is older than `min_version`
+pub fn require_app_version(bundle_id: &str, min_version: &str, simulator: Option<&str>) -> Result<()> {
+    let required: AppVersion = min_version.parse()?;
+    let installed_str = get_app_version(bundle_id, simulator)?.short_version;
+    let installed: AppVersion = installed_str.parse()
+        .with_context(|| format!("{} has no comparable version", bundle_id))?;
+
+    if installed < required {
+        bail!(
+            "{} version {} is older than required {}",
+            bundle_id, installed_str, min_version
+        );
+    }
+    Ok(())
+}
+
+/// Fail with a descriptive error if the installed app is older than `min_version`
+pub fn assert_app_version_at_least(bundle_id: &str, min_version: &str, simulator: Option<&str>) -> Result<()> {
+    require_app_version(bundle_id, min_version, simulator)
+}
+
+/// Launch an app
+pub fn launch_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
+    launch_app_with_args(bundle_id, &[], &[], simulator)
+}
+
+/// Shell-style rendering of a launch, for error messages
+fn launch_command_line(udid: &str, bundle_id: &str, args: &[&str], env: &[(&str, &str)]) -> String {
+    let quote = |s: &str| if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@".contains(c)) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    };
+
+    env.iter()
+        .map(|(key, value)| format!("SIMCTL_CHILD_{}={}", key, quote(value)))
+        .chain(["xcrun", "simctl", "launch", udid, bundle_id].map(String::from))
+        .chain(args.iter().map(|a| quote(a)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn validate_env_names(env: &[(&str, &str)]) -> Result<()> {
+    if let Some((key, _)) = env.iter().find(|(key, _)| key.is_empty() || key.contains('=')) {
+        bail!("Invalid environment variable name '{}'", key);
+    }
+    Ok(())
+}
+
+/// Launch an app with launch arguments and environment variables. simctl has no env flag:
+/// variables are handed to the app through `SIMCTL_CHILD_`-prefixed variables on simctl itself
+pub fn launch_app_with_args(bundle_id: &str, args: &[&str], env: &[(&str, &str)], simulator: Option<&str>) -> Result<()> {
+    validate_env_names(env)?;
+    let udid = get_simulator_udid(simulator)?;
+
+    let mut simctl_args = vec!["launch", udid.as_str(), bundle_id];
+    simctl_args.extend_from_slice(args);
+    let mut cmd = simctl_command(&simctl_args);
+    for (key, value) in env {
+        cmd.env(format!("SIMCTL_CHILD_{}", key), value);
+    }
+
+    let output = simctl_output(&mut cmd)?;
+    if !output.status.success() {
+        bail!(
+            "Failed to launch {}: {}\nCommand: {}",
+            bundle_id,
+            String::from_utf8_lossy(&output.stderr).trim(),
+            launch_command_line(&udid, bundle_id, args, env)
+        );
+    }
+    Ok(())
+}
+
+/// Stop an app
+pub fn stop_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["terminate", &udid, bundle_id])?;
+
+    if !output.status.success() {
+        bail!("Failed to stop {}: {}", bundle_id, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Send a running app to the background by pressing Home, triggering
+/// `applicationDidEnterBackground` / `sceneDidEnterBackground`
+pub fn background_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
+    if !get_running_apps(simulator)?.iter().any(|app| app == bundle_id) {
+        bail!("{} is not running; launch it before sending it to the background", bundle_id);
+    }
+    press_key("home", simulator)?;
+    Ok(())
+}
+
+/// Bring a backgrounded app back to the foreground, triggering
+/// `applicationWillEnterForeground`; launches it if it is not running
+pub fn foreground_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
+    launch_app(bundle_id, simulator)
+}
+
+/// Put the app through a background/foreground cycle: Home, `duration_ms` in the
+/// background (iOS suspends it a few seconds after it leaves the screen unless it
+/// holds a background task), then relaunch it
+pub fn simulate_suspend(bundle_id: &str, duration_ms: u64, simulator: Option<&str>) -> Result<()> {
+    background_app(bundle_id, simulator)?;
+    std::thread::sleep(std::time::Duration::from_millis(duration_ms));
+    foreground_app(bundle_id, simulator)
+}
+
+/// Install an app
+pub fn install_app(path: &str, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["install", &udid, path])?;
+
+    if !output.status.success() {
+        bail!("Failed to install: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Uninstall an app
+pub fn uninstall_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["uninstall", &udid, bundle_id])?;
+
+    if !output.status.success() {
+        bail!("Failed to uninstall: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Uninstall every app whose bundle ID or display name matches `filter`
+///
+/// The matches are passed to `confirm`, and nothing is uninstalled unless it
+/// returns true; it is not called when nothing matches. Failures are reported
+/// and skipped; returns the bundle IDs that were uninstalled.
+pub fn uninstall_matching_apps(
+    filter: &str,
+    confirm: impl FnOnce(&[String]) -> Result<bool>,
+    simulator: Option<&str>,
+) -> Result<Vec<String>> {
+    if filter.trim().is_empty() {
+        bail!("Refusing to uninstall with an empty filter");
+    }
+
+    let matches: Vec<String> = list_apps(Some(filter), simulator)?
+        .into_iter()
+        .map(|app| app.bundle_id)
+        .collect();
+
+    if matches.is_empty() || !confirm(&matches)? {
+        return Ok(Vec::new());
+    }
+
+    let mut uninstalled = Vec::new();
+    for bundle in matches {
+        match uninstall_app(&bundle, simulator) {
+            Ok(()) => uninstalled.push(bundle),
+            Err(e) => eprintln!("Failed to uninstall {}: {}", bundle, e),
+        }
+    }
+    Ok(uninstalled)
+}
+
+/// True for a "y"/"yes" answer to a confirmation prompt; anything else declines
+pub fn is_affirmative(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Find element by text via accessibility tree
+pub fn find_element(query: &str, _simulator: Option<&str>) -> Result<Option<(i32, i32)>> {
+    let elements = get_accessibility_elements()?;
+
+    Ok(match_element(&elements, query).map(element_center))
+}
+
+/// Collects title, value and description of every element in one pass over
+/// `entire contents`, without positions, sizes or nesting
+const TEXT_DUMP_SCRIPT: &str = r#"
+set outputText to ""
+tell application "System Events"
+    tell process "Simulator"
+        repeat with elem in (entire contents of front window)
+            try
+                set outputText to outputText & (title of elem as string) & linefeed
+            end try
+            try
+                set outputText to outputText & (value of elem as string) & linefeed
+            end try
+            try
+                set outputText to outputText & (description of elem as string) & linefeed
+            end try
+        end repeat
+    end tell
+end tell
+return outputText
+"#;
+
+/// Case-insensitive substring check over a text dump, like `match_element`'s label matching
+fn text_dump_contains(dump: &str, text: &str) -> bool {
+    let text_lower = text.to_lowercase();
+    dump.lines()
+        .filter(|line| *line != "missing value")
+        .any(|line| line.to_lowercase().contains(&text_lower))
+}
+
+/// Whether any on-screen element's title, value or description contains `text`
+///
+/// Much cheaper than `find_element`: a single flat AppleScript query for text
+/// values, with no `UiElement` parsing, so it suits tight polling loops. It does
+/// not filter out zero-sized elements.
+pub fn text_exists(text: &str, _simulator: Option<&str>) -> Result<bool> {
+    ACCESSIBILITY_CHECK.call_once(|| {
+        if let Err(e) = check_accessibility_permission() {
+            eprintln!("Warning: {}", e);
+        }
+    });
+
+    let output = Command::new("osascript")
+        .args(["-e", TEXT_DUMP_SCRIPT])
+        .output()
+        .context("Failed to read screen text via AppleScript")?;
+    if !output.status.success() {
+        bail!("Failed to read screen text: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(text_dump_contains(&String::from_utf8_lossy(&output.stdout), text))
+}
+
+/// First visible element whose title, value or description contains `query`,
+/// or whose accessibility identifier equals it
+fn match_element<'a>(elements: &'a [UiElement], query: &str) -> Option<&'a UiElement> {
+    let query_lower = query.to_lowercase();
+
+    elements.iter().find(|elem| {
+        let matches = elem.title.to_lowercase().contains(&query_lower)
+            || elem.value.to_lowercase().contains(&query_lower)
+            || elem.description.to_lowercase().contains(&query_lower)
+            || (!elem.identifier.is_empty() && elem.identifier == query);
+
+        matches && elem.width > 0 && elem.height > 0
+    })
+}
+
+/// Find an element by accessibility identifier (exact match)
+///
+/// Relies on the macOS AXIdentifier the Simulator exposes, which normally
+/// carries the app's `accessibilityIdentifier` but may differ from the
+/// `XCUIElement.identifier` XCTest reports.
+pub fn find_element_by_id(identifier: &str, _simulator: Option<&str>) -> Result<Option<UiElement>> {
+    Ok(get_accessibility_elements()?
+        .into_iter()
+        .find(|e| e.identifier == identifier))
+}
+
+/// Elements whose role equals `role` (case-insensitive), optionally narrowed
+/// to those whose title, value or description contains `label_query`
+fn filter_by_role(elements: Vec<UiElement>, role: &str, label_query: Option<&str>) -> Vec<UiElement> {
+    let label_lower = label_query.map(str::to_lowercase);
+
+    elements.into_iter()
+        .filter(|e| e.role.eq_ignore_ascii_case(role))
+        .filter(|e| match &label_lower {
+            Some(q) => e.title.to_lowercase().contains(q)
+                || e.value.to_lowercase().contains(q)
+                || e.description.to_lowercase().contains(q),
+            None => true,
+        })
+        .collect()
+}
+
+/// First element with the given role (e.g. "AXButton"), optionally matching a label
+pub fn find_element_by_role(role: &str, label_query: Option<&str>, simulator: Option<&str>) -> Result<Option<UiElement>> {
+    Ok(find_all_elements_by_role(role, label_query, simulator)?.into_iter().next())
+}
+
+/// All elements with the given role, optionally matching a label, in tree order
+pub fn find_all_elements_by_role(role: &str, label_query: Option<&str>, _simulator: Option<&str>) -> Result<Vec<UiElement>> {
+    Ok(filter_by_role(get_accessibility_elements()?, role, label_query))
+}
+
+/// Smallest element whose bounds contain (x, y); ties keep the earlier (outer) one
+fn element_at(elements: Vec<UiElement>, x: i32, y: i32) -> Option<UiElement> {
+    elements.into_iter()
+        .filter(|e| e.width > 0 && e.height > 0)
+        .filter(|e| x >= e.x && x < e.x + e.width && y >= e.y && y < e.y + e.height)
+        .min_by_key(|e| e.width as i64 * e.height as i64)
+}
+
+/// Most specific element occupying a point — the inverse of `find_element`,
+/// handy for checking what a coordinate tap actually hit
+pub fn find_element_at(x: i32, y: i32, _simulator: Option<&str>) -> Result<Option<UiElement>> {
+    Ok(element_at(get_accessibility_elements()?, x, y))
+}
+
+/// Labels of on-screen elements, for assertion diagnostics (at most 20, unlabelled ones skipped)
+fn visible_labels(elements: &[UiElement]) -> String {
+    let labels: Vec<String> = elements.iter()
+        .filter(|e| e.width > 0 && e.height > 0)
+        .map(element_label)
+        .filter(|label| !label.is_empty())
+        .take(20)
+        .map(|label| format!("\"{}\"", label))
+        .collect();
+    if labels.is_empty() { "none".to_string() } else { labels.join(", ") }
+}
+
+/// Fail unless an element matching `query` (as in `find_element`) is on screen;
+/// the error lists the labels that are visible instead
+pub fn assert_element_visible(query: &str, _simulator: Option<&str>) -> Result<()> {
+    let elements = get_accessibility_elements()?;
+    if match_element(&elements, query).is_none() {
+        bail!("Expected element '{}' to be visible, but it was not found. Visible elements: {}", query, visible_labels(&elements));
+    }
+    Ok(())
+}
+
+/// Fail if an element matching `query` (as in `find_element`) is on screen
+pub fn assert_element_hidden(query: &str, _simulator: Option<&str>) -> Result<()> {
+    let elements = get_accessibility_elements()?;
+    if let Some(elem) = match_element(&elements, query) {
+        bail!(
+            "Expected element '{}' to be hidden, but found {} \"{}\" at ({},{}) size={}x{}",
+            query, elem.role, element_label(elem), elem.x, elem.y, elem.width, elem.height
+        );
+    }
+    Ok(())
+}
+
+fn element_center(elem: &UiElement) -> (i32, i32) {
+    (elem.x + elem.width / 2, elem.y + elem.height / 2)
+}
+
+/// Call `check` every `poll_ms` until it yields a value, failing with the
+/// elapsed time once `timeout_ms` has passed
+fn poll_until<T>(
+    timeout_ms: u64,
+    poll_ms: u64,
+    what: &str,
+    mut check: impl FnMut() -> Result<Option<T>>,
+) -> Result<T> {
+    let start = std::time::Instant::now();
+    let timeout = std::time::Duration::from_millis(timeout_ms);
+
+    loop {
+        if let Some(value) = check()? {
+            return Ok(value);
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            bail!("Timed out waiting for {} after {}ms (timeout {}ms)", what, elapsed.as_millis(), timeout_ms);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(poll_ms).min(timeout - elapsed));
+    }
+}
+
+/// Wait until an element matching `query` appears; returns its screen coordinates
+pub fn wait_for_element(query: &str, timeout_ms: u64, poll_ms: u64, _simulator: Option<&str>) -> Result<(i32, i32)> {
+    poll_until(timeout_ms, poll_ms, &format!("element '{}'", query), || {
+        Ok(match_element(&get_accessibility_elements()?, query).map(element_center))
+    })
+}
+
+/// Wait until no element matches `query` (e.g. a spinner or sheet dismissed)
+pub fn wait_for_element_gone(query: &str, timeout_ms: u64, poll_ms: u64, _simulator: Option<&str>) -> Result<()> {
+    poll_until(timeout_ms, poll_ms, &format!("element '{}' to disappear", query), || {
+        Ok(match_element(&get_accessibility_elements()?, query).is_none().then_some(()))
+    })
+}
+
+/// Runs per `measure_url_open_time` measurement
+const URL_OPEN_RUNS: usize = 5;
+/// Per-run limit for the ready element to appear
+const URL_OPEN_TIMEOUT_MS: u64 = 30_000;
+
+fn median_duration(mut samples: Vec<std::time::Duration>) -> Option<std::time::Duration> {
+    samples.sort();
+    let mid = samples.len() / 2;
+    match samples.len() {
+        0 => None,
+        n if n % 2 == 1 => Some(samples[mid]),
+        _ => Some((samples[mid - 1] + samples[mid]) / 2),
+    }
+}
+
+/// Median time from `open_url` until `ready_element` is on screen, over 5 runs
+///
+/// The app is sent Home between runs, so the first run is a cold open if the
+/// app was not running and the rest are warm opens.
+pub fn measure_url_open_time(url: &str, ready_element: &str, simulator: Option<&str>) -> Result<std::time::Duration> {
+    let mut samples = Vec::with_capacity(URL_OPEN_RUNS);
+
+    for run in 1..=URL_OPEN_RUNS {
+        if run > 1 {
+            press_key("home", simulator)?;
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+
+        let start = std::time::Instant::now();
+        open_url(url, simulator)?;
+        poll_until(URL_OPEN_TIMEOUT_MS, 100, &format!("element '{}'", ready_element), || {
+            Ok(match_element(&get_accessibility_elements()?, ready_element).map(|_| ()))
+        })?;
+        samples.push(start.elapsed());
+    }
+
+    median_duration(samples).context("No measurements taken")
+}
+
+/// Log messages apps emit once their first screen is up
+const LAUNCH_SIGNALS: &[&str] = &["[app] launch", "viewDidAppear"];
+const LAUNCH_SIGNAL_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LaunchTimeMeasurement {
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: u64,
+    pub trials: u32,
+}
+
+impl LaunchTimeMeasurement {
+    fn from_samples(samples: &[std::time::Duration]) -> Option<Self> {
+        let ms: Vec<u64> = samples.iter().map(|d| d.as_millis() as u64).collect();
+        Some(LaunchTimeMeasurement {
+            min_ms: *ms.iter().min()?,
+            max_ms: *ms.iter().max()?,
+            mean_ms: ms.iter().sum::<u64>() / ms.len() as u64,
+            trials: ms.len() as u32,
+        })
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+fn is_launch_signal(line: &str) -> bool {
+    LAUNCH_SIGNALS.iter().any(|signal| line.contains(signal))
+}
+
+/// Empty the app's Library/Caches so a cold launch cannot reuse cached data
+fn clear_app_caches(udid: &str, bundle_id: &str) -> Result<()> {
+    let root = container_root(udid, Some(bundle_id), &ContainerKind::Data)?;
+    let caches = root.join("Library/Caches");
+    if caches.exists() {
+        std::fs::remove_dir_all(&caches)
+            .with_context(|| format!("Failed to clear {}", caches.display()))?;
+        std::fs::create_dir_all(&caches)?;
+    }
+    Ok(())
+}
+
+/// Time from `launch_app` until the app logs a launch signal ("[app] launch" or
+/// "viewDidAppear"), over `trials` runs. Cold runs terminate the app and clear its
+/// caches first; warm runs send it Home and bring it back.
+pub fn measure_launch_time(bundle_id: &str, warm: bool, trials: u32, simulator: Option<&str>) -> Result<LaunchTimeMeasurement> {
+    if trials == 0 {
+        bail!("trials must be at least 1");
+    }
+    let udid = get_simulator_udid(simulator)?;
+
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    let stream = stream_logs(None, move |line| {
+        if is_launch_signal(line) {
+            let _ = tx.send(());
+        }
+    }, simulator)?;
+    // Give `log stream` a moment to attach before the first launch
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    let measure = || -> Result<Vec<std::time::Duration>> {
+        if warm {
+            // A warm launch needs the process already running
+            launch_app(bundle_id, simulator)?;
+            std::thread::sleep(std::time::Duration::from_secs(2));
+        }
+
+        let mut samples = Vec::with_capacity(trials as usize);
+        for _ in 0..trials {
+            if warm {
+                press_key("home", simulator)?;
+            } else {
+                // Not running is fine
+                let _ = simctl_exec(&["terminate", &udid, bundle_id]);
+                clear_app_caches(&udid, bundle_id)?;
+            }
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            while rx.try_recv().is_ok() {}
+
+            let start = std::time::Instant::now();
+            launch_app(bundle_id, simulator)?;
+            rx.recv_timeout(std::time::Duration::from_millis(LAUNCH_SIGNAL_TIMEOUT_MS))
+                .map_err(|_| with_suggestion(
+                    anyhow::anyhow!("{} did not log a launch signal within {}ms", bundle_id, LAUNCH_SIGNAL_TIMEOUT_MS),
+                    "Log \"[app] launch\" (or override viewDidAppear) once the first screen is shown",
+                ))?;
+            samples.push(start.elapsed());
+        }
+        Ok(samples)
+    };
+    let samples = measure();
+    stream.stop()?;
+
+    LaunchTimeMeasurement::from_samples(&samples?).context("No measurements taken")
+}
+
+/// Accessibility roles that automation can act on (tap, type, toggle, drag)
+pub const INTERACTIVE_ROLES: &[&str] = &[
+    "AXButton",
+    "AXTextField",
+    "AXTextArea",
+    "AXSlider",
+    "AXToggleButton",
+    "AXLink",
+    "AXCheckBox",
+    "AXRadioButton",
+    "AXComboBox",
+];
+
+/// Keep only elements whose role is in INTERACTIVE_ROLES
+fn filter_interactive(elements: Vec<UiElement>) -> Vec<UiElement> {
+    elements
+        .into_iter()
+        .filter(|e| INTERACTIVE_ROLES.contains(&e.role.as_str()))
+        .collect()
+}
+
+/// Get only the interactive elements of the accessibility tree
+pub fn get_interactive_elements(_simulator: Option<&str>) -> Result<Vec<UiElement>> {
+    Ok(filter_interactive(get_accessibility_elements()?))
+}
+
+/// Tap element by text
+pub fn tap_element(query: &str, simulator: Option<&str>) -> Result<()> {
+    if let Some((x, y)) = find_element(query, simulator)? {
+        // These are screen coordinates already (from AppleScript), tap directly
+        click_screen_point(x, y);
+    } else {
+        bail!("Element '{}' not found", query);
+    }
+    Ok(())
+}
+
+/// Click at host screen coordinates (as reported by the accessibility tree)
+fn click_screen_point(x: i32, y: i32) {
+    if let Err(e) = ensure_simulator_focused(None) {
+        eprintln!("Warning: {}", e);
+    }
+    let script = format!(
+        r#"tell application "System Events"
+    click at {{{}, {}}}
+end tell"#,
+        x, y
+    );
+    let _ = Command::new("osascript").args(["-e", &script]).output();
+}
+
+/// How long the pointer is held before moving, so iOS lifts the item for drag and drop
+const DRAG_LIFT_MS: u32 = 600;
+
+/// cliclick events for a press-hold-move-release drag between host screen points
+fn drag_cliclick_args(from: (i32, i32), to: (i32, i32), duration_ms: u32) -> Vec<String> {
+    let step_wait_ms = duration_ms / GESTURE_STEPS;
+    let mut args = vec![
+        format!("m:{},{}", from.0, from.1),
+        format!("dd:{},{}", from.0, from.1),
+        format!("w:{}", DRAG_LIFT_MS),
+    ];
+    for (x, y) in interpolate_points(from, to, GESTURE_STEPS) {
+        args.push(format!("dm:{},{}", x, y));
+        args.push(format!("w:{}", step_wait_ms));
+    }
+    args.push(format!("du:{},{}", to.0, to.1));
+    args
+}
+
+/// Drag between host screen points (as reported by the accessibility tree)
+/// Requires cliclick since AppleScript cannot hold the mouse button
+fn drag_screen_points(from: (i32, i32), to: (i32, i32), duration_ms: u32, simulator: Option<&str>) -> Result<()> {
+    if !has_cliclick() {
+        bail!("drag and drop requires cliclick to hold the mouse button (brew install cliclick)");
+    }
+    ensure_simulator_focused(simulator)?;
+
+    let output = Command::new("cliclick")
+        .args(drag_cliclick_args(from, to, duration_ms))
+        .output()
+        .context("Failed to drag with cliclick")?;
+    if !output.status.success() {
+        bail!("Failed to drag: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Drag the element matching `source_query` onto the one matching `target_query`;
+/// the source is held briefly first so iOS picks it up, then moved over `duration_ms`
+pub fn drag_element_to_element(source_query: &str, target_query: &str, duration_ms: u32, simulator: Option<&str>) -> Result<()> {
+    let from = find_element(source_query, simulator)?
+        .ok_or_else(|| anyhow::anyhow!("Source element '{}' not found", source_query))?;
+    let to = find_element(target_query, simulator)?
+        .ok_or_else(|| anyhow::anyhow!("Target element '{}' not found", target_query))?;
+
+    drag_screen_points(from, to, duration_ms, simulator)?;
+    Ok(())
+}
+
+/// Drag the element matching `source_query` to a drop zone at (target_x, target_y)
+/// in simulator pixels
+pub fn drag_element_to_coords(source_query: &str, target_x: i32, target_y: i32, duration_ms: u32, simulator: Option<&str>) -> Result<()> {
+    let from = find_element(source_query, simulator)?
+        .ok_or_else(|| anyhow::anyhow!("Source element '{}' not found", source_query))?;
+    let to = sim_to_screen_coords(target_x, target_y, simulator)?;
+
+    drag_screen_points(from, to, duration_ms, simulator)?;
+    Ok(())
+}
+
+/// Clear device logs; returns false when `log erase` was refused, which needs
+/// elevated privileges on the simulator (rebooting it clears the logs instead)
+pub fn clear_logs(simulator: Option<&str>) -> Result<bool> {
+    let udid = get_simulator_udid(simulator)?;
+
+    // Try predicate-based approach: show last 0 seconds effectively clears view
+    let output = simctl_exec(&["spawn", &udid, "log", "erase", "--all"]);
+
+    if let Ok(out) = output {
+        if out.status.success() {
+            return Ok(true);
+        }
+    }
+
+    // log erase requires root
+    Ok(false)
+}
+
+/// Host directory CoreSimulator writes per-device logs to, one `<udid>` folder each
+fn core_simulator_logs_dir() -> Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME is not set")?;
+    Ok(Path::new(&home).join("Library/Logs/CoreSimulator"))
+}
+
+/// `<logs_dir>/<udid>/system.log`, or the device's log folder when the runtime no
+/// longer writes `system.log` (iOS 10+ logs to the unified log instead)
+fn resolve_logs_path(logs_dir: &Path, udid: &str) -> Result<PathBuf> {
+    let device_dir = logs_dir.join(udid);
+    let system_log = device_dir.join("system.log");
+    if system_log.is_file() {
+        Ok(system_log)
+    } else if device_dir.is_dir() {
+        Ok(device_dir)
+    } else {
+        Err(with_suggestion(
+            anyhow::anyhow!("No log directory for simulator {} at {}", udid, device_dir.display()),
+            "Boot the simulator once, or read the unified log with `claude-in-mobile logs ios`",
+        ))
+    }
+}
+
+/// On-disk simulator log (`~/Library/Logs/CoreSimulator/<udid>/system.log`, or that
+/// folder when there is no `system.log`), for tailing with external tools
+pub fn get_simulator_logs_path(simulator: Option<&str>) -> Result<PathBuf> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+    resolve_logs_path(&core_simulator_logs_dir()?, &udid)
+}
+
+/// Open the simulator log in the macOS Console app
+pub fn open_logs_in_console(simulator: Option<&str>) -> Result<()> {
+    let path = get_simulator_logs_path(simulator)?;
+
+    let status = Command::new("open").args(["-a", "Console"]).arg(&path).status()
+        .context("Failed to execute open")?;
+    if !status.success() {
+        bail!("Failed to open {} in Console", path.display());
+    }
+    Ok(())
+}
+
+/// Details of a single simulator, as reported by `get_system_info`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatorInfo {
+    pub name: String,
+    pub state: String,
+    /// Runtime key without its CoreSimulator prefix, e.g. "iOS-17-2"
+    pub runtime: String,
+    pub udid: String,
+    /// Device type key without its CoreSimulator prefix, e.g. "iPhone-15-Pro"
+    pub device_type: String,
+    /// OS version parsed from the runtime, e.g. "17.2"
+    pub os_version: String,
+    /// Free space on the volume holding the simulator's data directory
+    pub available_storage_gb: Option<f64>,
+}
+
+/// "iOS-17-2" → "17.2"
+fn os_version_from_runtime(runtime: &str) -> String {
+    runtime
+        .split_once('-')
+        .map(|(_, version)| version.replace('-', "."))
+        .unwrap_or_default()
+}
+
+/// Build `SimulatorInfo` (minus storage) and its data path from `simctl list devices -j`
+fn parse_simulator_info(json: &serde_json::Value, udid: &str) -> Option<(SimulatorInfo, Option<String>)> {
+    json["devices"].as_object()?.iter().find_map(|(runtime, devices)| {
+        let device = devices.as_array()?.iter().find(|d| d["udid"].as_str() == Some(udid))?;
+        let runtime = runtime.replace("com.apple.CoreSimulator.SimRuntime.", "");
+        let info = SimulatorInfo {
+            name: device["name"].as_str().unwrap_or("Unknown").to_string(),
+            state: device["state"].as_str().unwrap_or("Unknown").to_string(),
+            os_version: os_version_from_runtime(&runtime),
+            runtime,
+            udid: udid.to_string(),
+            device_type: device["deviceTypeIdentifier"].as_str().unwrap_or("")
+                .replace("com.apple.CoreSimulator.SimDeviceType.", ""),
+            available_storage_gb: None,
+        };
+        Some((info, device["dataPath"].as_str().map(str::to_string)))
+    })
+}
+
+/// Available space in GB from `df -k <path>` output
+fn parse_df_available_gb(output: &str) -> Option<f64> {
+    let fields: Vec<&str> = output.lines().nth(1)?.split_whitespace().collect();
+    let kb: f64 = fields.get(3)?.parse().ok()?;
+    Some(kb / (1024.0 * 1024.0))
+}
+
+/// Name, state, runtime, device type, OS version and free storage of the simulator
+pub fn get_system_info(simulator: Option<&str>) -> Result<SimulatorInfo> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+    let (mut info, data_path) = parse_simulator_info(&simctl_list_json("devices")?, &udid)
+        .ok_or_else(|| anyhow::anyhow!("Simulator '{}' not found", udid))?;
+
+    if let Some(path) = data_path {
+        info.available_storage_gb = Command::new("df")
+            .args(["-k", &path])
+            .output()
+            .ok()
+            .and_then(|out| parse_df_available_gb(&String::from_utf8_lossy(&out.stdout)));
+    }
+
+    Ok(info)
+}
+
+/// Print system info
+pub fn print_system_info(simulator: Option<&str>) -> Result<()> {
+    let info = get_system_info(simulator)?;
+    println!("System Info:");
+    println!("  Name: {}", info.name);
+    println!("  State: {}", info.state);
+    println!("  Runtime: {}", info.runtime);
+    println!("  OS Version: {}", info.os_version);
+    println!("  Device Type: {}", info.device_type);
+    println!("  UDID: {}", info.udid);
+    if let Some(gb) = info.available_storage_gb {
+        println!("  Available Storage: {:.1} GB", gb);
+    }
+    Ok(())
+}
+
+/// Running app bundle IDs from `launchctl list` output, in listing order,
+/// skipping system view services and entries without a PID
+fn parse_running_apps(stdout: &str) -> Vec<String> {
+    let re = regex::Regex::new(r"UIKitApplication:([^\[]+)\[").unwrap();
+
+    let mut apps: Vec<String> = Vec::new();
+    for line in stdout.lines() {
+        if let Some(cap) = re.captures(line) {
+            let bundle = cap[1].to_string();
+            // Skip system background services
+            if !bundle.contains("WidgetRenderer")
+                && !bundle.contains("ViewService")
+                && !bundle.contains("Spotlight") {
+                // Check if PID is running (first column is PID, "-" means not running)
+                let pid = line.split_whitespace().next().unwrap_or("-");
+                if pid != "-" {
+                    apps.push(bundle);
+                }
+            }
+        }
+    }
+    apps
+}
+
+/// Bundle IDs of running apps via launchctl, foreground and background alike
+///
+/// `launchctl list` is in no particular order and says nothing about which app is
+/// frontmost, so use this to check that an app is running, not that it is on screen.
+pub fn get_running_apps(simulator: Option<&str>) -> Result<Vec<String>> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["spawn", &udid, "launchctl", "list"])?;
+    if !output.status.success() {
+        bail!("Failed to list running processes: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(parse_running_apps(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Print the running apps (which one is frontmost cannot be told from launchctl)
+pub fn print_current_activity(simulator: Option<&str>) -> Result<()> {
+    let apps = get_running_apps(simulator)?;
+    if apps.is_empty() {
+        println!("No apps running (SpringBoard/Home Screen)");
+    }
+    for app in &apps {
+        println!("Running app: {}", app);
+    }
+    Ok(())
+}
+
+/// Recent device log lines (last 5 minutes, at most `lines`)
+pub fn get_logs(
+    filter: Option<&str>,
+    lines: usize,
+    level: Option<LogLevel>,
+    subsystem: Option<&str>,
+    simulator: Option<&str>,
+) -> Result<Vec<String>> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let filter_args = log_filter_args(false, filter, level, subsystem);
+    let mut args = vec!["spawn", &udid, "log", "show", "--last", "5m", "--style", "compact"];
+    args.extend(filter_args.iter().map(String::as_str));
+
+    let output = simctl_exec(&args)?;
+    if !output.status.success() {
+        bail!("Failed to read logs: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().take(lines).map(str::to_string).collect())
+}
+
+/// Print recent device log lines
+pub fn print_logs(
+    filter: Option<&str>,
+    lines: usize,
+    level: Option<LogLevel>,
+    subsystem: Option<&str>,
+    simulator: Option<&str>,
+) -> Result<()> {
+    for line in get_logs(filter, lines, level, subsystem, simulator)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Minimum level for `log show` / `log stream` (`Debug` includes everything)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Default,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Default => "default",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+impl std::str::FromStr for LogLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "default" => Ok(LogLevel::Default),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            other => bail!("Unknown iOS log level '{}': use default, info or debug", other),
+        }
+    }
+}
+
+/// Quote a value for a `log` predicate string literal
+fn predicate_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Level and `--predicate` arguments for `log stream` (`stream`) or `log show`.
+/// Only `log stream` takes `--level`; `log show` opts into levels with `--info` / `--debug`.
+fn log_filter_args(stream: bool, filter: Option<&str>, level: Option<LogLevel>, subsystem: Option<&str>) -> Vec<String> {
+    let mut args = Vec::new();
+    match (stream, level) {
+        (true, Some(level)) => args.extend(["--level".to_string(), level.as_str().to_string()]),
+        (false, Some(LogLevel::Info)) => args.push("--info".to_string()),
+        (false, Some(LogLevel::Debug)) => args.push("--debug".to_string()),
+        (false, Some(LogLevel::Default)) | (_, None) => {}
+    }
+
+    let clauses: Vec<String> = filter.map(|f| format!("processImagePath CONTAINS {}", predicate_literal(f)))
+        .into_iter()
+        .chain(subsystem.map(|s| format!("subsystem == {}", predicate_literal(s))))
+        .collect();
+    if !clauses.is_empty() {
+        args.extend(["--predicate".to_string(), clauses.join(" AND ")]);
+    }
+    args
+}
+
+/// Reboot simulator
+pub fn reboot(simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let _ = simctl_exec(&["shutdown", &udid]);
+    std::thread::sleep(std::time::Duration::from_secs(1));
+
+    let output = simctl_exec(&["boot", &udid])?;
+
+    if !output.status.success() {
+        bail!("Failed to reboot: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Boot a shut-down simulator with extra environment variables in its launchd environment
+///
+/// `simctl boot` forwards `SIMCTL_CHILD_`-prefixed variables from its own environment to the
+/// booted device, so every process started afterwards sees them. Unlike the per-launch
+/// variables of `launch_app_with_args`, they are already set when system services and
+/// apps launched by other means (URLs, notifications) start.
+pub fn boot_with_env(env: &[(&str, &str)], simulator: Option<&str>) -> Result<()> {
+    validate_env_names(env)?;
+    let Some(name) = simulator else {
+        bail!("boot_with_env needs the simulator to boot (name or UDID)");
+    };
+    let device = find_simulator(name)?;
+    if device.state != "Shutdown" {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Simulator '{}' is {}; boot-time variables only apply to a shut-down device", device.name, device.state),
+            &format!("Run `xcrun simctl shutdown {}` first", device.udid),
+        ));
+    }
+
+    let mut cmd = simctl_command(&["boot", &device.udid]);
+    for (key, value) in env {
+        cmd.env(format!("SIMCTL_CHILD_{}", key), value);
+    }
+    let output = simctl_output(&mut cmd)?;
+    if !output.status.success() {
+        bail!("Failed to boot {}: {}", device.name, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Factory-reset a simulator (all content and settings are wiped). simctl can only erase a
+/// shut-down device: a booted one is an error unless `force`, which shuts it down first
+pub fn erase_simulator(force: bool, simulator: Option<&str>) -> Result<()> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+    let device = find_simulator(&udid)?;
+
+    if device.state != "Shutdown" {
+        if !force {
+            return Err(with_suggestion(
+                anyhow::anyhow!("Simulator '{}' is {}; it must be shut down before erasing", device.name, device.state),
+                "Pass force to shut it down first, or run `xcrun simctl shutdown <udid>`",
+            ));
+        }
+        let output = simctl_exec(&["shutdown", &udid])?;
+        if !output.status.success() {
+            bail!("Failed to shut down {}: {}", device.name, String::from_utf8_lossy(&output.stderr));
+        }
+    }
+
+    let output = simctl_exec(&["erase", &udid])?;
+    if !output.status.success() {
+        bail!("Failed to erase simulator: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Factory-reset a simulator, requiring a non-empty reason that is logged first
+/// Guards against accidental erases from scripts
+pub fn erase_simulator_confirmed(simulator: Option<&str>, reason: &str, force: bool) -> Result<()> {
+    if reason.trim().is_empty() {
+        bail!("Refusing to erase simulator: a non-empty reason is required");
+    }
+
+    eprintln!("Erasing simulator {} (reason: {})", simulator.unwrap_or("booted"), reason.trim());
+    erase_simulator(force, simulator)
+}
+
+/// Erase every shut-down simulator; returns how many were erased
+pub fn erase_all_simulators() -> Result<usize> {
+    let devices = list_devices()?;
+    let booted: Vec<&str> = devices.iter()
+        .filter(|d| d.state != "Shutdown")
+        .map(|d| d.name.as_str())
+        .collect();
+    if !booted.is_empty() {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Cannot erase all simulators while these are running: {}", booted.join(", ")),
+            "Run `xcrun simctl shutdown all` first",
+        ));
+    }
+
+    let output = simctl_exec(&["erase", "all"])?;
+    if !output.status.success() {
+        bail!("Failed to erase simulators: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(devices.len())
+}
+
+/// CoreSimulator release that added `simctl keychain` (Xcode 11.4)
+const KEYCHAIN_MIN_CORESIMULATOR: u32 = 681;
+
+/// Major CoreSimulator version from `simctl --version` ("PROGRAM:simctl  PROJECT:CoreSimulator-857.14")
+fn parse_coresimulator_version(stdout: &str) -> Option<u32> {
+    let version = stdout.split("CoreSimulator-").nth(1)?;
+    version.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+/// Remove all keychain items (passwords, keys, certificates) from the simulator
+pub fn reset_keychain(simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let version = simctl_exec(&["--version"])
+        .ok()
+        .and_then(|o| parse_coresimulator_version(&String::from_utf8_lossy(&o.stdout)));
+    let supported = match version {
+        Some(version) => version >= KEYCHAIN_MIN_CORESIMULATOR,
+        None => simctl_help_mentions(&[], "keychain"),
+    };
+    if !supported {
+        return Err(IosError::FeatureNotSupported {
+            feature: "simctl keychain reset".to_string(),
+            requirement: "Xcode 11.4 or later".to_string(),
+            workaround: "erase the simulator with `claude-in-mobile erase-simulator --confirm --reason ...`".to_string(),
+        }.into());
+    }
+
+    let output = simctl_exec(&["keychain", &udid, "reset"])?;
+    if !output.status.success() {
+        bail!("Failed to reset keychain: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+// ============== Test Lanes ==============
+
+fn lane_name(base_name: &str, lane: u32) -> String {
+    format!("{} Lane {}", base_name, lane)
+}
+
+/// Find a simulator by name or UDID
+fn find_simulator(name_or_udid: &str) -> Result<Simulator> {
+    list_devices()?
+        .into_iter()
+        .find(|sim| sim.name == name_or_udid || sim.udid == name_or_udid)
+        .ok_or_else(|| with_suggestion(
+            anyhow::anyhow!("Simulator '{}' not found", name_or_udid),
+            "Run `claude-in-mobile devices ios` to see available devices",
+        ))
+}
+
+/// Clone `base_simulator` into `lane_count` lanes ("<base> Lane 1", ...), boot them concurrently
+/// and return their UDIDs. Lanes already created are deleted again if any step fails.
+pub fn create_test_lane(base_simulator: &str, lane_count: u32) -> Result<Vec<String>> {
+    if lane_count == 0 {
+        bail!("lane_count must be at least 1");
+    }
+    let base = find_simulator(base_simulator)?;
+    if base.state != "Shutdown" {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Simulator '{}' must be shut down to be cloned (state: {})", base.name, base.state),
+            &format!("Run `xcrun simctl shutdown {}` first", base.udid),
+        ));
+    }
+
+    let mut udids = Vec::new();
+    for lane in 1..=lane_count {
+        let name = lane_name(&base.name, lane);
+        let output = simctl_exec(&["clone", &base.udid, &name])?;
+        if !output.status.success() {
+            let _ = destroy_test_lanes(&udids);
+            bail!("Failed to clone '{}' as '{}': {}", base.name, name, String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let udid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        udids.push(udid);
+    }
+
+    let boots: Vec<_> = udids.iter()
+        .map(|udid| {
+            let udid = udid.clone();
+            std::thread::spawn(move || simctl_exec(&["boot", &udid]))
+        })
+        .collect();
+    let mut failures = Vec::new();
+    for (udid, boot) in udids.iter().zip(boots) {
+        match boot.join() {
+            Ok(Ok(output)) if output.status.success() => {}
+            Ok(Ok(output)) => failures.push(format!("{}: {}", udid, String::from_utf8_lossy(&output.stderr).trim())),
+            Ok(Err(e)) => failures.push(format!("{}: {}", udid, e)),
+            Err(_) => failures.push(format!("{}: boot thread panicked", udid)),
+        }
+    }
+    if !failures.is_empty() {
+        let _ = destroy_test_lanes(&udids);
+        bail!("Failed to boot test lanes:\n{}", failures.join("\n"));
+    }
+    Ok(udids)
+}
+
+/// Shut down and delete lane simulators, attempting every UDID before reporting failures
+pub fn destroy_test_lanes(udids: &[String]) -> Result<()> {
+    let mut failures = Vec::new();
+    for udid in udids {
+        // Shutdown fails harmlessly for lanes that never booted
+        let _ = simctl_exec(&["shutdown", udid]);
+        match simctl_exec(&["delete", udid]) {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => failures.push(format!("{}: {}", udid, String::from_utf8_lossy(&output.stderr).trim())),
+            Err(e) => failures.push(format!("{}: {}", udid, e)),
+        }
+    }
+    if !failures.is_empty() {
+        bail!("Failed to delete test lanes:\n{}", failures.join("\n"));
+    }
+    Ok(())
+}
+
+/// Per-user state kept between runs (`~/Library/Application Support/claude-in-mobile`)
+fn app_support_dir() -> Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME is not set")?;
+    Ok(Path::new(&home).join("Library/Application Support/claude-in-mobile"))
+}
+
+// ============== State Snapshots ==============
+
+/// Manifest mapping snapshot names to the UDIDs of their clone simulators
+fn snapshot_manifest_path() -> Result<PathBuf> {
+    Ok(app_support_dir()?.join("snapshots.json"))
+}
+
+/// JSON object state file keyed by name or UDID; missing files read as empty
+fn read_state_map<V: serde::de::DeserializeOwned>(path: &Path) -> Result<std::collections::BTreeMap<String, V>> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).with_context(|| format!("Invalid state file {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+        Err(e) => Err(anyhow::Error::new(e).context(format!("Failed to read {}", path.display()))),
+    }
+}
+
+fn write_state_map<V: Serialize>(path: &Path, manifest: &std::collections::BTreeMap<String, V>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(manifest)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Saved snapshots by name (name → snapshot UDID)
+pub fn list_states() -> Result<std::collections::BTreeMap<String, String>> {
+    read_state_map(&snapshot_manifest_path()?)
+}
+
+/// `simctl clone` a shut-down device as `name`, returning the clone's UDID
+fn clone_device(udid: &str, name: &str) -> Result<String> {
+    let output = simctl_exec(&["clone", udid, name])?;
+    if !output.status.success() {
+        bail!("Failed to clone {} as '{}': {}", udid, name, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Snapshot the simulator's full state (apps, data, settings) into a clone named after
+/// `name` and record it in the manifest; returns the snapshot UDID. A booted simulator is
+/// shut down for the clone and booted again afterwards.
+pub fn save_state(name: &str, simulator: Option<&str>) -> Result<String> {
+    if name.trim().is_empty() {
+        bail!("Snapshot name must not be empty");
+    }
+    let device = find_simulator(&concrete_udid(&get_simulator_udid(simulator)?)?)?;
+    let was_booted = device.state == "Booted";
+    if was_booted {
+        simctl_exec(&["shutdown", &device.udid])?;
+    }
+
+    let cloned = clone_device(&device.udid, &format!("{} Snapshot {}", device.name, name));
+    if was_booted {
+        simctl_exec(&["boot", &device.udid])?;
+    }
+    let snapshot_udid = cloned?;
+
+    let path = snapshot_manifest_path()?;
+    let mut manifest = read_state_map(&path)?;
+    if let Some(old) = manifest.insert(name.to_string(), snapshot_udid.clone()) {
+        let _ = simctl_exec(&["delete", &old]);
+    }
+    write_state_map(&path, &manifest)?;
+    Ok(snapshot_udid)
+}
+
+/// Run a simctl subcommand, failing with `what` and simctl's stderr
+fn simctl_checked(args: &[&str], what: &str) -> Result<()> {
+    let output = simctl_exec(args)?;
+    if !output.status.success() {
+        bail!("Failed to {}: {}", what, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Restore a snapshot (by manifest name or UDID) onto `target`, replacing it with a
+/// fresh clone of the snapshot under the same name, which is then booted. The clone
+/// gets a new UDID, which is returned; the name still addresses it afterwards.
+///
+/// This discards the target's current state, so like `erase_simulator_confirmed` it
+/// requires a non-empty `reason`. The original is only renamed aside until the clone
+/// has booted, and is put back if any step fails.
+pub fn load_state(snapshot_udid: &str, reason: &str, target: Option<&str>) -> Result<String> {
+    if reason.trim().is_empty() {
+        bail!("Refusing to replace simulator with a snapshot: a non-empty reason is required");
+    }
+    let snapshot_udid = list_states()?
+        .get(snapshot_udid)
+        .cloned()
+        .unwrap_or_else(|| snapshot_udid.to_string());
+    let snapshot = find_simulator(&snapshot_udid)?;
+    let target = find_simulator(&concrete_udid(&get_simulator_udid(target)?)?)?;
+    if target.udid == snapshot.udid {
+        bail!("Cannot restore snapshot '{}' onto itself; pass a different target simulator", snapshot.name);
+    }
+
+    eprintln!("Replacing simulator {} with snapshot '{}' (reason: {})", target.name, snapshot.name, reason.trim());
+    let was_booted = target.state != "Shutdown";
+    if was_booted {
+        simctl_checked(&["shutdown", &target.udid], &format!("shut down {}", target.name))?;
+    }
+    let aside_name = format!("{} (before restore)", target.name);
+    simctl_checked(&["rename", &target.udid, &aside_name], &format!("rename {}", target.name))?;
+
+    let restored = clone_device(&snapshot.udid, &target.name).and_then(|restored| {
+        match simctl_checked(&["boot", &restored], "boot restored simulator") {
+            Ok(()) => Ok(restored),
+            Err(e) => {
+                let _ = simctl_exec(&["delete", &restored]);
+                Err(e)
+            }
+        }
+    });
+    let restored = match restored {
+        Ok(restored) => restored,
+        Err(e) => {
+            // Put the original back the way it was
+            let _ = simctl_exec(&["rename", &target.udid, &target.name]);
+            if was_booted {
+                let _ = simctl_exec(&["boot", &target.udid]);
+            }
+            return Err(e.context(format!("{} was left unchanged", target.name)));
+        }
+    };
+
+    simctl_checked(&["delete", &target.udid], &format!("delete the replaced simulator '{}'", aside_name))?;
+    Ok(restored)
+}
+
+// ============== Xcode Test Plans ==============
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestFailure {
+    pub test_name: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct XcodeTestResult {
+    pub passed: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub duration: std::time::Duration,
+    pub failures: Vec<TestFailure>,
+}
+
+/// Replace the "booted" alias with the UDID of the first booted simulator (xcodebuild needs a real id)
+fn concrete_udid(udid: &str) -> Result<String> {
+    if udid != "booted" {
+        return Ok(udid.to_string());
+    }
+    list_devices()?
+        .into_iter()
+        .find(|sim| sim.state == "Booted")
+        .map(|sim| sim.udid)
+        .ok_or_else(|| with_suggestion(
+            anyhow::anyhow!("No booted simulator found"),
+            "Boot a simulator or pass --simulator <name>",
+        ))
+}
+
+/// Tally xcodebuild test output. Handles XCTest ("Test Case '-[Mod.Class test]' passed (0.1 seconds).")
+/// and parallel/Swift Testing ("Test case 'Class.test()' failed on 'Clone 1 ...' (0.1 seconds)") lines.
+fn parse_xcodebuild_test_output(stdout: &str) -> XcodeTestResult {
+    let case_re = regex::Regex::new(r"^Test [Cc]ase '(?:-\[)?(.+?)\]?' (passed|failed|skipped)").unwrap();
+    let failure_re = regex::Regex::new(r"^(.+?):(\d+): error: (?:-\[)?(.+?)\]? : (.*)$").unwrap();
+
+    let mut result = XcodeTestResult::default();
+    for line in stdout.lines().map(str::trim) {
+        if let Some(caps) = case_re.captures(line) {
+            let name = caps[1].replace(' ', ".");
+            match &caps[2] {
+                "passed" => result.passed += 1,
+                "skipped" => result.skipped += 1,
+                _ => {
+                    result.failed += 1;
+                    // Parallel runs report failures without a preceding error line
+                    if !result.failures.iter().any(|f| f.test_name == name) {
+                        result.failures.push(TestFailure { test_name: name, message: String::new(), file: None, line: None });
+                    }
+                }
+            }
+        } else if let Some(caps) = failure_re.captures(line) {
+            result.failures.push(TestFailure {
+                test_name: caps[3].replace(' ', "."),
+                message: caps[4].to_string(),
+                file: Some(caps[1].to_string()),
+                line: caps[2].parse().ok(),
+            });
+        }
+    }
+    result
+}
+
+/// Run an existing Xcode test plan against a simulator (`test-without-building`; build first)
+/// `plan_path` may be a .xctestplan path or a bare plan name; run from the project directory
+pub fn run_test_plan(plan_path: &str, scheme: &str, simulator: Option<&str>) -> Result<XcodeTestResult> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+    // xcodebuild takes the plan name, not its path
+    let plan = Path::new(plan_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(plan_path);
+    let destination = format!("id={}", udid);
+
+    let started = std::time::Instant::now();
+    let output = Command::new("xcodebuild")
+        .args(["test-without-building", "-testPlan", plan, "-scheme", scheme, "-destination", &destination])
+        .output()
+        .map_err(|e| if e.kind() == std::io::ErrorKind::NotFound {
+            with_suggestion(anyhow::anyhow!("xcodebuild not found"), "Install Xcode and run `xcode-select -s /Applications/Xcode.app`")
+        } else {
+            anyhow::Error::new(e).context("Failed to execute xcodebuild")
+        })?;
+
+    let mut result = parse_xcodebuild_test_output(&String::from_utf8_lossy(&output.stdout));
+    result.duration = started.elapsed();
+
+    // Failing tests also exit non-zero; only treat it as an error when nothing ran
+    if !output.status.success() && result.passed + result.failed + result.skipped == 0 {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let tail: Vec<&str> = stderr.lines().rev().take(10).collect();
+        bail!("xcodebuild failed before running tests:\n{}", tail.into_iter().rev().collect::<Vec<_>>().join("\n"));
+    }
+    Ok(result)
+}
+
+// ============== Orientation ==============
+
+/// Device orientation, in UIDeviceOrientation terms (LandscapeLeft has the home button on the right)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    Portrait,
+    LandscapeLeft,
+    LandscapeRight,
+    PortraitUpsideDown,
+}
+
+impl Orientation {
+    /// Position in the order a clockwise ("Rotate Right") turn steps through
+    fn clockwise_index(self) -> i32 {
+        match self {
+            Orientation::Portrait => 0,
+            Orientation::LandscapeRight => 1,
+            Orientation::PortraitUpsideDown => 2,
+            Orientation::LandscapeLeft => 3,
+        }
+    }
+
+    fn is_landscape(self) -> bool {
+        matches!(self, Orientation::LandscapeLeft | Orientation::LandscapeRight)
+    }
+}
+
+/// Quarter turns from `from` to `to`: positive is clockwise, negative counter-clockwise
+fn rotation_steps(from: Orientation, to: Orientation) -> i32 {
+    match (to.clockwise_index() - from.clockwise_index()).rem_euclid(4) {
+        3 => -1,
+        n => n,
+    }
+}
+
+/// Portrait vs landscape from screen pixel dimensions (the direction cannot be told apart)
+fn orientation_from_dimensions(width: u32, height: u32) -> Orientation {
+    if width > height {
+        Orientation::LandscapeLeft
+    } else {
+        Orientation::Portrait
+    }
+}
+
+/// Last orientation `rotate_device` set on each device, by UDID
+fn orientations_path() -> Result<PathBuf> {
+    Ok(app_support_dir()?.join("orientations.json"))
+}
+
+/// The recorded orientation when it agrees with the screen's aspect, otherwise the
+/// estimate from its dimensions; `None` when the screen is landscape and nothing
+/// recorded says which way round
+fn resolve_orientation(recorded: Option<Orientation>, width: u32, height: u32) -> Option<Orientation> {
+    let estimate = orientation_from_dimensions(width, height);
+    match recorded {
+        Some(recorded) if recorded.is_landscape() == estimate.is_landscape() => Some(recorded),
+        _ if estimate.is_landscape() => None,
+        _ => Some(estimate),
+    }
+}
+
+fn recorded_orientation(udid: &str) -> Result<Option<Orientation>> {
+    Ok(read_state_map::<Orientation>(&orientations_path()?)?.get(udid).copied())
+}
+
+fn record_orientation(udid: &str, orientation: Orientation) -> Result<()> {
+    let path = orientations_path()?;
+    let mut orientations = read_state_map::<Orientation>(&path)?;
+    orientations.insert(udid.to_string(), orientation);
+    write_state_map(&path, &orientations)
+}
+
+/// Current orientation: the last one `rotate_device` set, as long as the screen's aspect
+/// still matches it, else inferred from the screenshot size. The size alone only tells
+/// portrait from landscape, so without a record upside-down reads as `Portrait` and
+/// either landscape as `LandscapeLeft`.
+pub fn get_orientation(simulator: Option<&str>) -> Result<Orientation> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+    let (width, height) = screen_dimensions(simulator)?;
+    Ok(resolve_orientation(recorded_orientation(&udid)?, width, height)
+        .unwrap_or_else(|| orientation_from_dimensions(width, height)))
+}
+
+/// One quarter turn: `simctl ui rotate_device_*` where simctl has it, else Hardware > Rotate
+/// Left/Right through its Cmd+Arrow shortcut
+fn rotate_quarter(udid: &str, clockwise: bool, native: bool, simulator: Option<&str>) -> Result<()> {
+    let direction = if clockwise { "right" } else { "left" };
+    if native {
+        let subcommand = format!("rotate_device_{}", direction);
+        let output = simctl_exec(&["ui", udid, &subcommand])?;
+        if !output.status.success() {
+            bail!("Failed to rotate {}: {}", direction, String::from_utf8_lossy(&output.stderr));
+        }
+        return Ok(());
+    }
+
+    ensure_simulator_focused(simulator)?;
+    let key_code = if clockwise { 124 } else { 123 };
+    let script = format!(
+        "tell application \"System Events\" to tell process \"Simulator\" to key code {} using command down",
+        key_code
+    );
+    let output = Command::new("osascript").args(["-e", &script]).output()
+        .context("Failed to execute osascript")?;
+    if !output.status.success() {
+        bail!("Failed to rotate {}: {}", direction, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Rotate the simulator to `orientation`
+///
+/// The starting point is the orientation this function last set on the device. When
+/// there is none and the screen is landscape, which way round is unknown, so this fails
+/// rather than risk ending up 180° off; rotate to portrait in the Simulator first.
+pub fn rotate_device(orientation: Orientation, simulator: Option<&str>) -> Result<()> {
+    let udid = concrete_udid(&get_simulator_udid(simulator)?)?;
+    let (width, height) = screen_dimensions(simulator)?;
+    let Some(current) = resolve_orientation(recorded_orientation(&udid)?, width, height) else {
+        return Err(with_suggestion(
+            anyhow::anyhow!("The simulator is in landscape, but it cannot tell whether it is LandscapeLeft or LandscapeRight"),
+            "Rotate it back to portrait (Device > Rotate Left/Right) and try again",
+        ));
+    };
+    let steps = rotation_steps(current, orientation);
+    let native = simctl_help_mentions(&["ui"], "rotate_device");
+
+    for _ in 0..steps.abs() {
+        rotate_quarter(&udid, steps > 0, native, simulator)?;
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+    record_orientation(&udid, orientation)?;
+    Ok(())
+}
+
+// ============== Appearance ==============
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+impl Appearance {
+    fn simctl_arg(self) -> &'static str {
+        match self {
+            Appearance::Light => "light",
+            Appearance::Dark => "dark",
+        }
+    }
+
+    fn opposite(self) -> Self {
+        match self {
+            Appearance::Light => Appearance::Dark,
+            Appearance::Dark => Appearance::Light,
+        }
+    }
+}
+
+fn parse_appearance(stdout: &str) -> Result<Appearance> {
+    match stdout.trim() {
+        "light" => Ok(Appearance::Light),
+        "dark" => Ok(Appearance::Dark),
+        other => bail!("Simulator does not report an appearance ('{}'); is the runtime iOS 13 or later?", other),
+    }
+}
+
+/// Run `simctl ui <udid> appearance [mode]`; the `ui` subcommand needs Xcode 11.4+
+fn simctl_appearance(udid: &str, mode: Option<Appearance>) -> Result<String> {
+    let mut args = vec!["ui", udid, "appearance"];
+    args.extend(mode.map(Appearance::simctl_arg));
+
+    let output = simctl_exec(&args)?;
+    if !output.status.success() {
+        return Err(with_suggestion(
+            anyhow::anyhow!("simctl ui appearance failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+            "Appearance control requires Xcode 11.4 or later and a booted simulator",
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Switch the simulator between light and dark mode (Xcode 11.4+)
+pub fn set_appearance(mode: Appearance, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    simctl_appearance(&udid, Some(mode))?;
+    Ok(())
+}
+
+/// Current light/dark mode (Xcode 11.4+)
+pub fn get_appearance(simulator: Option<&str>) -> Result<Appearance> {
+    let udid = get_simulator_udid(simulator)?;
+    parse_appearance(&simctl_appearance(&udid, None)?)
+}
+
+/// Flip between light and dark mode; returns the mode now active
+pub fn toggle_appearance(simulator: Option<&str>) -> Result<Appearance> {
+    let next = get_appearance(simulator)?.opposite();
+    set_appearance(next, simulator)?;
+    Ok(next)
+}
+
+// ============== Slow Animations ==============
+
+/// Simulator's Debug ▸ Slow Animations menu item (Cmd+T)
+const SLOW_ANIMATIONS_MENU_ITEM: &str =
+    r#"menu item "Slow Animations" of menu 1 of menu bar item "Debug" of menu bar 1"#;
+
+/// Whether osascript's AXMenuItemMarkChar output means the item is checked
+fn menu_item_checked(stdout: &str) -> bool {
+    let mark = stdout.trim();
+    !mark.is_empty() && mark != "missing value"
+}
+
+/// Run a System Events script against the Simulator process
+fn simulator_menu_script(body: &str) -> Result<String> {
+    let script = format!(
+        "tell application \"System Events\" to tell process \"Simulator\"\n    {}\nend tell",
+        body
+    );
+    let output = Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .context("Failed to run osascript")?;
+    if !output.status.success() {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Simulator menu scripting failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+            "Grant the terminal Accessibility access in System Settings > Privacy & Security",
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Whether Debug ▸ Slow Animations is enabled for the focused simulator window
+///
+/// `simctl ui` (Xcode 11.4+) only covers appearance, contrast and content size; no
+/// Xcode release through 16 exposes slow animations there, so this reads the menu
+/// item's check mark in the Simulator app instead.
+pub fn get_slow_animations(simulator: Option<&str>) -> Result<bool> {
+    let _udid = get_simulator_udid(simulator)?;
+    ensure_simulator_focused(simulator)?;
+
+    let stdout = simulator_menu_script(&format!(
+        "get value of attribute \"AXMenuItemMarkChar\" of {}",
+        SLOW_ANIMATIONS_MENU_ITEM
+    ))?;
+    Ok(menu_item_checked(&stdout))
+}
+
+/// Slow down (or restore) UI animations, e.g. to debug transitions.
+/// Toggles the Simulator app's Debug ▸ Slow Animations item only when it differs
+/// from `enabled` (see `get_slow_animations`).
+pub fn set_slow_animations(enabled: bool, simulator: Option<&str>) -> Result<()> {
+    if get_slow_animations(simulator)? != enabled {
+        simulator_menu_script(&format!("click {}", SLOW_ANIMATIONS_MENU_ITEM))?;
+    }
+    Ok(())
+}
+
+// ============== Hardware Keyboard ==============
+
+/// Simulator's I/O ▸ Keyboard ▸ Connect Hardware Keyboard menu item (Shift+Cmd+K)
+const HARDWARE_KEYBOARD_MENU_ITEM: &str = r#"menu item "Connect Hardware Keyboard" of menu 1 of menu item "Keyboard" of menu 1 of menu bar item "I/O" of menu bar 1"#;
+
+/// Whether the host keyboard is connected to the focused simulator window as a
+/// hardware keyboard (which hides the software keyboard)
+pub fn get_hardware_keyboard_enabled(simulator: Option<&str>) -> Result<bool> {
+    let _udid = get_simulator_udid(simulator)?;
+    ensure_simulator_focused(simulator)?;
+
+    let stdout = simulator_menu_script(&format!(
+        "get value of attribute \"AXMenuItemMarkChar\" of {}",
+        HARDWARE_KEYBOARD_MENU_ITEM
+    ))?;
+    Ok(menu_item_checked(&stdout))
+}
+
+/// Connect or disconnect the host keyboard; disconnect it so the software keyboard
+/// shows up in layout tests and host typing cannot leak into the simulator.
+/// Like `set_slow_animations`, the menu item is only clicked when its state differs.
+pub fn set_hardware_keyboard(enabled: bool, simulator: Option<&str>) -> Result<()> {
+    if get_hardware_keyboard_enabled(simulator)? != enabled {
+        simulator_menu_script(&format!("click {}", HARDWARE_KEYBOARD_MENU_ITEM))?;
+    }
+    Ok(())
+}
+
+// ============== Memory Pressure ==============
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryPressureLevel {
+    Warning,
+    Urgent,
+    Critical,
+}
+
+impl MemoryPressureLevel {
+    fn name(self) -> &'static str {
+        match self {
+            MemoryPressureLevel::Warning => "warning",
+            MemoryPressureLevel::Urgent => "urgent",
+            MemoryPressureLevel::Critical => "critical",
+        }
+    }
+
+    /// libdispatch memory-pressure flag carried as the notification state
+    /// (WARN 0x2 and CRITICAL 0x4 are public; URGENT 0x8 is the private level between them)
+    fn memorystatus_value(self) -> u32 {
+        match self {
+            MemoryPressureLevel::Warning => 0x2,
+            MemoryPressureLevel::Urgent => 0x8,
+            MemoryPressureLevel::Critical => 0x4,
+        }
+    }
+}
+
+const MEMORYSTATUS_NOTIFICATION: &str = "com.apple.system.memorystatus";
+
+/// `notifyutil` arguments that set the pressure state and post the notification in one run
+fn notifyutil_memory_args(level: MemoryPressureLevel) -> Vec<String> {
+    vec![
+        "notifyutil".to_string(),
+        "-s".to_string(), MEMORYSTATUS_NOTIFICATION.to_string(), level.memorystatus_value().to_string(),
+        "-p".to_string(), MEMORYSTATUS_NOTIFICATION.to_string(),
+    ]
+}
+
+/// Whether `simctl help <topic>` mentions `needle`, to detect subcommands newer Xcodes add
+fn simctl_help_mentions(topic: &[&str], needle: &str) -> bool {
+    let mut args = vec!["help"];
+    args.extend_from_slice(topic);
+    simctl_exec(&args)
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains(needle) || String::from_utf8_lossy(&o.stderr).contains(needle))
+        .unwrap_or(false)
+}
+
+/// Send a memory pressure event to the simulator
+///
+/// Uses `simctl memory_pressure` when the installed simctl lists it (no Xcode release up to
+/// 16 ships one); otherwise posts `com.apple.system.memorystatus` with `notifyutil` inside
+/// the simulator, which needs `simctl spawn` (Xcode 9+).
+pub fn simulate_memory_pressure(level: MemoryPressureLevel, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = if simctl_help_mentions(&[], "memory_pressure") {
+        simctl_exec(&["memory_pressure", &udid, level.name()])?
+    } else {
+        let notify = notifyutil_memory_args(level);
+        let mut args = vec!["spawn", udid.as_str()];
+        args.extend(notify.iter().map(String::as_str));
+        simctl_exec(&args)?
+    };
+    if !output.status.success() {
+        bail!("Failed to simulate {} memory pressure: {}", level.name(), String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+// ============== Status Bar ==============
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryState {
+    Charging,
+    Discharging,
+    Full,
+}
+
+impl BatteryState {
+    fn simctl_arg(self) -> &'static str {
+        match self {
+            BatteryState::Charging => "charging",
+            BatteryState::Discharging => "discharging",
+            BatteryState::Full => "charged",
+        }
+    }
+}
+
+/// Status bar values to override; unset fields keep their current value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatusBarOverride {
+    pub time: Option<String>,
+    pub battery_level: Option<u8>,
+    pub battery_state: Option<BatteryState>,
+    pub cellular_bars: Option<u8>,
+    pub wifi_bars: Option<u8>,
+    pub carrier_name: Option<String>,
+}
+
+impl StatusBarOverride {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Displayed time, e.g. "9:41" (or an ISO date string)
+    pub fn time(mut self, time: &str) -> Self {
+        self.time = Some(time.to_string());
+        self
+    }
+
+    pub fn battery_level(mut self, level: u8) -> Self {
+        self.battery_level = Some(level);
+        self
+    }
+
+    pub fn battery_state(mut self, state: BatteryState) -> Self {
+        self.battery_state = Some(state);
+        self
+    }
+
+    pub fn cellular_bars(mut self, bars: u8) -> Self {
+        self.cellular_bars = Some(bars);
+        self
+    }
+
+    pub fn wifi_bars(mut self, bars: u8) -> Self {
+        self.wifi_bars = Some(bars);
+        self
+    }
+
+    pub fn carrier_name(mut self, name: &str) -> Self {
+        self.carrier_name = Some(name.to_string());
+        self
+    }
+
+    /// `simctl status_bar override` flags, validating ranges simctl would otherwise reject vaguely
+    fn simctl_args(&self) -> Result<Vec<String>> {
+        let mut args = Vec::new();
+        let mut flag = |name: &str, value: String| {
+            args.push(name.to_string());
+            args.push(value);
+        };
+
+        if let Some(time) = &self.time {
+            flag("--time", time.clone());
+        }
+        if let Some(level) = self.battery_level {
+            if level > 100 {
+                bail!("Invalid battery level {}: must be 0-100", level);
+            }
+            flag("--batteryLevel", level.to_string());
+        }
+        if let Some(state) = self.battery_state {
+            flag("--batteryState", state.simctl_arg().to_string());
+        }
+        if let Some(bars) = self.cellular_bars {
+            if bars > 4 {
+                bail!("Invalid cellular bars {}: must be 0-4", bars);
+            }
+            flag("--cellularMode", "active".to_string());
+            flag("--cellularBars", bars.to_string());
+        }
+        if let Some(bars) = self.wifi_bars {
+            if bars > 3 {
+                bail!("Invalid Wi-Fi bars {}: must be 0-3", bars);
+            }
+            flag("--wifiMode", "active".to_string());
+            flag("--wifiBars", bars.to_string());
+        }
+        if let Some(name) = &self.carrier_name {
+            flag("--operatorName", name.clone());
+        }
+
+        if args.is_empty() {
+            bail!("Status bar override has no fields set");
+        }
+        Ok(args)
+    }
+}
+
+/// Override status bar indicators (time, battery, signal, carrier) until cleared
+pub fn set_status_bar(overrides: StatusBarOverride, simulator: Option<&str>) -> Result<()> {
+    let flags = overrides.simctl_args()?;
+    let udid = get_simulator_udid(simulator)?;
+
+    let mut args = vec!["status_bar", udid.as_str(), "override"];
+    args.extend(flags.iter().map(String::as_str));
+    let output = simctl_exec(&args)?;
+    if !output.status.success() {
+        bail!("Failed to override status bar: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Remove all status bar overrides
+pub fn clear_status_bar(simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["status_bar", &udid, "clear"])?;
+    if !output.status.success() {
+        bail!("Failed to clear status bar: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Simulated battery via a status bar override
+pub fn set_battery(level: u8, state: BatteryState, simulator: Option<&str>) -> Result<()> {
+    if level > 100 {
+        bail!("Invalid battery level {}%: must be between 0 and 100", level);
+    }
+    set_status_bar(StatusBarOverride::new().battery_level(level).battery_state(state), simulator)
+}
+
+fn parse_battery_state(raw: &str) -> Option<BatteryState> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        // simctl prints its enum value: charging, charged, discharging
+        "charging" | "0" => Some(BatteryState::Charging),
+        "charged" | "full" | "1" => Some(BatteryState::Full),
+        "discharging" | "2" => Some(BatteryState::Discharging),
+        _ => None,
+    }
+}
+
+/// Battery override from `simctl status_bar list` ("Battery State: 2, Battery Level: 100, ...")
+fn parse_status_bar_battery(stdout: &str) -> Option<(u8, BatteryState)> {
+    let line = stdout.lines().find(|l| l.contains("Battery Level:"))?;
+    let field = |name: &str| line.split(',')
+        .find_map(|part| part.trim().strip_prefix(name))
+        .map(str::trim);
+    Some((field("Battery Level:")?.parse().ok()?, parse_battery_state(field("Battery State:")?)?))
+}
+
+/// Host battery from `pmset -g batt` ("-InternalBattery-0 (id=1)\t85%; charging; ..."), which
+/// the simulator mirrors when nothing is overridden
+fn parse_pmset_battery(stdout: &str) -> Option<(u8, BatteryState)> {
+    let line = stdout.lines().find(|l| l.contains("InternalBattery"))?;
+    let mut fields = line.split('\t').nth(1)?.split(';').map(str::trim);
+    let level = fields.next()?.trim_end_matches('%').parse().ok()?;
+    let state = match fields.next()? {
+        "charged" => BatteryState::Full,
+        "discharging" => BatteryState::Discharging,
+        // "charging", "finishing charge", "AC attached"
+        _ => BatteryState::Charging,
+    };
+    Some((level, state))
+}
+
+/// Current battery level and state: the status bar override if one is set, else the host battery
+pub fn get_battery(simulator: Option<&str>) -> Result<(u8, BatteryState)> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["status_bar", &udid, "list"])?;
+    if output.status.success() {
+        if let Some(battery) = parse_status_bar_battery(&String::from_utf8_lossy(&output.stdout)) {
+            return Ok(battery);
+        }
+    }
+
+    let output = Command::new("pmset").args(["-g", "batt"]).output()
+        .context("Failed to execute pmset")?;
+    parse_pmset_battery(&String::from_utf8_lossy(&output.stdout))
+        .context("No battery override is set and the host reports no battery")
+}
+
+// ============== Locale ==============
+
+/// Check `tag` is a BCP-47 language tag (language[-Script][-REGION], "_" also accepted)
+fn validate_bcp47(tag: &str) -> Result<()> {
+    let re = regex::Regex::new(r"^[A-Za-z]{2,3}(?:[-_][A-Za-z]{4})?(?:[-_](?:[A-Za-z]{2}|\d{3}))?$").unwrap();
+    if !re.is_match(tag) {
+        bail!("Invalid locale '{}': expected a BCP-47 tag such as en, en-US or zh-Hans-CN", tag);
+    }
+    Ok(())
+}
+
+/// Write a key into the simulator's global defaults domain, optionally rebooting to apply it
+/// (without the reboot the change takes effect the next time the simulator boots)
+fn write_global_default(udid: &str, key: &str, value_args: &[&str], reboot_after: bool, simulator: Option<&str>) -> Result<()> {
+    let mut args = vec!["spawn", udid, "defaults", "write", "-g", key];
+    args.extend_from_slice(value_args);
+
+    let output = simctl_exec(&args)?;
+    if !output.status.success() {
+        bail!("Failed to write {}: {}", key, String::from_utf8_lossy(&output.stderr));
+    }
+
+    if reboot_after {
+        reboot(simulator)?;
+    }
+    Ok(())
+}
+
+/// Set the region format (AppleLocale, stored as e.g. "en_US")
+pub fn set_locale(locale_id: &str, reboot_after: bool, simulator: Option<&str>) -> Result<()> {
+    validate_bcp47(locale_id)?;
+    let udid = get_simulator_udid(simulator)?;
+
+    let locale = locale_id.replace('-', "_");
+    write_global_default(&udid, "AppleLocale", &["-string", &locale], reboot_after, simulator)
+}
+
+/// Set the preferred UI language (AppleLanguages, stored as e.g. "en-US")
+pub fn set_language(lang_code: &str, reboot_after: bool, simulator: Option<&str>) -> Result<()> {
+    validate_bcp47(lang_code)?;
+    let udid = get_simulator_udid(simulator)?;
+
+    let language = lang_code.replace('_', "-");
+    write_global_default(&udid, "AppleLanguages", &["-array", &language], reboot_after, simulator)
+}
+
+// ============== Settings Toggles ==============
+
+/// Roles the accessibility tree reports for UISwitch controls
+const TOGGLE_ROLES: &[&str] = &["AXSwitch", "AXCheckBox"];
+
+/// First switch whose title or description matches `label` (case-insensitive)
+fn find_toggle<'a>(elements: &'a [UiElement], label: &str) -> Option<&'a UiElement> {
+    let label_lower = label.to_lowercase();
+    elements.iter().find(|e| {
+        TOGGLE_ROLES.contains(&e.role.as_str())
+            && (e.title.to_lowercase() == label_lower || e.description.to_lowercase() == label_lower)
+    })
+}
+
+/// Switch state from its accessibility value ("1"/"0", or "on"/"off")
+fn toggle_state(elem: &UiElement) -> Option<bool> {
+    match elem.value.trim().to_lowercase().as_str() {
+        "1" | "on" | "true" => Some(true),
+        "0" | "off" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn locate_toggle(label: &str) -> Result<(UiElement, bool)> {
+    let elements = get_accessibility_elements()?;
+    let toggle = find_toggle(&elements, label)
+        .with_context(|| format!("Toggle '{}' not found on screen", label))?
+        .clone();
+    let state = toggle_state(&toggle)
+        .with_context(|| format!("Toggle '{}' has unexpected value '{}'", label, toggle.value))?;
+    Ok((toggle, state))
+}
+
+/// Read the state of an on-screen switch by its label
+pub fn get_toggle(label: &str, _simulator: Option<&str>) -> Result<bool> {
+    locate_toggle(label).map(|(_, state)| state)
+}
+
+/// Flip an on-screen switch to `enabled` if it is not already in that state;
+/// returns whether it had to be flipped
+pub fn set_toggle(label: &str, enabled: bool, _simulator: Option<&str>) -> Result<bool> {
+    let (toggle, state) = locate_toggle(label)?;
+    if state == enabled {
+        return Ok(false);
+    }
+
+    click_screen_point(toggle.x + toggle.width / 2, toggle.y + toggle.height / 2);
+    Ok(true)
+}
+
+/// Open Settings > Battery and wait for it to render
+fn open_battery_settings(simulator: Option<&str>) -> Result<()> {
+    open_url("App-Prefs:BATTERY_USAGE", simulator)?;
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    Ok(())
+}
+
+/// Turn Low Power Mode on or off via Settings > Battery
+pub fn set_low_power_mode(enabled: bool, simulator: Option<&str>) -> Result<()> {
+    open_battery_settings(simulator)?;
+    set_toggle("Low Power Mode", enabled, simulator).map(|_| ())
+}
+
+/// Read the Low Power Mode switch from Settings > Battery
+pub fn get_low_power_mode(simulator: Option<&str>) -> Result<bool> {
+    open_battery_settings(simulator)?;
+    get_toggle("Low Power Mode", simulator)
+}
+
+// ============== User Defaults ==============
+
+/// A value accepted by `defaults write`
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserDefaultValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl UserDefaultValue {
+    /// Type flag and value arguments for `defaults write`
+    fn defaults_args(&self) -> [String; 2] {
+        match self {
+            UserDefaultValue::String(v) => ["-string".to_string(), v.clone()],
+            UserDefaultValue::Int(v) => ["-int".to_string(), v.to_string()],
+            UserDefaultValue::Float(v) => ["-float".to_string(), v.to_string()],
+            UserDefaultValue::Bool(v) => ["-bool".to_string(), v.to_string()],
+        }
+    }
+}
+
+/// Read a key from an app's UserDefaults; `None` if the key is not set
+pub fn read_user_default(bundle_id: &str, key: &str, simulator: Option<&str>) -> Result<Option<String>> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["spawn", &udid, "defaults", "read", bundle_id, key])?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("does not exist") {
+            return Ok(None);
+        }
+        bail!("Failed to read default {}.{}: {}", bundle_id, key, stderr);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+/// Write a key into an app's UserDefaults (relaunch the app to pick it up)
+pub fn write_user_default(bundle_id: &str, key: &str, value: &UserDefaultValue, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+    let [type_flag, value_arg] = value.defaults_args();
+
+    let output = simctl_exec(&["spawn", &udid, "defaults", "write", bundle_id, key, &type_flag, &value_arg])?;
+
+    if !output.status.success() {
+        bail!("Failed to write default {}.{}: {}", bundle_id, key, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Read the app's launch counter stored under `key`; an unset key counts as 0
+pub fn get_app_open_count(bundle_id: &str, key: &str, simulator: Option<&str>) -> Result<u32> {
+    match read_user_default(bundle_id, key, simulator)? {
+        Some(raw) => raw.parse()
+            .with_context(|| format!("{}.{} is not a launch count: '{}'", bundle_id, key, raw)),
+        None => Ok(0),
+    }
+}
+
+/// Overwrite the app's launch counter, e.g. 0 to force first-launch onboarding
+pub fn set_app_open_count(bundle_id: &str, key: &str, count: u32, simulator: Option<&str>) -> Result<()> {
+    write_user_default(bundle_id, key, &UserDefaultValue::Int(count as i64), simulator)
+}
+
+fn plist_to_json(value: plist::Value) -> serde_json::Value {
+    use base64::Engine;
+
+    match value {
+        plist::Value::Dictionary(dict) => dict.into_iter()
+            .map(|(k, v)| (k, plist_to_json(v)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        plist::Value::Array(items) => items.into_iter().map(plist_to_json).collect(),
+        plist::Value::Boolean(b) => b.into(),
+        plist::Value::Integer(i) => i.as_signed().map(Into::into)
+            .or_else(|| i.as_unsigned().map(Into::into))
+            .unwrap_or_default(),
+        plist::Value::Real(f) => f.into(),
+        plist::Value::String(s) => s.into(),
+        plist::Value::Date(d) => d.to_xml_format().into(),
+        plist::Value::Data(bytes) => base64::engine::general_purpose::STANDARD.encode(bytes).into(),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// Render JSON as an XML property list fragment, the form `defaults write` accepts for nested values
+fn json_to_plist_xml(value: &serde_json::Value) -> Result<String> {
+    Ok(match value {
+        serde_json::Value::Object(map) => {
+            let mut xml = String::from("<dict>");
+            for (key, value) in map {
+                xml += &format!("<key>{}</key>{}", xml_escape(key), json_to_plist_xml(value)?);
+            }
+            xml + "</dict>"
+        }
+        serde_json::Value::Array(items) => {
+            let items: Result<Vec<String>> = items.iter().map(json_to_plist_xml).collect();
+            format!("<array>{}</array>", items?.concat())
+        }
+        serde_json::Value::String(s) => format!("<string>{}</string>", xml_escape(s)),
+        serde_json::Value::Bool(b) => if *b { "<true/>".to_string() } else { "<false/>".to_string() },
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => format!("<integer>{}</integer>", i),
+            None => format!("<real>{}</real>", n),
+        },
+        serde_json::Value::Null => bail!("UserDefaults cannot store null"),
+    })
+}
+
+/// Every key of an app's UserDefaults as a JSON object (empty if the domain does not exist yet)
+pub fn list_user_defaults(bundle_id: &str, simulator: Option<&str>) -> Result<serde_json::Value> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["spawn", &udid, "defaults", "export", bundle_id, "-"])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("does not exist") {
+            return Ok(serde_json::json!({}));
+        }
+        bail!("Failed to export defaults for {}: {}", bundle_id, stderr);
+    }
+
+    let domain = plist::Value::from_reader_xml(output.stdout.as_slice())
+        .with_context(|| format!("Failed to parse defaults for {}", bundle_id))?;
+    Ok(plist_to_json(domain))
+}
+
+/// Read a key with its plist type preserved; `Null` if the key is not set
+pub fn read_user_defaults(bundle_id: &str, key: &str, simulator: Option<&str>) -> Result<serde_json::Value> {
+    Ok(list_user_defaults(bundle_id, simulator)?
+        .get(key)
+        .cloned()
+        .unwrap_or(serde_json::Value::Null))
+}
+
+/// Write a JSON value: scalars use `-string`/`-int`/`-float`/`-bool`; objects and arrays are passed
+/// as XML plist (`-dict` would store every nested value as a string)
+pub fn write_user_defaults(bundle_id: &str, key: &str, value: &serde_json::Value, simulator: Option<&str>) -> Result<()> {
+    if !(value.is_object() || value.is_array()) {
+        return write_user_default(bundle_id, key, &user_default_from_json(key, value)?, simulator);
+    }
+
+    let udid = get_simulator_udid(simulator)?;
+    let xml = json_to_plist_xml(value)?;
+
+    let output = simctl_exec(&["spawn", &udid, "defaults", "write", bundle_id, key, &xml])?;
+    if !output.status.success() {
+        bail!("Failed to write default {}.{}: {}", bundle_id, key, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Remove a key from an app's UserDefaults; deleting an unset key is not an error
+pub fn delete_user_defaults(bundle_id: &str, key: &str, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["spawn", &udid, "defaults", "delete", bundle_id, key])?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.contains("not found") && !stderr.contains("does not exist") {
+            bail!("Failed to delete default {}.{}: {}", bundle_id, key, stderr);
+        }
+    }
+    Ok(())
+}
+
+// ============== Fixtures ==============
+
+/// Test data fixture; the `"target"` field selects how it is injected
+#[derive(Debug, Deserialize)]
+#[serde(tag = "target", rename_all = "snake_case")]
+enum Fixture {
+    /// `{"target": "user_defaults", "values": {"key": value, ...}}`
+    #[serde(alias = "userdefaults")]
+    UserDefaults { values: serde_json::Map<String, serde_json::Value> },
+    /// `{"target": "file", "path": "Documents/x.json", "contents": ...}` or
+    /// `"source": "local/file"` (relative to the fixture); non-string contents are written as JSON
+    File {
+        path: String,
+        #[serde(default)]
+        contents: Option<serde_json::Value>,
+        #[serde(default)]
+        source: Option<String>,
+    },
+    /// `{"target": "sqlite", "database": "Library/app.sqlite", "statements": ["INSERT ..."]}`
+    Sqlite { database: String, statements: Vec<String> },
+}
+
+/// Map a JSON scalar onto the `defaults write` type it fits
+fn user_default_from_json(key: &str, value: &serde_json::Value) -> Result<UserDefaultValue> {
+    match value {
+        serde_json::Value::String(v) => Ok(UserDefaultValue::String(v.clone())),
+        serde_json::Value::Bool(v) => Ok(UserDefaultValue::Bool(*v)),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Ok(UserDefaultValue::Int(i)),
+            None => Ok(UserDefaultValue::Float(n.as_f64().unwrap_or_default())),
+        },
+        other => bail!("Value for '{}' must be a string, number or bool, got {}", key, other),
+    }
+}
+
+/// Load test data from a JSON fixture into an app: UserDefaults keys, a file
+/// in its data container, or SQL statements run against a database there
+pub fn inject_fixture(bundle_id: &str, fixture_path: &str, simulator: Option<&str>) -> Result<()> {
+    let raw = std::fs::read_to_string(fixture_path)
+        .with_context(|| format!("Failed to read fixture {}", fixture_path))?;
+    let fixture: Fixture = serde_json::from_str(&raw)
+        .with_context(|| format!("Invalid fixture {}", fixture_path))?;
+
+    match fixture {
+        Fixture::UserDefaults { values } => {
+            for (key, value) in &values {
+                write_user_default(bundle_id, key, &user_default_from_json(key, value)?, simulator)?;
+            }
+        }
+        Fixture::File { path, contents, source } => match (contents, source) {
+            (Some(contents), None) => {
+                let text = match contents {
+                    serde_json::Value::String(text) => text,
+                    other => serde_json::to_string_pretty(&other)?,
+                };
+                let tmp = temp_file("fixture", ".tmp")?;
+                std::fs::write(tmp.path(), text)?;
+                push_file(&tmp.path().to_string_lossy(), &path, Some(bundle_id), simulator)?;
+            }
+            (None, Some(source)) => {
+                let base = Path::new(fixture_path).parent().unwrap_or(Path::new("."));
+                push_file(&base.join(source).to_string_lossy(), &path, Some(bundle_id), simulator)?;
+            }
+            _ => bail!("File fixture needs exactly one of \"contents\" or \"source\""),
+        },
+        Fixture::Sqlite { database, statements } => {
+            let udid = get_simulator_udid(simulator)?;
+            let root = container_root(&udid, Some(bundle_id), &ContainerKind::Data)?;
+            let db = resolve_container_path(&root, &database)?;
+
+            let sql = statements.join(";\n") + ";\n";
+            let output = pipe_to_command("sqlite3", &["-bail", &db.to_string_lossy()], &sql)?;
+            if !output.status.success() {
+                bail!("sqlite3 failed on {}: {}", db.display(), String::from_utf8_lossy(&output.stderr));
+            }
+        }
+    }
+    Ok(())
+}
+
+// ============== File Transfer ==============
+
+/// Which container of an app a remote path is rooted at
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerKind {
+    App,
+    Data,
+    /// Shared app group container, e.g. `group.com.example.shared`
+    Group(String),
+}
+
+impl ContainerKind {
+    fn simctl_arg(&self) -> &str {
+        match self {
+            ContainerKind::App => "app",
+            ContainerKind::Data => "data",
+            ContainerKind::Group(id) => id,
+        }
+    }
+}
+
+/// Remote path split into the container it lives in and the path inside it
+#[derive(Debug, PartialEq)]
+struct RemotePath {
+    /// `None` means the simulator's own data directory
+    bundle_id: Option<String>,
+    kind: ContainerKind,
+    path: String,
+}
+
+/// Parse `@<bundle_id>[/app|/data|/groups|/<group id>]:<path>`; anything
+/// without the `@` prefix is relative to the simulator's data directory.
+/// With `/groups` the first path component names the group.
+fn parse_remote_path(remote: &str) -> Result<RemotePath> {
+    let Some(rest) = remote.strip_prefix('@') else {
+        return Ok(RemotePath { bundle_id: None, kind: ContainerKind::Data, path: remote.to_string() });
+    };
+
+    let (spec, path) = rest.split_once(':')
+        .with_context(|| format!("Invalid remote '{}': expected @<bundle_id>:<path>", remote))?;
+    let (bundle_id, container) = spec.split_once('/').unwrap_or((spec, "data"));
+    if bundle_id.is_empty() {
+        bail!("Invalid remote '{}': missing bundle ID", remote);
+    }
+
+    let (kind, path) = match container {
+        "data" => (ContainerKind::Data, path.to_string()),
+        "app" => (ContainerKind::App, path.to_string()),
+        "groups" => {
+            let (group, inner) = path.trim_start_matches('/').split_once('/')
+                .with_context(|| format!("Invalid remote '{}': expected @{}/groups:<group id>/<path>", remote, bundle_id))?;
+            (ContainerKind::Group(group.to_string()), inner.to_string())
+        }
+        group if group.starts_with("group.") => (ContainerKind::Group(group.to_string()), path.to_string()),
+        other => bail!("Unknown container '{}': use app, data, groups, or a group.* identifier", other),
+    };
+
+    Ok(RemotePath { bundle_id: Some(bundle_id.to_string()), kind, path })
+}
+
+/// Root directory of an app's container, or of the whole simulator when no
+/// bundle ID is given
+fn container_root(udid: &str, bundle_id: Option<&str>, kind: &ContainerKind) -> Result<PathBuf> {
+    let output = match bundle_id {
+        Some(bundle) => {
+            let output = simctl_exec(&["get_app_container", udid, bundle, kind.simctl_arg()])?;
+            if !output.status.success() {
+                return Err(IosError::ContainerNotFound {
+                    bundle_id: bundle.to_string(),
+                    container: kind.simctl_arg().to_string(),
+                    message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                }.into());
+            }
+            output
+        }
+        None => simctl_exec(&["getenv", udid, "HOME"])?,
+    };
+
+    if !output.status.success() {
+        bail!("Failed to locate data container: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if root.is_empty() {
+        bail!("simctl returned an empty container path");
+    }
+    Ok(PathBuf::from(root))
+}
+
+/// Host path of an app's bundle, data or app group container
+pub fn get_app_container(bundle_id: &str, container: ContainerKind, simulator: Option<&str>) -> Result<PathBuf> {
+    let udid = get_simulator_udid(simulator)?;
+    container_root(&udid, Some(bundle_id), &container)
+}
+
+/// Reveal an app's container in Finder
+pub fn open_app_container(bundle_id: &str, container: ContainerKind, simulator: Option<&str>) -> Result<()> {
+    let path = get_app_container(bundle_id, container, simulator)?;
+
+    let status = Command::new("open").arg(&path).status()
+        .context("Failed to execute open")?;
+    if !status.success() {
+        bail!("Failed to open {}", path.display());
+    }
+    Ok(())
+}
+
+/// Resolve a (possibly `@`-prefixed) remote path to a host path, looking up
+/// container roots through `lookup`
+fn resolve_remote_path(
+    remote: &str,
+    lookup: impl FnOnce(Option<&str>, &ContainerKind) -> Result<PathBuf>,
+) -> Result<PathBuf> {
+    let parsed = parse_remote_path(remote)?;
+    let root = lookup(parsed.bundle_id.as_deref(), &parsed.kind)?;
+    resolve_container_path(&root, &parsed.path)
+}
+
+/// Resolve `remote` inside `root`; a leading `/` is relative to the root and
+/// `..` components are rejected so the path cannot escape the container
+fn resolve_container_path(root: &Path, remote: &str) -> Result<PathBuf> {
+    let relative = Path::new(remote.trim_start_matches('/'));
+    let mut resolved = root.to_path_buf();
+
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => bail!("Invalid remote path '{}': must stay inside the container", remote),
+        }
+    }
+
+    if resolved == root {
+        bail!("Remote path '{}' must name a file inside the container", remote);
+    }
+    Ok(resolved)
+}
+
+/// Push file into the simulator's data directory, or into an app's data
+/// container when `bundle_id` is given (or `remote` is `@<bundle_id>:<path>`); returns the bytes copied
+pub fn push_file(local: &str, remote: &str, bundle_id: Option<&str>, simulator: Option<&str>) -> Result<u64> {
+    let udid = get_simulator_udid(simulator)?;
+    let dest = match bundle_id {
+        Some(bundle) => resolve_container_path(&container_root(&udid, Some(bundle), &ContainerKind::Data)?, remote)?,
+        None => resolve_remote_path(remote, |bundle, kind| container_root(&udid, bundle, kind))?,
+    };
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::copy(local, &dest)
+        .with_context(|| format!("Failed to copy {} to {}", local, dest.display()))
+}
+
+/// Add a photo or video to the simulator's Photos library
+pub fn push_media(local: &str, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["addmedia", &udid, local])?;
+
+    if !output.status.success() {
+        bail!("Failed to add media: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Expand glob patterns; plain paths pass through so a missing file is reported per file
+fn expand_media_paths(paths: &[&str]) -> Vec<std::result::Result<String, (String, String)>> {
+    let mut expanded = Vec::new();
+    for &pattern in paths {
+        if !pattern.contains(['*', '?', '[']) {
+            expanded.push(Ok(pattern.to_string()));
+            continue;
+        }
+        match glob::glob(pattern) {
+            Ok(matches) => {
+                let before = expanded.len();
+                for entry in matches {
+                    expanded.push(entry
+                        .map(|p| p.to_string_lossy().to_string())
+                        .map_err(|e| (e.path().to_string_lossy().to_string(), e.to_string())));
+                }
+                if expanded.len() == before {
+                    expanded.push(Err((pattern.to_string(), "no files match".to_string())));
+                }
+            }
+            Err(e) => expanded.push(Err((pattern.to_string(), format!("invalid pattern: {}", e)))),
+        }
+    }
+    expanded
+}
+
+/// Add photos and videos to the Photos library, expanding glob patterns. Each file is added
+/// separately; the returned list holds `(path, error)` for every file that failed
+pub fn add_media(paths: &[&str], simulator: Option<&str>) -> Result<Vec<(String, String)>> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let mut failures = Vec::new();
+    for path in expand_media_paths(paths) {
+        let path = match path {
+            Ok(path) => path,
+            Err(failure) => {
+                failures.push(failure);
+                continue;
+            }
+        };
+        match simctl_exec(&["addmedia", &udid, &path]) {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => failures.push((path, String::from_utf8_lossy(&output.stderr).trim().to_string())),
+            Err(e) => failures.push((path, e.to_string())),
+        }
+    }
+    Ok(failures)
+}
+
+/// Download a photo or video and add it to the Photos library
+pub fn add_media_from_url(url: &str, simulator: Option<&str>) -> Result<()> {
+    let parsed = validate_url(url)?;
+    // simctl picks the media type from the extension
+    let extension = Path::new(parsed.path())
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{}", e))
+        .context("Media URL has no file extension (e.g. .jpg, .png, .mp4)")?;
+
+    let temp = temp_file("ios_media_", &extension)?;
+    let temp_path = temp.path().to_string_lossy().to_string();
+    let output = Command::new("curl")
+        .args(["-fsSL", "--max-time", "60", "-o", &temp_path, parsed.as_str()])
+        .output()
+        .context("Failed to execute curl")?;
+    if !output.status.success() {
+        bail!("Failed to download {}: {}", url, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    if let Some((_, error)) = add_media(&[&temp_path], simulator)?.into_iter().next() {
+        bail!("Failed to add media from {}: {}", url, error);
+    }
+    Ok(())
+}
+
+/// Pull file out of the simulator; `remote` is relative to the simulator's
+/// data directory, or `@<bundle_id>[/app|/data|/groups]:<path>` for an app container;
+/// returns the bytes copied
+pub fn pull_file(remote: &str, local: &str, simulator: Option<&str>) -> Result<u64> {
+    let udid = get_simulator_udid(simulator)?;
+    let source = resolve_remote_path(remote, |bundle, kind| container_root(&udid, bundle, kind))?;
+
+    std::fs::copy(&source, local)
+        .with_context(|| format!("Failed to copy {} to {}", source.display(), local))
+}
+
+// ============== Privacy Permissions ==============
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionAction {
+    Grant,
+    Revoke,
+    Reset,
+}
+
+impl PermissionAction {
+    fn simctl_arg(self) -> &'static str {
+        match self {
+            PermissionAction::Grant => "grant",
+            PermissionAction::Revoke => "revoke",
+            PermissionAction::Reset => "reset",
+        }
+    }
+}
+
+/// Service names accepted by `simctl privacy` (support varies by Xcode version)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionService {
+    Camera,
+    Microphone,
+    Photos,
+    Location,
+    Contacts,
+    Calendar,
+    Reminders,
+    Motion,
+    Health,
+    HomeKit,
+    MediaLibrary,
+    Siri,
+    SpeechRecognition,
+}
+
+impl PermissionService {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PermissionService::Camera => "camera",
+            PermissionService::Microphone => "microphone",
+            PermissionService::Photos => "photos",
+            PermissionService::Location => "location",
+            PermissionService::Contacts => "contacts",
+            PermissionService::Calendar => "calendar",
+            PermissionService::Reminders => "reminders",
+            PermissionService::Motion => "motion",
+            PermissionService::Health => "health",
+            PermissionService::HomeKit => "homekit",
+            PermissionService::MediaLibrary => "media-library",
+            PermissionService::Siri => "siri",
+            PermissionService::SpeechRecognition => "speech-recognition",
+        }
+    }
+}
+
+impl std::fmt::Display for PermissionService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn privacy_args<'a>(udid: &'a str, action: PermissionAction, service: &'a str, bundle_id: &'a str) -> [&'a str; 5] {
+    ["privacy", udid, action.simctl_arg(), service, bundle_id]
+}
+
+/// Grant, revoke or reset a privacy permission for an app (`service` e.g. `PermissionService::Camera.as_str()`)
+pub fn set_permission(bundle_id: &str, service: &str, action: PermissionAction, simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&privacy_args(&udid, action, service, bundle_id))?;
+    if !output.status.success() {
+        return Err(with_suggestion(
+            anyhow::anyhow!(
+                "Failed to {} {} for {}: {}",
+                action.simctl_arg(), service, bundle_id, String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            "Run `xcrun simctl privacy help` for the services this Xcode supports",
+        ));
+    }
+    Ok(())
+}
+
+// ============== Push Notifications ==============
+
+/// Builder for the `aps`-wrapped payload `simctl push` expects
+#[derive(Debug, Clone, Default)]
+pub struct NotificationPayload {
+    title: Option<String>,
+    body: Option<String>,
+    badge: Option<u32>,
+    sound: Option<String>,
+    custom: serde_json::Map<String, serde_json::Value>,
+}
+
+impl NotificationPayload {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title(mut self, s: &str) -> Self {
+        self.title = Some(s.to_string());
+        self
+    }
+
+    pub fn body(mut self, s: &str) -> Self {
+        self.body = Some(s.to_string());
+        self
+    }
+
+    pub fn badge(mut self, n: u32) -> Self {
+        self.badge = Some(n);
+        self
+    }
+
+    pub fn sound(mut self, name: &str) -> Self {
+        self.sound = Some(name.to_string());
+        self
+    }
+
+    /// Top-level key delivered to the app alongside `aps` (the key `aps` itself is reserved)
+    pub fn custom_data(mut self, key: &str, value: serde_json::Value) -> Self {
+        self.custom.insert(key.to_string(), value);
+        self
+    }
+
+    pub fn build(&self) -> serde_json::Value {
+        let mut alert = serde_json::Map::new();
+        if let Some(title) = &self.title {
+            alert.insert("title".to_string(), title.clone().into());
+        }
+        if let Some(body) = &self.body {
+            alert.insert("body".to_string(), body.clone().into());
+        }
+
+        let mut aps = serde_json::Map::new();
+        if !alert.is_empty() {
+            aps.insert("alert".to_string(), alert.into());
+        }
+        if let Some(badge) = self.badge {
+            aps.insert("badge".to_string(), badge.into());
+        }
+        if let Some(sound) = &self.sound {
+            aps.insert("sound".to_string(), sound.clone().into());
+        }
+
+        let mut payload: serde_json::Map<String, serde_json::Value> = self.custom.iter()
+            .filter(|(key, _)| key.as_str() != "aps")
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        payload.insert("aps".to_string(), aps.into());
+        payload.into()
+    }
+}
+
+/// Deliver a push notification to an installed app
+pub fn push_notification(bundle_id: &str, payload: &serde_json::Value, simulator: Option<&str>) -> Result<()> {
+    if payload.get("aps").is_none() {
+        return Err(with_suggestion(
+            anyhow::anyhow!("Push payload has no \"aps\" dictionary"),
+            "Build the payload with NotificationPayload or wrap it in {\"aps\": {...}}",
+        ));
+    }
+    let udid = get_simulator_udid(simulator)?;
+
+    let temp = temp_file("ios_push_", ".apns")?;
+    std::fs::write(temp.path(), serde_json::to_vec(payload)?)
+        .context("Failed to write push payload")?;
+    let payload_path = temp.path().to_string_lossy().to_string();
+
+    let output = simctl_exec(&["push", &udid, bundle_id, &payload_path])?;
+    if !output.status.success() {
+        bail!("Failed to push notification to {}: {}", bundle_id, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// APNs environment an app registers its device token with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApnsEnvironment {
+    Sandbox,
+    Production,
+}
+
+impl ApnsEnvironment {
+    /// Value of the `aps-environment` entitlement
+    fn entitlement_value(self) -> &'static str {
+        match self {
+            ApnsEnvironment::Sandbox => "development",
+            ApnsEnvironment::Production => "production",
+        }
+    }
+}
+
+/// `existing` entitlements (if any) with `aps-environment` set to `env`
+fn with_aps_environment(existing: Option<plist::Value>, env: ApnsEnvironment) -> plist::Dictionary {
+    let mut entitlements = existing.and_then(plist::Value::into_dictionary).unwrap_or_default();
+    entitlements.insert("aps-environment".to_string(), env.entitlement_value().into());
+    entitlements
+}
+
+/// Point an installed app at the sandbox or production APNs environment
+///
+/// Re-signs the app bundle in place (ad-hoc, which the simulator accepts) with its
+/// current entitlements plus `aps-environment`. Relaunch the app afterwards so it
+/// registers for a token against the new environment. Limitations: this only
+/// affects real remote notifications, which need Xcode 14+ on Apple silicon or a T2
+/// Mac, matching server credentials, and is undone by reinstalling the app. For
+/// local testing, `push_notification` (`simctl push`) delivers a crafted payload
+/// directly and bypasses APNs altogether.
+pub fn set_apns_environment(env: ApnsEnvironment, bundle_id: &str, simulator: Option<&str>) -> Result<()> {
+    let app = get_app_container(bundle_id, ContainerKind::App, simulator)?;
+
+    let current = Command::new("codesign")
+        .args(["-d", "--entitlements", ":-"])
+        .arg(&app)
+        .output()
+        .context("Failed to run codesign")?;
+    let existing = plist::Value::from_reader_xml(current.stdout.as_slice()).ok();
+
+    let file = temp_file("ios_entitlements_", ".plist")?;
+    plist::Value::Dictionary(with_aps_environment(existing, env))
+        .to_file_xml(file.path())
+        .context("Failed to write entitlements")?;
+
+    let output = Command::new("codesign")
+        .args(["--force", "--sign", "-", "--entitlements"])
+        .arg(file.path())
+        .arg(&app)
+        .output()
+        .context("Failed to run codesign")?;
+    if !output.status.success() {
+        bail!("Failed to re-sign {}: {}", bundle_id, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+// ============== Clipboard ==============
+
+/// Get clipboard content (host clipboard since simulator shares it)
+pub fn get_clipboard(_simulator: Option<&str>) -> Result<String> {
+    let output = Command::new("pbpaste")
+        .output()
+        .context("Failed to execute pbpaste")?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Print clipboard content
+pub fn print_clipboard(simulator: Option<&str>) -> Result<()> {
+    println!("{}", get_clipboard(simulator)?);
+    Ok(())
+}
+
+/// Set clipboard content (host clipboard since simulator shares it)
+pub fn set_clipboard(text: &str, _simulator: Option<&str>) -> Result<()> {
+    pipe_to_command("pbcopy", &[], text)?;
+    Ok(())
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn is_png(data: &[u8]) -> bool {
+    data.starts_with(PNG_SIGNATURE)
+}
+
+/// Run an AppleScript `on run argv` handler with a single path argument
+fn osascript_with_path(script: &str, path: &std::path::Path) -> Result<std::process::Output> {
+    Command::new("osascript")
+        .args(["-e", script])
+        .arg(path)
+        .output()
+        .context("Failed to run osascript")
+}
+
+/// Put a screenshot of the simulator on the host clipboard as PNG,
+/// ready to paste inside the simulator
+pub fn copy_screenshot_to_clipboard(simulator: Option<&str>) -> Result<()> {
+    let png = screenshot(simulator)?;
+    let file = temp_file("ios_clipboard_", ".png")?;
+    std::fs::write(file.path(), &png).context("Failed to write screenshot")?;
+
+    let script = r#"on run argv
+    set the clipboard to (read (POSIX file (item 1 of argv)) as «class PNGf»)
+end run"#;
+    let output = osascript_with_path(script, file.path())?;
+    if !output.status.success() {
+        bail!("Failed to copy screenshot to clipboard: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// PNG bytes of the image on the host clipboard, via `pngpaste` when installed
+/// and AppleScript otherwise
+pub fn get_clipboard_as_image(_simulator: Option<&str>) -> Result<Vec<u8>> {
+    if let Ok(output) = Command::new("pngpaste").arg("-").output() {
+        if output.status.success() && is_png(&output.stdout) {
+            return Ok(output.stdout);
+        }
+    }
+
+    let file = temp_file("ios_clipboard_", ".png")?;
+    let script = r#"on run argv
+    set png to (the clipboard as «class PNGf»)
+    set f to open for access (POSIX file (item 1 of argv)) with write permission
+    set eof f to 0
+    write png to f
+    close access f
+end run"#;
+    let output = osascript_with_path(script, file.path())?;
+    let data = std::fs::read(file.path()).unwrap_or_default();
+    if !output.status.success() || !is_png(&data) {
+        bail!("Clipboard does not contain an image");
+    }
+    Ok(data)
+}
+
+// ============== Log Streaming ==============
+
+/// A running `log stream` process and the thread forwarding its lines
+pub struct LogStreamHandle {
+    child: std::process::Child,
+    reader: std::thread::JoinHandle<()>,
+}
+
+impl LogStreamHandle {
+    /// Kill the log stream and wait for the reader thread to drain
+    pub fn stop(self) -> Result<()> {
+        let LogStreamHandle { mut child, reader } = self;
+        // Already exited (e.g. simulator shut down) is fine
+        let _ = child.kill();
+        child.wait().context("Failed to wait for log stream")?;
+        reader.join().map_err(|_| anyhow::anyhow!("Log stream reader thread panicked"))?;
+        Ok(())
+    }
+}
+
+/// Stream live device logs, calling `callback` with each line from a background thread
+pub fn stream_logs<F>(filter: Option<&str>, callback: F, simulator: Option<&str>) -> Result<LogStreamHandle>
+where
+    F: Fn(&str) + Send + 'static,
+{
+    stream_logs_filtered(filter, None, None, callback, simulator)
+}
+
+/// [`stream_logs`] with the level and subsystem filters of [`get_logs`]
+pub fn stream_logs_filtered<F>(
+    filter: Option<&str>,
+    level: Option<LogLevel>,
+    subsystem: Option<&str>,
+    callback: F,
+    simulator: Option<&str>,
+) -> Result<LogStreamHandle>
+where
+    F: Fn(&str) + Send + 'static,
+{
+    use std::io::BufRead;
+
+    let udid = get_simulator_udid(simulator)?;
+
+    let filter_args = log_filter_args(true, filter, level, subsystem);
+    let mut args = vec!["spawn", &udid, "log", "stream", "--style", "compact"];
+    args.extend(filter_args.iter().map(String::as_str));
+
+    let mut child = simctl_command(&args)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .context("Failed to start log stream")?;
+    let stdout = child.stdout.take().context("Log stream has no stdout")?;
+
+    let reader = std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout).lines() {
+            match line {
+                Ok(line) => callback(&line),
+                Err(_) => break,
+            }
+        }
+    });
+
+    Ok(LogStreamHandle { child, reader })
+}
+
+/// Stream live device logs into `writer` (e.g. a file), one line per entry
+pub fn stream_logs_to_writer(
+    filter: Option<&str>,
+    writer: Box<dyn std::io::Write + Send>,
+    simulator: Option<&str>,
+) -> Result<LogStreamHandle> {
+    let writer = std::sync::Mutex::new(writer);
+    stream_logs(filter, move |line| {
+        if let Ok(mut w) = writer.lock() {
+            // Flush per line so the capture is complete whenever the stream is stopped
+            let _ = writeln!(w, "{}", line).and_then(|_| w.flush());
+        }
+    }, simulator)
+}
+
+// ============== Crash Logs ==============
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashLog {
+    pub path: PathBuf,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub bundle_id: String,
+    pub content: String,
+}
+
+fn parse_crash_timestamp(raw: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_str(raw.trim(), "%Y-%m-%d %H:%M:%S%.f %z")
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Utc))
+}
+
+/// Bundle id and crash time from a report: `.ips` files start with a one-line JSON header,
+/// legacy `.crash` files have `Identifier:` and `Date/Time:` lines
+fn parse_crash_metadata(content: &str) -> Option<(String, Option<chrono::DateTime<chrono::Utc>>)> {
+    let first_line = content.lines().next()?;
+    if let Ok(header) = serde_json::from_str::<serde_json::Value>(first_line) {
+        let bundle_id = header["bundleID"].as_str()?.to_string();
+        let timestamp = header["timestamp"].as_str().and_then(parse_crash_timestamp);
+        return Some((bundle_id, timestamp));
+    }
+
+    let field = |name: &str| content.lines()
+        .find_map(|line| line.strip_prefix(name))
+        .map(str::trim);
+    let bundle_id = field("Identifier:")?.to_string();
+    Some((bundle_id, field("Date/Time:").and_then(parse_crash_timestamp)))
+}
+
+fn diagnostic_reports_dir() -> Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME is not set")?;
+    Ok(Path::new(&home).join("Library/Logs/DiagnosticReports"))
+}
+
+/// Crash reports for simulator apps, newest first. The simulator writes them to the host's
+/// `~/Library/Logs/DiagnosticReports`, so reports from every simulator are included
+pub fn get_crash_logs(bundle_id: Option<&str>, simulator: Option<&str>) -> Result<Vec<CrashLog>> {
+    let _udid = get_simulator_udid(simulator)?;
+    let dir = diagnostic_reports_dir()?;
+
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+
+    let mut crashes = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_report = matches!(path.extension().and_then(|e| e.to_str()), Some("ips" | "crash"));
+        if !is_report {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else { continue };
+        let Some((found_bundle, timestamp)) = parse_crash_metadata(&content) else { continue };
+        if bundle_id.is_some_and(|wanted| wanted != found_bundle) {
+            continue;
+        }
+
+        let timestamp = timestamp
+            .or_else(|| entry.metadata().and_then(|m| m.modified()).ok().map(Into::into))
+            .unwrap_or_default();
+        crashes.push(CrashLog { path, timestamp, bundle_id: found_bundle, content });
+    }
+
+    crashes.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
+    Ok(crashes)
+}
+
+/// A frame of the crashed thread in an `.ips` report, resolved to its image
+#[derive(Debug, PartialEq)]
+struct CrashFrame {
+    image_name: String,
+    image_path: String,
+    load_address: u64,
+    address: u64,
+}
+
+/// Frames of the triggering thread from an `.ips` report body (the JSON after the header line)
+fn crashed_thread_frames(body: &serde_json::Value) -> Vec<CrashFrame> {
+    let images = body["usedImages"].as_array().cloned().unwrap_or_default();
+    let Some(thread) = body["threads"].as_array()
+        .and_then(|threads| threads.iter().find(|t| t["triggered"].as_bool() == Some(true)))
+    else {
+        return Vec::new();
+    };
+
+    thread["frames"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|frame| {
+            let image = images.get(frame["imageIndex"].as_u64()? as usize)?;
+            let load_address = image["base"].as_u64()?;
+            Some(CrashFrame {
+                image_name: image["name"].as_str().unwrap_or("???").to_string(),
+                image_path: image["path"].as_str().unwrap_or("").to_string(),
+                load_address,
+                address: load_address + frame["imageOffset"].as_u64()?,
+            })
+        })
+        .collect()
+}
+
+/// Resolve one image's addresses with `atos`; unresolved frames keep their raw address
+fn atos_symbols(image_path: &str, load_address: u64, addresses: &[u64]) -> Vec<Option<String>> {
+    let mut args = vec!["atos".to_string(), "-o".to_string(), image_path.to_string(), "-l".to_string(), format!("{:#x}", load_address)];
+    args.extend(addresses.iter().map(|a| format!("{:#x}", a)));
+
+    let output = Command::new("xcrun").args(&args).output();
+    let symbols: Vec<String> = match output {
+        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout).lines().map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+    (0..addresses.len())
+        // atos echoes the bare address back when it has no symbol
+        .map(|i| symbols.get(i).filter(|s| !s.starts_with("0x")).cloned())
+        .collect()
+}
+
+/// Human-readable backtrace: `.ips` reports are resolved frame by frame with `atos`,
+/// legacy `.crash` reports go through Xcode's `symbolicatecrash`
+pub fn symbolicate_crash(crash: &CrashLog) -> Result<String> {
+    if crash.path.extension().and_then(|e| e.to_str()) == Some("crash") {
+        return symbolicatecrash(&crash.path);
+    }
+
+    let body = crash.content.split_once('\n')
+        .map(|(_, body)| body)
+        .context("Crash report has no body after the header")?;
+    let body: serde_json::Value = serde_json::from_str(body).context("Failed to parse .ips crash report")?;
+    let frames = crashed_thread_frames(&body);
+    if frames.is_empty() {
+        bail!("No crashed thread found in {}", crash.path.display());
+    }
+
+    let mut symbols: Vec<Option<String>> = vec![None; frames.len()];
+    let mut images: Vec<(&str, u64)> = frames.iter().map(|f| (f.image_path.as_str(), f.load_address)).collect();
+    images.sort();
+    images.dedup();
+    for (image_path, load_address) in images.into_iter().filter(|(path, _)| !path.is_empty()) {
+        let indices: Vec<usize> = (0..frames.len())
+            .filter(|&i| frames[i].image_path == image_path && frames[i].load_address == load_address)
+            .collect();
+        let addresses: Vec<u64> = indices.iter().map(|&i| frames[i].address).collect();
+        for (i, symbol) in indices.into_iter().zip(atos_symbols(image_path, load_address, &addresses)) {
+            symbols[i] = symbol;
+        }
+    }
+
+    Ok(frames.iter()
+        .zip(symbols)
+        .enumerate()
+        .map(|(i, (frame, symbol))| format!(
+            "{:<3} {:<30} {}",
+            i,
+            frame.image_name,
+            symbol.unwrap_or_else(|| format!("{:#x}", frame.address))
+        ))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn symbolicatecrash(path: &Path) -> Result<String> {
+    let developer_dir = Command::new("xcode-select").arg("-p").output()
+        .context("Failed to execute xcode-select")?;
+    let developer_dir = String::from_utf8_lossy(&developer_dir.stdout).trim().to_string();
+    let tool = Path::new(&developer_dir)
+        .join("../SharedFrameworks/DVTFoundation.framework/Versions/A/Resources/symbolicatecrash");
+    if !tool.exists() {
+        return Err(with_suggestion(
+            anyhow::anyhow!("symbolicatecrash not found under {}", developer_dir),
+            "Select a full Xcode install with `sudo xcode-select -s /Applications/Xcode.app`",
+        ));
+    }
+
+    let output = Command::new(&tool)
+        .arg(path)
+        .env("DEVELOPER_DIR", &developer_dir)
+        .output()
+        .context("Failed to execute symbolicatecrash")?;
+    if !output.status.success() {
+        bail!("symbolicatecrash failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// ============== Diagnostics ==============
+
+/// Archive path reported by `simctl diagnose`: the last absolute path in its output
+/// that names an archive (.tar.gz, .tgz or .zip)
+fn parse_diagnose_archive(output: &str) -> Option<PathBuf> {
+    output
+        .split(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+        .map(|token| token.trim_end_matches(['.', ',', ':']))
+        .rfind(|token| token.starts_with('/') && [".tar.gz", ".tgz", ".zip"].iter().any(|ext| token.ends_with(ext)))
+        .map(PathBuf::from)
+}
+
+/// Collect a `simctl diagnose` bundle, for Apple bug reports or Xcode debugging
+///
+/// Runs non-interactively (`-b`), targeting only `simulator` when one is given, and
+/// writes into `output_dir` if set. Returns the archive path parsed from simctl's
+/// output, since its default location differs between Xcode versions.
+pub fn diagnose(output_dir: Option<&str>, simulator: Option<&str>) -> Result<PathBuf> {
+    let mut args = vec!["diagnose".to_string(), "-b".to_string()];
+    if let Some(dir) = output_dir {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir))?;
+        args.push(format!("--output={}", dir));
+    }
+    if simulator.is_some() {
+        args.push(format!("--udid={}", concrete_udid(&get_simulator_udid(simulator)?)?));
+    }
+
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = simctl_exec(&arg_refs)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        bail!("simctl diagnose failed: {}", stderr.trim());
+    }
+
+    let archive = parse_diagnose_archive(&format!("{}\n{}", stdout, stderr))
+        .ok_or_else(|| anyhow::anyhow!("simctl diagnose finished but reported no archive path:\n{}", stdout.trim()))?;
+    Ok(archive)
+}
+
+// ============== Video Recording ==============
+
+/// A running `simctl io recordVideo` session
+pub struct RecordingHandle {
+    child: std::process::Child,
+    path: std::path::PathBuf,
+}
+
+impl RecordingHandle {
+    /// Output file the recording is written to
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+/// Check that a recording path ends in a container simctl can write (.mp4 or .mov)
+fn validate_video_extension(path: &str) -> Result<()> {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "mp4" | "mov" => Ok(()),
+        "" => bail!("Recording path '{}' has no extension; use .mp4 or .mov", path),
+        other => bail!("Unsupported recording extension '.{}'; use .mp4 or .mov", other),
+    }
+}
+
+/// Start recording the simulator screen to `output_path` in the background
+pub fn start_recording(output_path: &str, simulator: Option<&str>) -> Result<RecordingHandle> {
+    validate_video_extension(output_path)?;
+    let udid = get_simulator_udid(simulator)?;
+
+    let child = simctl_command(&["io", &udid, "recordVideo", "--force", output_path])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to start simctl recordVideo")?;
+    Ok(RecordingHandle { child, path: std::path::PathBuf::from(output_path) })
+}
+
+/// Stop a recording with SIGINT (so simctl finalizes the file) and wait for it to exit
+pub fn stop_recording(handle: RecordingHandle) -> Result<()> {
+    let RecordingHandle { child, path } = handle;
+
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .context("Failed to send SIGINT to recordVideo")?;
+    if !status.success() {
+        bail!("Failed to signal recording process {}", child.id());
+    }
+
+    let output = child.wait_with_output().context("Failed to wait for recordVideo")?;
+    if !path.exists() {
+        bail!(
+            "Recording stopped but {} was not written: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+// ============== Visual Comparison ==============
+
+/// Decode two PNGs to RGBA, failing if their dimensions differ
+fn decode_pair(a: &[u8], b: &[u8]) -> Result<(image::RgbaImage, image::RgbaImage)> {
+    let img_a = image::load_from_memory(a).context("Failed to decode first image")?.to_rgba8();
+    let img_b = image::load_from_memory(b).context("Failed to decode second image")?.to_rgba8();
+    if img_a.dimensions() != img_b.dimensions() {
+        bail!(
+            "Image dimensions differ: {}x{} vs {}x{}",
+            img_a.width(), img_a.height(), img_b.width(), img_b.height()
+        );
+    }
+    Ok((img_a, img_b))
+}
+
+/// Largest per-channel difference between two pixels, normalized to 0.0-1.0
+fn pixel_difference(p: &image::Rgba<u8>, q: &image::Rgba<u8>) -> f64 {
+    p.0.iter()
+        .zip(q.0.iter())
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap_or(0) as f64 / 255.0
+}
+
+/// Fraction of pixels (0.0-1.0) whose RGBA difference exceeds `threshold` (0.0-1.0 per channel)
+pub fn screenshot_diff(a: &[u8], b: &[u8], threshold: f64) -> Result<f64> {
+    let (img_a, img_b) = decode_pair(a, b)?;
+    let total = img_a.pixels().len();
+    if total == 0 {
+        return Ok(0.0);
+    }
+
+    let changed = img_a.pixels()
+        .zip(img_b.pixels())
+        .filter(|(p, q)| pixel_difference(p, q) > threshold)
+        .count();
+
+    Ok(changed as f64 / total as f64)
+}
+
+/// Visual diff: changed pixels in red over a faded copy of the first image (PNG bytes)
+pub fn screenshot_diff_image(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
+    let (img_a, img_b) = decode_pair(a, b)?;
+    let red = image::Rgba([255u8, 0, 0, 255]);
+
+    let diff = image::RgbaImage::from_fn(img_a.width(), img_a.height(), |x, y| {
+        let p = img_a.get_pixel(x, y);
+        if p != img_b.get_pixel(x, y) {
+            red
+        } else {
+            let fade = |c: u8| (c as u16 / 4 + 191) as u8;
+            image::Rgba([fade(p[0]), fade(p[1]), fade(p[2]), 255])
+        }
+    });
+
+    encode_png(&image::DynamicImage::ImageRgba8(diff))
+}
+
+/// Per-channel difference above which a pixel counts as changed while waiting for renders
+const RENDER_PIXEL_THRESHOLD: f64 = 0.1;
+/// Fraction of changed pixels that counts as the UI having updated
+const RENDER_CHANGE_FRACTION: f64 = 0.005;
+/// Poll interval for `benchmark_tap` (60 fps)
+const RENDER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+
+/// Whether `after` differs visibly from `before`; a size change (rotation) counts as changed
+fn screen_changed(before: &[u8], after: &[u8]) -> bool {
+    screenshot_diff(before, after, RENDER_PIXEL_THRESHOLD)
+        .map_or(true, |fraction| fraction > RENDER_CHANGE_FRACTION)
+}
+
+/// Milliseconds from a tap at (x, y) until the screen visibly changes or an element
+/// matching `expected_change_query` appears (pass "" to rely on pixels alone).
+///
+/// Polls at up to 60 fps, though each poll is bounded by screenshot capture time.
+/// Timing starts once the tap has been delivered, after the Simulator focus delay.
+pub fn benchmark_tap(x: i32, y: i32, expected_change_query: &str, timeout_ms: u64, simulator: Option<&str>) -> Result<u64> {
+    let before = screenshot(simulator)?;
+    tap(x, y, simulator)?;
+    let start = std::time::Instant::now();
+    let timeout = std::time::Duration::from_millis(timeout_ms);
+
+    loop {
+        let frame_start = std::time::Instant::now();
+        if screen_changed(&before, &screenshot(simulator)?) {
+            break;
+        }
+        if !expected_change_query.is_empty()
+            && match_element(&get_accessibility_elements()?, expected_change_query).is_some()
+        {
+            break;
+        }
+        if start.elapsed() >= timeout {
+            bail!("No UI change within {}ms after tapping ({}, {})", timeout_ms, x, y);
+        }
+        std::thread::sleep(RENDER_POLL_INTERVAL.saturating_sub(frame_start.elapsed()));
+    }
+
+    let elapsed = start.elapsed().as_millis() as u64;
+    Ok(elapsed)
+}
+
+/// Consecutive unchanged frames after a change before the screen counts as settled
+const SCREEN_SETTLE_FRAMES: u32 = 2;
+
+/// Where `wait_for_screen_change` is: waiting for a change, then for it to settle
+struct ScreenChangeWatch {
+    baseline: Vec<u8>,
+    diff_threshold: f64,
+    /// Changed fraction and the latest frame once the screen has moved off the baseline
+    changed: Option<(f64, Vec<u8>)>,
+    stable_frames: u32,
+}
+
+impl ScreenChangeWatch {
+    fn new(baseline: Vec<u8>, diff_threshold: f64) -> Self {
+        Self { baseline, diff_threshold, changed: None, stable_frames: 0 }
+    }
+
+    /// Feed the next frame; returns the changed fraction once the change has settled
+    fn observe(&mut self, frame: Vec<u8>) -> Option<f64> {
+        match &mut self.changed {
+            None => {
+                let fraction = screenshot_diff(&self.baseline, &frame, RENDER_PIXEL_THRESHOLD).unwrap_or(1.0);
+                if fraction > self.diff_threshold {
+                    self.changed = Some((fraction, frame));
+                }
+                None
+            }
+            Some((fraction, last)) => {
+                if screen_changed(last, &frame) {
+                    // Still animating: measure stability from this frame on
+                    *fraction = screenshot_diff(&self.baseline, &frame, RENDER_PIXEL_THRESHOLD).unwrap_or(1.0);
+                    *last = frame;
+                    self.stable_frames = 0;
+                    None
+                } else {
+                    self.stable_frames += 1;
+                    (self.stable_frames >= SCREEN_SETTLE_FRAMES).then_some(*fraction)
+                }
+            }
+        }
+    }
+}
+
+/// Wait until the screen differs from how it looked on entry by more than
+/// `diff_threshold` (fraction of pixels, 0.0–1.0) and then stops changing,
+/// polling every `poll_ms`. Useful after an action to wait for its transition
+/// to finish before the next one; a size change (rotation) counts as changed.
+/// Returns the fraction of pixels that changed.
+pub fn wait_for_screen_change(timeout_ms: u64, diff_threshold: f64, poll_ms: u64, simulator: Option<&str>) -> Result<f64> {
+    if !(0.0..=1.0).contains(&diff_threshold) {
+        bail!("Invalid diff threshold {}: must be between 0.0 and 1.0", diff_threshold);
+    }
+    let mut watch = ScreenChangeWatch::new(screenshot(simulator)?, diff_threshold);
+    poll_until(timeout_ms, poll_ms, "the screen to change and settle", || {
+        Ok(watch.observe(screenshot(simulator)?))
+    })
+}
+
+/// RGBA of the pixel at (x, y) in PNG bytes, failing when the point is outside the image
+fn pixel_in_png(data: &[u8], x: i32, y: i32) -> Result<(u8, u8, u8, u8)> {
+    let img = image::load_from_memory(data).context("Failed to decode screenshot")?.to_rgba8();
+    if x < 0 || y < 0 || x as u32 >= img.width() || y as u32 >= img.height() {
+        bail!("Pixel ({}, {}) is outside the {}x{} screen", x, y, img.width(), img.height());
+    }
+    let [r, g, b, a] = img.get_pixel(x as u32, y as u32).0;
+    Ok((r, g, b, a))
+}
+
+/// Color (r, g, b, a) of the screenshot pixel at simulator coordinate (x, y)
+pub fn pixel_color_at(x: i32, y: i32, simulator: Option<&str>) -> Result<(u8, u8, u8, u8)> {
+    pixel_in_png(&screenshot(simulator)?, x, y)
+}
+
+/// Whether every channel of `actual` is within `tolerance` of `expected`
+fn color_matches(actual: (u8, u8, u8, u8), expected: (u8, u8, u8, u8), tolerance: u8) -> bool {
+    let (a, e) = ([actual.0, actual.1, actual.2, actual.3], [expected.0, expected.1, expected.2, expected.3]);
+    a.iter().zip(e.iter()).all(|(a, e)| a.abs_diff(*e) <= tolerance)
+}
+
+/// Fail unless the pixel at (x, y) matches `expected_rgba` within `tolerance` per channel
+pub fn assert_pixel_color(x: i32, y: i32, expected_rgba: (u8, u8, u8, u8), tolerance: u8, simulator: Option<&str>) -> Result<()> {
+    let actual = pixel_color_at(x, y, simulator)?;
+    if !color_matches(actual, expected_rgba, tolerance) {
+        bail!(
+            "Pixel ({}, {}) is rgba{:?}, expected rgba{:?} (tolerance {})",
+            x, y, actual, expected_rgba, tolerance
+        );
+    }
+    Ok(())
+}
+
+// ============== OCR ==============
+
+/// A word recognized by tesseract, with its box in screenshot pixels
+#[derive(Debug, Clone, PartialEq)]
+struct OcrWord {
+    text: String,
+    line: usize,
+    bbox: (i32, i32, i32, i32),
+}
+
+/// Words from tesseract hOCR output in reading order, numbered by text line
+fn parse_hocr_words(hocr: &str) -> Vec<OcrWord> {
+    let re = regex::Regex::new(
+        r#"(?s)class=['"]ocr_line['"]|class=['"]ocrx_word['"][^>]*?title=['"]bbox (\d+) (\d+) (\d+) (\d+)[^>]*>(.*?)</span>"#
+    ).unwrap();
+    let tags = regex::Regex::new(r"<[^>]+>").unwrap();
+
+    let mut line = 0;
+    let mut words = Vec::new();
+    for cap in re.captures_iter(hocr) {
+        let inner = match cap.get(5) {
+            Some(inner) => inner,
+            None => {
+                line += 1;
+                continue;
+            }
+        };
+        let coord = |i: usize| cap[i].parse::<i32>().unwrap_or(0);
+        let text = tags.replace_all(inner.as_str(), "")
+            .replace("&amp;", "&").replace("&lt;", "<").replace("&gt;", ">")
+            .replace("&quot;", "\"").replace("&#39;", "'");
+        let text = text.trim();
+        if !text.is_empty() {
+            words.push(OcrWord { text: text.to_string(), line, bbox: (coord(1), coord(2), coord(3), coord(4)) });
+        }
+    }
+    words
+}
+
+/// Box (x, y, width, height) around the first run of consecutive words on one
+/// line that spells `text` (case-insensitive, ignoring surrounding punctuation)
+fn find_ocr_text(words: &[OcrWord], text: &str) -> Option<(i32, i32, i32, i32)> {
+    let normalize = |w: &str| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+    let query: Vec<String> = text.split_whitespace().map(normalize).collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    words.windows(query.len()).find(|run| {
+        run.iter().all(|w| w.line == run[0].line)
+            && run.iter().zip(&query).all(|(w, q)| normalize(&w.text) == *q)
+    }).map(|run| {
+        let x1 = run.iter().map(|w| w.bbox.0).min().unwrap_or(0);
+        let y1 = run.iter().map(|w| w.bbox.1).min().unwrap_or(0);
+        let x2 = run.iter().map(|w| w.bbox.2).max().unwrap_or(0);
+        let y2 = run.iter().map(|w| w.bbox.3).max().unwrap_or(0);
+        (x1, y1, x2 - x1, y2 - y1)
+    })
+}
+
+/// Locate `text` in a screenshot with tesseract OCR, for UIs the accessibility tree
+/// cannot see (Flutter, React Native canvases). Returns (x, y, width, height) in
+/// simulator pixels, or `None` when the text is not found or tesseract is not installed.
+pub fn find_text_on_screen(text: &str, simulator: Option<&str>) -> Result<Option<(i32, i32, i32, i32)>> {
+    let file = temp_file("ios_ocr_", ".png")?;
+    std::fs::write(file.path(), screenshot(simulator)?).context("Failed to write screenshot")?;
+
+    let output = match Command::new("tesseract").arg(file.path()).args(["stdout", "hocr"]).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!("Warning: tesseract is not installed, OCR lookup skipped (brew install tesseract)");
+            return Ok(None);
+        }
+        Err(e) => return Err(anyhow::Error::new(e).context("Failed to run tesseract")),
+    };
+    if !output.status.success() {
+        bail!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    Ok(find_ocr_text(&parse_hocr_words(&String::from_utf8_lossy(&output.stdout)), text))
+}
+
+/// Tap the center of `text` as found by OCR (see `find_text_on_screen`)
+pub fn tap_text(text: &str, simulator: Option<&str>) -> Result<()> {
+    match find_text_on_screen(text, simulator)? {
+        Some((x, y, w, h)) => tap(x + w / 2, y + h / 2, simulator),
+        None => bail!("Text '{}' not found on screen", text),
+    }
+}
+
+// ============== Template Matching ==============
+
+/// Best zero-mean normalized cross-correlation of `tpl` over top-left positions
+/// `xs`x`ys` of `img`, as (x, y, score in -1.0..=1.0); `None` when no position fits
+fn ncc_search(
+    img: &image::GrayImage,
+    tpl: &image::GrayImage,
+    xs: std::ops::Range<u32>,
+    ys: std::ops::Range<u32>,
+) -> Option<(u32, u32, f64)> {
+    let (tw, th) = tpl.dimensions();
+    let n = (tw * th) as f64;
+    let t_mean = tpl.pixels().map(|p| p[0] as f64).sum::<f64>() / n;
+    let t_dev: Vec<f64> = tpl.pixels().map(|p| p[0] as f64 - t_mean).collect();
+    let t_norm = t_dev.iter().map(|d| d * d).sum::<f64>().sqrt();
+
+    let mut best: Option<(u32, u32, f64)> = None;
+    for y in ys.start..ys.end.min((img.height() + 1).saturating_sub(th)) {
+        for x in xs.start..xs.end.min((img.width() + 1).saturating_sub(tw)) {
+            let (mut sum, mut sum_sq, mut cross) = (0.0, 0.0, 0.0);
+            for ty in 0..th {
+                for tx in 0..tw {
+                    let v = img.get_pixel(x + tx, y + ty)[0] as f64;
+                    sum += v;
+                    sum_sq += v * v;
+                    cross += v * t_dev[(ty * tw + tx) as usize];
+                }
+            }
+            let i_norm = (sum_sq - sum * sum / n).max(0.0).sqrt();
+            let score = if i_norm > 0.0 && t_norm > 0.0 { cross / (i_norm * t_norm) } else { 0.0 };
+            if best.is_none_or(|(_, _, s)| score > s) {
+                best = Some((x, y, score));
+            }
+        }
+    }
+    best
+}
+
+/// Locate `tpl` in `screen` coarse-to-fine: search a downscaled copy, then refine
+/// around the best hit at full resolution. Returns (x, y, score).
+fn locate_template(screen: &image::GrayImage, tpl: &image::GrayImage) -> Option<(u32, u32, f64)> {
+    use image::imageops::{resize, FilterType};
+
+    let (tw, th) = tpl.dimensions();
+    let factor = (tw.min(th) / 16).clamp(1, 8);
+    if factor == 1 {
+        return ncc_search(screen, tpl, 0..u32::MAX, 0..u32::MAX);
+    }
+
+    let small = resize(screen, screen.width() / factor, screen.height() / factor, FilterType::Triangle);
+    let small_tpl = resize(tpl, tw / factor, th / factor, FilterType::Triangle);
+    let (x, y, _) = ncc_search(&small, &small_tpl, 0..u32::MAX, 0..u32::MAX)?;
+    let (cx, cy) = (x * factor, y * factor);
+
+    let margin = factor * 2;
+    ncc_search(
+        screen,
+        tpl,
+        cx.saturating_sub(margin)..cx + margin + 1,
+        cy.saturating_sub(margin)..cy + margin + 1,
+    )
+}
+
+/// Find `template_png` in a screenshot by normalized cross-correlation, for UIs
+/// missing from the accessibility tree (React Native, games). Returns the best
+/// match as (x, y, width, height) in simulator pixels when its score reaches
+/// `threshold` (0.0–1.0; around 0.9 suits pixel-identical assets).
+pub fn find_image_on_screen(template_png: &[u8], threshold: f64, simulator: Option<&str>) -> Result<Option<(i32, i32, i32, i32)>> {
+    if !(0.0..=1.0).contains(&threshold) {
+        bail!("Invalid threshold {}: must be between 0.0 and 1.0", threshold);
+    }
+    let tpl = image::load_from_memory(template_png).context("Failed to decode template image")?.to_luma8();
+    let screen = image::load_from_memory(&screenshot(simulator)?).context("Failed to decode screenshot")?.to_luma8();
+    if tpl.width() > screen.width() || tpl.height() > screen.height() {
+        bail!(
+            "Template {}x{} is larger than the {}x{} screen",
+            tpl.width(), tpl.height(), screen.width(), screen.height()
+        );
+    }
+
+    Ok(locate_template(&screen, &tpl)
+        .filter(|&(_, _, score)| score >= threshold)
+        .map(|(x, y, _)| (x as i32, y as i32, tpl.width() as i32, tpl.height() as i32)))
+}
+
+/// Tap the center of `template_png` as found by `find_image_on_screen`
+pub fn tap_image(template_png: &[u8], threshold: f64, simulator: Option<&str>) -> Result<()> {
+    match find_image_on_screen(template_png, threshold, simulator)? {
+        Some((x, y, w, h)) => tap(x + w / 2, y + h / 2, simulator),
+        None => bail!("Template image not found on screen (threshold {})", threshold),
+    }
+}
+
+// ============== Location ==============
+
+/// Interval between location updates while simulating a route
+const ROUTE_UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+fn validate_coordinate(lat: f64, lon: f64) -> Result<()> {
+    if !(-90.0..=90.0).contains(&lat) {
+        bail!("Invalid latitude {}: must be between -90 and 90", lat);
+    }
+    if !(-180.0..=180.0).contains(&lon) {
+        bail!("Invalid longitude {}: must be between -180 and 180", lon);
+    }
+    Ok(())
+}
+
+/// Great-circle distance in meters
+fn haversine_m(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+}
+
+/// Positions along the route spaced `step_m` apart, always ending on the last waypoint
+fn route_positions(waypoints: &[(f64, f64)], step_m: f64) -> Vec<(f64, f64)> {
+    let Some(&first) = waypoints.first() else {
+        return Vec::new();
+    };
+
+    let mut positions = vec![first];
+    // Distance already travelled past the last emitted position
+    let mut carried = 0.0;
+    for pair in waypoints.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let length = haversine_m(from, to);
+        let mut along = step_m - carried;
+        while along < length {
+            let t = along / length;
+            positions.push((from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t));
+            along += step_m;
+        }
+        carried = length - (along - step_m);
+    }
+    let last = *waypoints.last().unwrap();
+    if positions.last() != Some(&last) {
+        positions.push(last);
+    }
+    positions
+}
+
+fn simctl_set_location(udid: &str, lat: f64, lon: f64) -> Result<()> {
+    let coordinate = format!("{},{}", lat, lon);
+    let output = simctl_exec(&["location", udid, "set", &coordinate])?;
+    if !output.status.success() {
+        bail!("Failed to set location: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Set the simulated GPS position
+pub fn set_location(lat: f64, lon: f64, simulator: Option<&str>) -> Result<()> {
+    validate_coordinate(lat, lon)?;
+    let udid = get_simulator_udid(simulator)?;
+
+    simctl_set_location(&udid, lat, lon)?;
+    Ok(())
+}
+
+/// Stop simulating a location
+pub fn clear_location(simulator: Option<&str>) -> Result<()> {
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&["location", &udid, "clear"])?;
+    if !output.status.success() {
+        bail!("Failed to clear location: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Walk the waypoints at `speed_mps`, updating the location once per second (blocks until done)
+pub fn simulate_route(waypoints: &[(f64, f64)], speed_mps: f64, simulator: Option<&str>) -> Result<()> {
+    if waypoints.is_empty() {
+        bail!("A route needs at least one waypoint");
+    }
+    if !speed_mps.is_finite() || speed_mps <= 0.0 {
+        bail!("Invalid speed {} m/s: must be positive", speed_mps);
+    }
+    for &(lat, lon) in waypoints {
+        validate_coordinate(lat, lon)?;
+    }
+    let udid = get_simulator_udid(simulator)?;
+
+    let positions = route_positions(waypoints, speed_mps * ROUTE_UPDATE_INTERVAL.as_secs_f64());
+
+    for (i, &(lat, lon)) in positions.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(ROUTE_UPDATE_INTERVAL);
+        }
+        simctl_set_location(&udid, lat, lon)?;
+    }
+    Ok(())
+}
+
+// ============== Network ==============
+
+/// True if a curl `%{http_code}` string is a 2xx status
+fn is_success_status(code: &str) -> bool {
+    matches!(code.trim().parse::<u16>(), Ok(200..=299))
+}
+
+/// Check whether `host` is reachable over HTTPS from inside the simulator
+pub fn test_network_reachability(host: &str, simulator: Option<&str>) -> Result<bool> {
+    let url = validate_url(&format!("https://{}/", host))?;
+    let udid = get_simulator_udid(simulator)?;
+
+    let output = simctl_exec(&[
+        "spawn", &udid, "/usr/bin/curl",
+        "-s", "--max-time", "5", "-o", "/dev/null", "-w", "%{http_code}",
+        url.as_str(),
+    ])?;
+
+    Ok(is_success_status(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Poll until `host` becomes reachable from the simulator or `timeout_ms` elapses
+pub fn wait_for_network(host: &str, timeout_ms: u64, simulator: Option<&str>) -> Result<()> {
+    let start = std::time::Instant::now();
+    let timeout = std::time::Duration::from_millis(timeout_ms);
+
+    loop {
+        if test_network_reachability(host, simulator)? {
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            bail!("Host '{}' not reachable after {}ms", host, start.elapsed().as_millis());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+}
+
+// ============== Network Conditions ==============
+
+/// Throttling applied to all host traffic: simulators have no network interface
+/// of their own, so every app on the Mac is conditioned along with the simulator
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkProfile {
+    /// ~240 kbps with 400 ms latency
+    Edge,
+    /// ~780 kbps with 100 ms latency
+    ThreeG,
+    /// Added latency in ms, no bandwidth cap
+    HighLatency(u32),
+    /// `bandwidth_kbps` 0 means uncapped; `loss_pct` is 0-100
+    Custom { latency_ms: u32, bandwidth_kbps: u32, loss_pct: f32 },
+    /// No throttling
+    None,
+}
+
+/// Concrete dummynet pipe settings behind a profile
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LinkConditions {
+    latency_ms: u32,
+    bandwidth_kbps: u32,
+    loss_pct: f32,
+}
+
+impl NetworkProfile {
+    fn conditions(&self) -> Option<LinkConditions> {
+        let link = |latency_ms, bandwidth_kbps, loss_pct| Some(LinkConditions { latency_ms, bandwidth_kbps, loss_pct });
+        match *self {
+            NetworkProfile::Edge => link(400, 240, 0.0),
+            NetworkProfile::ThreeG => link(100, 780, 0.0),
+            NetworkProfile::HighLatency(ms) => link(ms, 0, 0.0),
+            NetworkProfile::Custom { latency_ms, bandwidth_kbps, loss_pct } => link(latency_ms, bandwidth_kbps, loss_pct),
+            NetworkProfile::None => None,
+        }
+    }
+
+    /// Name the conditions with a preset when they match one
+    fn from_conditions(c: LinkConditions) -> Self {
+        for preset in [NetworkProfile::Edge, NetworkProfile::ThreeG] {
+            if preset.conditions() == Some(c) {
+                return preset;
+            }
+        }
+        match c {
+            LinkConditions { latency_ms: 0, bandwidth_kbps: 0, loss_pct: 0.0 } => NetworkProfile::None,
+            LinkConditions { latency_ms, bandwidth_kbps: 0, loss_pct: 0.0 } => NetworkProfile::HighLatency(latency_ms),
+            LinkConditions { latency_ms, bandwidth_kbps, loss_pct } => NetworkProfile::Custom { latency_ms, bandwidth_kbps, loss_pct },
+        }
+    }
+}
+
+/// pf anchor under `com.apple/*`, which the stock macOS pf.conf already loads
+const NETWORK_PF_ANCHOR: &str = "com.apple/claude-in-mobile";
+/// dummynet pipes for outbound (latency, bandwidth, loss) and inbound (bandwidth) traffic
+const NETWORK_PIPE_OUT: &str = "1";
+const NETWORK_PIPE_IN: &str = "2";
+
+/// `dnctl` arguments configuring both pipes for `c`
+fn dnctl_config_args(c: &LinkConditions) -> [Vec<String>; 2] {
+    let bandwidth = |args: &mut Vec<String>| {
+        if c.bandwidth_kbps > 0 {
+            args.extend(["bw".to_string(), format!("{}Kbit/s", c.bandwidth_kbps)]);
+        }
+    };
+
+    let mut out: Vec<String> = ["pipe", NETWORK_PIPE_OUT, "config"].map(String::from).to_vec();
+    if c.latency_ms > 0 {
+        out.extend(["delay".to_string(), c.latency_ms.to_string()]);
+    }
+    bandwidth(&mut out);
+    if c.loss_pct > 0.0 {
+        out.extend(["plr".to_string(), format!("{}", c.loss_pct / 100.0)]);
+    }
+
+    let mut inbound: Vec<String> = ["pipe", NETWORK_PIPE_IN, "config"].map(String::from).to_vec();
+    bandwidth(&mut inbound);
+
+    [out, inbound]
+}
+
+/// pf rules sending all host traffic through the pipes; there is no narrower match
+/// for simulator traffic, which leaves through the host's own interfaces
+fn network_pf_rules() -> String {
+    format!(
+        "dummynet out all pipe {}\ndummynet in all pipe {}\n",
+        NETWORK_PIPE_OUT, NETWORK_PIPE_IN
+    )
+}
+
+/// Run a privileged command without prompting (fails if sudo needs a password)
+fn sudo_exec(args: &[&str], input: Option<&str>) -> Result<std::process::Output> {
+    let mut sudo_args = vec!["-n"];
+    sudo_args.extend_from_slice(args);
+
+    let output = match input {
+        Some(input) => pipe_to_command("sudo", &sudo_args, input)?,
+        None => Command::new("sudo").args(&sudo_args).output().context("Failed to execute sudo")?,
+    };
+    if !output.status.success() {
+        return Err(with_suggestion(
+            anyhow::anyhow!("{} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()),
+            "Network conditioning needs root: run `sudo -v` first or allow dnctl/pfctl in sudoers",
+        ));
+    }
+    Ok(output)
+}
+
+/// Where the pf enable reference taken by `set_network_condition` is kept until cleared
+fn pf_token_path() -> Result<PathBuf> {
+    Ok(app_support_dir()?.join("pf-token"))
+}
+
+/// Token from `pfctl -E` stderr ("pf enabled\nToken : 1234567890")
+fn parse_pf_token(stderr: &str) -> Option<String> {
+    stderr.lines()
+        .find_map(|line| line.trim().strip_prefix("Token")?.trim_start().strip_prefix(':'))
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty() && token.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Our pipes that `dnctl list` currently shows
+fn owned_pipes(list_output: &str) -> Vec<&'static str> {
+    [NETWORK_PIPE_OUT, NETWORK_PIPE_IN]
+        .into_iter()
+        .filter(|pipe| {
+            let prefix = format!("{:0>5}:", pipe);
+            list_output.lines().any(|l| l.trim_start().starts_with(&prefix))
+        })
+        .collect()
+}
+
+/// Take a pf enable reference unless we already hold one
+fn acquire_pf_reference() -> Result<()> {
+    let path = pf_token_path()?;
+    if path.exists() {
+        return Ok(());
+    }
+
+    let output = sudo_exec(&["pfctl", "-E"], None)?;
+    let token = parse_pf_token(&String::from_utf8_lossy(&output.stderr))
+        .context("pfctl -E did not report an enable token")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&path, &token).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Release the pf reference taken by `acquire_pf_reference`, if any
+fn release_pf_reference() -> Result<()> {
+    let path = pf_token_path()?;
+    let token = match std::fs::read_to_string(&path) {
+        Ok(token) => token.trim().to_string(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read {}", path.display()))),
+    };
+    sudo_exec(&["pfctl", "-X", &token], None)?;
+    std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Remove our pf anchor rules and dummynet pipes, leaving other pipes alone
+fn clear_network_condition() -> Result<()> {
+    sudo_exec(&["pfctl", "-a", NETWORK_PF_ANCHOR, "-F", "all"], None)?;
+    let output = sudo_exec(&["dnctl", "list"], None)?;
+    for pipe in owned_pipes(&String::from_utf8_lossy(&output.stdout)) {
+        sudo_exec(&["dnctl", "pipe", "delete", pipe], None)?;
+    }
+    release_pf_reference()
+}
+
+/// Throttle the simulator's network
+///
+/// This conditions all traffic on the host, not just the simulator: simulators
+/// share the Mac's network stack, so browsers, package managers and every other
+/// app slow down too until the profile is set back to `NetworkProfile::None`.
+pub fn set_network_condition(profile: NetworkProfile, simulator: Option<&str>) -> Result<()> {
+    let _udid = get_simulator_udid(simulator)?;
+
+    let Some(conditions) = profile.conditions() else {
+        clear_network_condition()?;
+        return Ok(());
+    };
+    if !(0.0..=100.0).contains(&conditions.loss_pct) {
+        bail!("Invalid packet loss {}%: must be between 0 and 100", conditions.loss_pct);
+    }
+
+    for args in dnctl_config_args(&conditions) {
+        let mut dnctl = vec!["dnctl"];
+        dnctl.extend(args.iter().map(String::as_str));
+        sudo_exec(&dnctl, None)?;
+    }
+    sudo_exec(&["pfctl", "-a", NETWORK_PF_ANCHOR, "-f", "-"], Some(&network_pf_rules()))?;
+    // The reference keeps pf enabled while conditioning is active; released on clear
+    acquire_pf_reference()?;
+    Ok(())
+}
+
+/// Parse the outbound pipe's settings from `dnctl list`
+/// ("00001:   240.000 Kbit/s  400 ms   50 sl.plr 0.010000 ...")
+fn parse_dnctl_list(output: &str) -> Option<LinkConditions> {
+    let prefix = format!("{:0>5}:", NETWORK_PIPE_OUT);
+    let line = output.lines().find(|l| l.trim_start().starts_with(&prefix))?;
+
+    let bw_re = regex::Regex::new(r"([\d.]+)\s*(K|M)?bit/s").unwrap();
+    let delay_re = regex::Regex::new(r"(\d+)\s*ms").unwrap();
+    let plr_re = regex::Regex::new(r"plr\s+([\d.]+)").unwrap();
+
+    let bandwidth_kbps = bw_re.captures(line)
+        .and_then(|c| {
+            let value: f64 = c[1].parse().ok()?;
+            Some(match c.get(2).map(|m| m.as_str()) {
+                Some("M") => value * 1000.0,
+                Some(_) => value,
+                None => value / 1000.0,
+            })
+        })
+        .map_or(0, |kbps| kbps.round() as u32);
+    let latency_ms = delay_re.captures(line).and_then(|c| c[1].parse().ok()).unwrap_or(0);
+    let loss_pct = plr_re.captures(line)
+        .and_then(|c| c[1].parse::<f32>().ok())
+        .map_or(0.0, |plr| (plr * 100.0 * 1000.0).round() / 1000.0);
+
+    Some(LinkConditions { latency_ms, bandwidth_kbps, loss_pct })
+}
+
+/// Currently active network profile, read back from dummynet
+pub fn get_network_condition(simulator: Option<&str>) -> Result<NetworkProfile> {
+    let _udid = get_simulator_udid(simulator)?;
+
+    let output = sudo_exec(&["dnctl", "list"], None)?;
+    Ok(parse_dnctl_list(&String::from_utf8_lossy(&output.stdout))
+        .map_or(NetworkProfile::None, NetworkProfile::from_conditions))
+}
+
+// ============== HAR Capture ==============
+
+/// Running HAR capture; the host's HTTP/HTTPS proxy points at it until `stop`.
+/// Dropping the handle without calling `stop` still restores the proxy settings.
+pub struct HarCaptureHandle {
+    proxy: Option<crate::har::HarProxy>,
+    output_path: PathBuf,
+    saved_proxies: Vec<ServiceProxies>,
+    restored: bool,
+}
+
+/// One proxy setting as reported by `networksetup -getwebproxy`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProxySetting {
+    enabled: bool,
+    server: String,
+    port: String,
+}
+
+/// A network service's web and secure web proxy before the capture changed them
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ServiceProxies {
+    service: String,
+    web: ProxySetting,
+    secure_web: ProxySetting,
+}
+
+/// Enabled network services from `networksetup -listallnetworkservices`
+/// (the first line is a legend; disabled services are prefixed with `*`)
+fn parse_network_services(stdout: &str) -> Vec<String> {
+    stdout.lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('*'))
+        .map(String::from)
+        .collect()
+}
+
+fn networksetup(args: &[&str]) -> Result<()> {
+    let output = Command::new("networksetup")
+        .args(args)
+        .output()
+        .context("Failed to execute networksetup")?;
+
+    if !output.status.success() {
+        bail!("networksetup {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Parse `Enabled: Yes` / `Server: host` / `Port: 8080` lines
+fn parse_proxy_setting(stdout: &str) -> ProxySetting {
+    let field = |name: &str| {
+        stdout.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    ProxySetting {
+        enabled: field("Enabled").eq_ignore_ascii_case("yes"),
+        server: field("Server"),
+        port: field("Port"),
+    }
+}
+
+fn get_proxy_setting(flag: &str, service: &str) -> Result<ProxySetting> {
+    let output = Command::new("networksetup")
+        .args([flag, service])
+        .output()
+        .context("Failed to execute networksetup")?;
+    if !output.status.success() {
+        bail!("networksetup {} failed: {}", flag, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(parse_proxy_setting(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Current web and secure web proxy of each service, for `restore_host_proxy`
+fn snapshot_host_proxy(services: &[String]) -> Result<Vec<ServiceProxies>> {
+    services.iter()
+        .map(|service| Ok(ServiceProxies {
+            service: service.clone(),
+            web: get_proxy_setting("-getwebproxy", service)?,
+            secure_web: get_proxy_setting("-getsecurewebproxy", service)?,
+        }))
+        .collect()
+}
+
+/// Point each service's web and secure web proxy at `addr`
+fn set_host_proxy(saved: &[ServiceProxies], addr: std::net::SocketAddr) -> Result<()> {
+    let host = addr.ip().to_string();
+    let port = addr.port().to_string();
+    for proxies in saved {
+        networksetup(&["-setwebproxy", &proxies.service, &host, &port])?;
+        networksetup(&["-setsecurewebproxy", &proxies.service, &host, &port])?;
+    }
+    Ok(())
+}
+
+/// `networksetup` calls that put one proxy setting back exactly as it was
+fn restore_proxy_args(set_flag: &str, state_flag: &str, service: &str, setting: &ProxySetting) -> Vec<Vec<String>> {
+    let mut calls = Vec::new();
+    if !setting.server.is_empty() {
+        calls.push(vec![set_flag.to_string(), service.to_string(), setting.server.clone(), setting.port.clone()]);
+    }
+    let state = if setting.enabled { "on" } else { "off" };
+    calls.push(vec![state_flag.to_string(), service.to_string(), state.to_string()]);
+    calls
+}
+
+/// Put back the proxy settings captured by `snapshot_host_proxy`; keeps going
+/// after a failure so one broken service does not strand the others
+fn restore_host_proxy(saved: &[ServiceProxies]) -> Result<()> {
+    let mut first_error = None;
+    for proxies in saved {
+        let calls = restore_proxy_args("-setwebproxy", "-setwebproxystate", &proxies.service, &proxies.web)
+            .into_iter()
+            .chain(restore_proxy_args("-setsecurewebproxy", "-setsecurewebproxystate", &proxies.service, &proxies.secure_web));
+        for call in calls {
+            let args: Vec<&str> = call.iter().map(String::as_str).collect();
+            if let Err(e) = networksetup(&args) {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Record the simulator's HTTP traffic to a HAR 1.2 file
+///
+/// The simulator uses the host's network stack, so this sets the macOS proxy
+/// for every enabled network service until `HarCaptureHandle::stop`, which puts
+/// back the exact settings found here. HTTPS is tunnelled and recorded per
+/// connection without decrypting it.
+pub fn start_har_capture(output_path: &str, simulator: Option<&str>) -> Result<HarCaptureHandle> {
+    let _udid = get_simulator_udid(simulator)?;
+
+    let output = Command::new("networksetup")
+        .arg("-listallnetworkservices")
+        .output()
+        .context("Failed to execute networksetup")?;
+    let services = parse_network_services(&String::from_utf8_lossy(&output.stdout));
+    if services.is_empty() {
+        bail!("No enabled network services to route through the HAR proxy");
+    }
+
+    let saved_proxies = snapshot_host_proxy(&services)?;
+    let proxy = crate::har::HarProxy::start()?;
+    let addr = proxy.addr();
+    // From here on, dropping the handle (including on the error path) restores the proxies
+    let handle = HarCaptureHandle {
+        proxy: Some(proxy),
+        output_path: PathBuf::from(output_path),
+        saved_proxies,
+        restored: false,
+    };
+    set_host_proxy(&handle.saved_proxies, addr)?;
+    Ok(handle)
+}
+
+impl HarCaptureHandle {
+    fn restore_proxies(&mut self) -> Result<()> {
+        if self.restored {
+            return Ok(());
+        }
+        self.restored = true;
+        restore_host_proxy(&self.saved_proxies)
+    }
+
+    /// Restore the host proxy settings, write the HAR file and summarize it
+    pub fn stop(mut self) -> Result<crate::har::HarSummary> {
+        let restored = self.restore_proxies();
+        let har = self.proxy.take().context("HAR capture already stopped")?.finish()?;
+
+        let file = std::fs::File::create(&self.output_path)
+            .with_context(|| format!("Failed to create {}", self.output_path.display()))?;
+        serde_json::to_writer_pretty(file, &har)?;
+        restored.context("HAR written, but failed to restore the host proxy settings")?;
+
+        Ok(har.summary())
+    }
+}
+
+impl Drop for HarCaptureHandle {
+    fn drop(&mut self) {
+        if let Err(e) = self.restore_proxies() {
+            eprintln!("Warning: failed to restore host proxy settings: {:#}", e);
+        }
+    }
+}
+
+// ============== Automation Actions ==============
+
+fn default_long_press_ms() -> u32 { 1000 }
+fn default_swipe_ms() -> u32 { 300 }
+fn default_wait_timeout_ms() -> u64 { 10000 }
+
+/// One automation step, shared by the REPL and replayable scripts
+/// Serialized as `{action: tap, x: 100, y: 200}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    Tap { x: i32, y: i32 },
+    TapElement { query: String },
+    LongPress {
+        x: i32,
+        y: i32,
+        #[serde(default = "default_long_press_ms")]
+        duration_ms: u32,
+    },
+    Swipe {
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        #[serde(default = "default_swipe_ms")]
+        duration_ms: u32,
+    },
+    InputText {
+        text: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        clear: bool,
+    },
+    PressKey { key: String },
+    OpenUrl { url: String },
+    LaunchApp { bundle_id: String },
+    StopApp { bundle_id: String },
+    #[serde(alias = "wait_ms")]
+    Wait { ms: u64 },
+    WaitFor {
+        query: String,
+        #[serde(default = "default_wait_timeout_ms")]
+        timeout_ms: u64,
+    },
+    Screenshot { path: String },
+    AssertElement { query: String },
+}
+
+/// Pick the screenshot encoding from the file extension
+fn output_format_for_path(path: &str) -> OutputFormat {
+    match Path::new(path).extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("jpg" | "jpeg") => OutputFormat::Jpeg { quality: 80 },
+        _ => OutputFormat::Png,
+    }
+}
+
+impl Action {
+    pub fn execute(&self, simulator: Option<&str>) -> Result<()> {
+        match self {
+            Action::Tap { x, y } => tap(*x, *y, simulator),
+            Action::TapElement { query } => tap_element(query, simulator),
+            Action::LongPress { x, y, duration_ms } => long_press(*x, *y, *duration_ms, simulator),
+            Action::Swipe { x1, y1, x2, y2, duration_ms } => swipe(*x1, *y1, *x2, *y2, *duration_ms, simulator),
+            Action::InputText { text, clear } => input_text(text, *clear, simulator),
+            Action::PressKey { key } => press_key(key, simulator),
+            Action::OpenUrl { url } => open_url(url, simulator),
+            Action::LaunchApp { bundle_id } => launch_app(bundle_id, simulator),
+            Action::StopApp { bundle_id } => stop_app(bundle_id, simulator),
+            Action::Wait { ms } => {
+                std::thread::sleep(std::time::Duration::from_millis(*ms));
+                Ok(())
+            }
+            Action::WaitFor { query, timeout_ms } => wait_for_element(query, *timeout_ms, 500, simulator).map(|_| ()),
+            Action::Screenshot { path } => screenshot_to_file(path, output_format_for_path(path), simulator),
+            Action::AssertElement { query } => assert_element_visible(query, simulator),
+        }
+    }
+}
+
+// ============== REPL ==============
+
+#[derive(Debug, PartialEq)]
+enum ReplCommand {
+    Step(Action),
+    Screenshot(String),
+    Ui,
+    History,
+    Save(String),
+    Help,
+    Quit,
+    Empty,
+}
+
+const REPL_HELP: &str = "\
+Steps are YAML flow mappings, e.g. {action: tap, x: 100, y: 200}
+  !screenshot <path>  save a screenshot
+  !ui                 dump the accessibility tree
+  !history            show executed steps
+  !save <path>        write executed steps as a YAML script
+  !help               show this help
+  !quit               exit (or Ctrl-D)";
+
+fn parse_repl_line(line: &str) -> Result<ReplCommand> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(ReplCommand::Empty);
+    }
+
+    let Some(command) = line.strip_prefix('!') else {
+        let step = serde_yaml::from_str(line).context("Invalid step")?;
+        return Ok(ReplCommand::Step(step));
+    };
+    let (name, arg) = command.split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, arg)| (name, arg.trim()));
+    let path = |what: &str| {
+        if arg.is_empty() {
+            bail!("Usage: !{} <path>", what);
+        }
+        Ok(arg.to_string())
+    };
+    Ok(match name {
+        "screenshot" => ReplCommand::Screenshot(path("screenshot")?),
+        "save" => ReplCommand::Save(path("save")?),
+        "ui" => ReplCommand::Ui,
+        "history" => ReplCommand::History,
+        "help" => ReplCommand::Help,
+        "quit" | "exit" => ReplCommand::Quit,
+        _ => bail!("Unknown command !{} (try !help)", name),
+    })
+}
+
+/// One-line form of a step; JSON flow syntax is also valid YAML, so it can be pasted back in
+fn step_line(step: &Action) -> String {
+    serde_json::to_string(step).unwrap_or_else(|_| format!("{:?}", step))
+}
+
+/// Interactive loop: execute YAML steps read from `input` one at a time and keep a
+/// replayable history; the prompt and command output go to `out`
+pub fn repl(simulator: Option<&str>, input: impl std::io::BufRead, out: &mut impl std::io::Write) -> Result<()> {
+    let _udid = get_simulator_udid(simulator)?;
+    writeln!(out, "{}", REPL_HELP)?;
+
+    let mut history: Vec<Action> = Vec::new();
+    let mut lines = input.lines();
+    loop {
+        write!(out, "> ")?;
+        out.flush()?;
+        let Some(line) = lines.next() else {
+            writeln!(out)?;
+            break;
+        };
+
+        let result = parse_repl_line(&line?).and_then(|command| match command {
+            ReplCommand::Step(step) => {
+                step.execute(simulator)?;
+                writeln!(out, "ok")?;
+                history.push(step);
+                Ok(true)
+            }
+            ReplCommand::Screenshot(path) => {
+                screenshot_to_file(&path, output_format_for_path(&path), simulator)?;
+                writeln!(out, "Screenshot saved to: {}", path)?;
+                Ok(true)
+            }
+            ReplCommand::Ui => {
+                write!(out, "{}", format_tree(&get_ui_tree(simulator)?))?;
+                Ok(true)
+            }
+            ReplCommand::History => {
+                for (i, step) in history.iter().enumerate() {
+                    writeln!(out, "{:>3}: {}", i + 1, step_line(step))?;
+                }
+                Ok(true)
+            }
+            ReplCommand::Save(path) => {
+                std::fs::write(&path, serde_yaml::to_string(&history)?)
+                    .with_context(|| format!("Failed to write {}", path))?;
+                writeln!(out, "Saved {} step(s) to {}", history.len(), path)?;
+                Ok(true)
+            }
+            ReplCommand::Help => {
+                writeln!(out, "{}", REPL_HELP)?;
+                Ok(true)
+            }
+            ReplCommand::Quit => Ok(false),
+            ReplCommand::Empty => Ok(true),
+        });
+
+        match result {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("error: {:#}", e),
+        }
+    }
+    Ok(())
+}
+
+// ============== Recorder ==============
+
+/// Records the actions performed through it so they can be saved and replayed.
+///
+/// Clones share one recording, so a `Recorder` can be handed to several threads.
+/// Each wrapper executes its action immediately and appends it on success while
+/// recording is active.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    actions: Arc<Mutex<Vec<Action>>>,
+    recording: Arc<AtomicBool>,
+    simulator: Option<String>,
+}
+
+impl Recorder {
+    /// Begin recording actions run against `simulator`
+    pub fn start(simulator: Option<&str>) -> Self {
+        Recorder {
+            actions: Arc::new(Mutex::new(Vec::new())),
+            recording: Arc::new(AtomicBool::new(true)),
+            simulator: simulator.map(str::to_string),
+        }
+    }
+
+    fn push(&self, action: Action) {
+        if self.recording.load(Ordering::SeqCst) {
+            self.actions.lock().unwrap().push(action);
+        }
+    }
+
+    /// Execute `action` and record it if it succeeded
+    pub fn record(&self, action: Action) -> Result<()> {
+        action.execute(self.simulator.as_deref())?;
+        self.push(action);
+        Ok(())
+    }
+
+    pub fn tap(&self, x: i32, y: i32) -> Result<()> {
+        self.record(Action::Tap { x, y })
+    }
+
+    pub fn tap_element(&self, query: &str) -> Result<()> {
+        self.record(Action::TapElement { query: query.to_string() })
+    }
+
+    pub fn swipe(&self, x1: i32, y1: i32, x2: i32, y2: i32, duration_ms: u32) -> Result<()> {
+        self.record(Action::Swipe { x1, y1, x2, y2, duration_ms })
+    }
+
+    pub fn input_text(&self, text: &str) -> Result<()> {
+        self.record(Action::InputText { text: text.to_string(), clear: false })
+    }
+
+    pub fn press_key(&self, key: &str) -> Result<()> {
+        self.record(Action::PressKey { key: key.to_string() })
+    }
+
+    pub fn wait(&self, ms: u64) -> Result<()> {
+        self.record(Action::Wait { ms })
+    }
+
+    /// Stop recording and return the actions captured so far
+    pub fn stop(&self) -> Vec<Action> {
+        self.recording.store(false, Ordering::SeqCst);
+        self.actions()
+    }
+
+    /// Actions captured so far
+    pub fn actions(&self) -> Vec<Action> {
+        self.actions.lock().unwrap().clone()
+    }
+
+    /// Write the recorded actions to `path` as a JSON array
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(&self.actions())?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Load a recording saved with `save`; the result is stopped, ready to replay
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let actions: Vec<Action> = serde_json::from_str(&json)
+            .with_context(|| format!("Invalid recording {}", path.display()))?;
+        Ok(Recorder {
+            actions: Arc::new(Mutex::new(actions)),
+            recording: Arc::new(AtomicBool::new(false)),
+            simulator: None,
+        })
+    }
+
+    /// Execute the recorded actions in order on `simulator`, stopping at the first failure
+    pub fn replay(&self, simulator: Option<&str>) -> Result<()> {
+        run_actions(&self.actions(), simulator)
+    }
+}
 
-    if !output.status.success() {
-        bail!("simctl listapps failed: {}", String::from_utf8_lossy(&output.stderr));
+// ============== Scripts ==============
+
+/// Execute `actions` in order, failing with the step number and its details
+fn run_actions(actions: &[Action], simulator: Option<&str>) -> Result<()> {
+    for (i, action) in actions.iter().enumerate() {
+        action.execute(simulator)
+            .with_context(|| format!("Step {} of {} failed: {}", i + 1, actions.len(), step_line(action)))?;
     }
+    Ok(())
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+/// Parse a script: a JSON array of steps, or a YAML list (as saved by the REPL) when `yaml` is set
+fn parse_script(contents: &str, yaml: bool) -> Result<Vec<Action>> {
+    if yaml {
+        serde_yaml::from_str(contents).context("Invalid YAML script")
+    } else {
+        serde_json::from_str(contents).context("Invalid JSON script")
+    }
+}
 
-    let bundle_re = regex::Regex::new(r#"^\s+"([^"]+)"\s+=\s+\{"#).unwrap();
-    let display_re = regex::Regex::new(r#"CFBundleDisplayName\s*=\s*"?([^";]+)"?\s*;"#).unwrap();
+/// Run an automation script: a JSON array of steps such as
+/// `[{"action": "tap", "x": 100, "y": 200}, {"action": "wait_ms", "ms": 500}]`.
+/// `.yaml`/`.yml` files saved from the REPL are accepted too. Returns the number of steps run.
+pub fn run_script(script_path: &str, simulator: Option<&str>) -> Result<usize> {
+    let contents = std::fs::read_to_string(script_path)
+        .with_context(|| format!("Failed to read script {}", script_path))?;
+    let yaml = matches!(
+        Path::new(script_path).extension().and_then(|e| e.to_str()),
+        Some("yaml" | "yml")
+    );
+    let actions = parse_script(&contents, yaml).with_context(|| format!("Failed to load {}", script_path))?;
 
-    let mut apps: Vec<String> = Vec::new();
-    let mut current_bundle: Option<String> = None;
-    let mut current_display: Option<String> = None;
+    run_actions(&actions, simulator)?;
+    Ok(actions.len())
+}
 
-    for line in stdout.lines() {
-        if let Some(cap) = bundle_re.captures(line) {
-            if let Some(bundle) = current_bundle.take() {
-                let display = current_display.take().unwrap_or_default();
-                let entry = if display.is_empty() {
-                    bundle
-                } else {
-                    format!("{} ({})", bundle, display)
-                };
-                apps.push(entry);
-            }
-            current_bundle = Some(cap[1].to_string());
-            current_display = None;
-        } else if current_bundle.is_some() {
-            if let Some(cap) = display_re.captures(line) {
-                current_display = Some(cap[1].trim().to_string());
+// ============== Tests ==============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_simulator_udid_booted() {
+        let result = get_simulator_udid(None);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "booted");
+    }
+
+    #[test]
+    fn test_erase_requires_reason() {
+        assert!(erase_simulator_confirmed(None, "", false).is_err());
+        assert!(erase_simulator_confirmed(None, "   ", true).is_err());
+    }
+
+    #[test]
+    fn test_suggestion_display() {
+        let err = Suggestion::new(anyhow::anyhow!("Simulator 'X' not found"), "Run devices");
+        assert_eq!(err.to_string(), "Simulator 'X' not found\nSuggestion: Run devices");
+
+        let plain = Suggestion { error: anyhow::anyhow!("boom"), suggestion: None };
+        assert_eq!(plain.to_string(), "boom");
+
+        let wrapped: anyhow::Error = with_suggestion(anyhow::anyhow!("a"), "b");
+        assert!(wrapped.downcast_ref::<Suggestion>().is_some());
+        assert_eq!(wrapped.to_string(), "a\nSuggestion: b");
+    }
+
+    #[test]
+    fn test_app_version_parse_and_order() {
+        let v: AppVersion = "1.2.3".parse().unwrap();
+        assert_eq!(v, AppVersion { major: 1, minor: 2, patch: 3 });
+        assert_eq!("4.5".parse::<AppVersion>().unwrap().to_string(), "4.5.0");
+
+        assert!("10.0".parse::<AppVersion>().unwrap() > "9.0".parse().unwrap());
+        assert!("1.10".parse::<AppVersion>().unwrap() > "1.9.9".parse().unwrap());
+        assert_eq!("2.0".parse::<AppVersion>().unwrap(), "2.0.0".parse().unwrap());
+
+        assert!("1".parse::<AppVersion>().is_err());
+        assert!("1.2.3.4".parse::<AppVersion>().is_err());
+        assert!("1.x".parse::<AppVersion>().is_err());
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert!(validate_url("https://example.com/search?q=a%20b").is_ok());
+        assert!(validate_url("myapp://profile/42").is_ok());
+        assert!(validate_url("tel:+15551234567").is_ok());
+
+        assert!(validate_url("not a url").is_err());
+        assert!(validate_url("https://example.com/a b").is_err());
+        assert!(validate_url("https://example.com/%zz").is_err());
+        assert!(validate_url("https://example.com/%4").is_err());
+        assert!(validate_url("javascript:alert(1)").is_err());
+        assert!(validate_url("no-scheme.example.com").is_err());
+    }
+
+    const SHELL_PAYLOADS: &[&str] = &[
+        "; rm -rf /tmp/ios_injection_probe",
+        "`touch /tmp/ios_injection_probe`",
+        "$(touch /tmp/ios_injection_probe)",
+        "' | touch /tmp/ios_injection_probe; echo '",
+    ];
+
+    #[test]
+    fn test_simctl_args_are_not_shell_interpolated() {
+        for payload in SHELL_PAYLOADS {
+            let url = format!("myapp://open?q={}", payload);
+            let cmd = simctl_command(&["openurl", "booted", &url]);
+            let args: Vec<_> = cmd.get_args().collect();
+            assert_eq!(cmd.get_program(), "xcrun");
+            assert_eq!(args.len(), 4);
+            assert_eq!(args[3], url.as_str());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pipe_to_command_treats_text_as_data() {
+        let probe = std::path::Path::new("/tmp/ios_injection_probe");
+        std::fs::remove_file(probe).ok();
+
+        for payload in SHELL_PAYLOADS {
+            let output = pipe_to_command("cat", &[], payload).unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), *payload);
+        }
+
+        assert!(!probe.exists());
+    }
+
+    #[test]
+    fn test_temp_file_paths_are_unique() {
+        let a = temp_file("ios_screenshot_", ".png").unwrap();
+        let b = temp_file("ios_screenshot_", ".png").unwrap();
+        assert_ne!(a.path(), b.path());
+        assert!(a.path().to_string_lossy().ends_with(".png"));
+
+        let path = a.path().to_path_buf();
+        drop(a);
+        assert!(!path.exists());
+    }
+
+    fn element(role: &str, title: &str, x: i32, y: i32, width: i32, height: i32) -> UiElement {
+        UiElement {
+            index: 0,
+            role: role.to_string(),
+            title: title.to_string(),
+            value: String::new(),
+            description: String::new(),
+            identifier: String::new(),
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_elements_to_report() {
+        let elements = vec![
+            element("AXButton", "Login", 10, 20, 100, 40),
+            element("AXCustomThing", "", 0, 0, 5, 5),
+        ];
+
+        let report = elements_to_report(&elements);
+        assert_eq!(report["version"], "1.0");
+        assert!(report["timestamp"].is_string());
+        assert_eq!(report["device_info"]["element_count"], 2);
+
+        let first = &report["elements"][0];
+        assert_eq!(first["role"], "button");
+        assert_eq!(first["name"], "Login");
+        assert_eq!(first["bounds"]["width"], 100);
+        assert_eq!(report["elements"][1]["role"], "customthing");
+    }
+
+    #[test]
+    fn test_filter_interactive() {
+        let elements = vec![
+            element("AXButton", "OK", 0, 0, 10, 10),
+            element("AXStaticText", "Title", 0, 0, 10, 10),
+            element("AXTextField", "", 0, 0, 10, 10),
+        ];
+        let roles: Vec<String> = filter_interactive(elements).into_iter().map(|e| e.role).collect();
+        assert_eq!(roles, vec!["AXButton", "AXTextField"]);
+    }
+
+    #[test]
+    fn test_is_success_status() {
+        assert!(is_success_status("200"));
+        assert!(is_success_status("204\n"));
+        assert!(!is_success_status("301"));
+        assert!(!is_success_status("000"));
+        assert!(!is_success_status(""));
+    }
+
+    #[test]
+    fn test_pinch_points_zoom_in() {
+        let ((f1_start, f1_end), (f2_start, f2_end)) = pinch_points(500, 1000, 2.0);
+        assert_eq!(f1_start, (700, 1000));
+        assert_eq!(f1_end, (900, 1000));
+        assert_eq!(f2_start, (300, 1000));
+        assert_eq!(f2_end, (100, 1000));
+    }
+
+    #[test]
+    fn test_pinch_points_zoom_out_and_identity() {
+        let ((f1_start, f1_end), (f2_start, f2_end)) = pinch_points(500, 1000, 0.5);
+        assert_eq!((f1_start, f1_end), ((700, 1000), (600, 1000)));
+        assert_eq!((f2_start, f2_end), ((300, 1000), (400, 1000)));
+
+        let ((s, e), _) = pinch_points(0, 0, 1.0);
+        assert_eq!(s, e);
+
+        // Fingers stay symmetric around the center for any scale
+        for scale in [0.25, 0.8, 1.5, 3.0] {
+            let ((_, a), (_, b)) = pinch_points(400, 800, scale);
+            assert_eq!(a.0 - 400, 400 - b.0);
+        }
+    }
+
+    #[test]
+    fn test_pinch_rejects_bad_scale() {
+        assert!(pinch(0, 0, 0.0, 300, None).is_err());
+        assert!(pinch(0, 0, -1.0, 300, None).is_err());
+        assert!(pinch(0, 0, f64::NAN, 300, None).is_err());
+    }
+
+    #[test]
+    fn test_rotate_points_clockwise_quarter_turn() {
+        let pts = rotate_points(500, 500, 90.0, 100, 2);
+        assert_eq!(pts.len(), 3);
+        assert_eq!(pts[0], ((600, 500), (400, 500)));
+        assert_eq!(pts[1], ((571, 571), (429, 429)));
+        // Clockwise on screen: 3 o'clock moves down to 6 o'clock
+        assert_eq!(pts[2], ((500, 600), (500, 400)));
+    }
+
+    #[test]
+    fn test_rotate_points_counter_clockwise() {
+        let pts = rotate_points(0, 0, -90.0, 10, 1);
+        assert_eq!(pts.last().unwrap(), &((0, -10), (0, 10)));
+    }
+
+    #[test]
+    fn test_classify_swipe() {
+        assert_eq!(classify_swipe(500, 1500, 500, 500), SwipeDirection::Up);
+        assert_eq!(classify_swipe(500, 500, 520, 1500), SwipeDirection::Down);
+        assert_eq!(classify_swipe(900, 500, 100, 600), SwipeDirection::Left);
+        assert_eq!(classify_swipe(100, 500, 900, 500), SwipeDirection::Right);
+        assert_eq!(classify_swipe(500, 500, 100, 100), SwipeDirection::DiagonalUpLeft);
+        assert_eq!(classify_swipe(500, 500, 900, 250), SwipeDirection::DiagonalUpRight);
+        assert_eq!(classify_swipe(500, 500, 300, 900), SwipeDirection::DiagonalDownLeft);
+        assert_eq!(classify_swipe(0, 0, 10, 10), SwipeDirection::DiagonalDownRight);
+        assert_eq!(classify_swipe(5, 5, 5, 5), SwipeDirection::Right);
+        assert_eq!(SwipeDirection::DiagonalUpLeft.to_string(), "diagonally up-left");
+    }
+
+    #[test]
+    fn test_double_tap_script() {
+        let script = double_tap_script(10, 20, DEFAULT_DOUBLE_TAP_INTERVAL_MS);
+        assert_eq!(script.matches("click at {10, 20}").count(), 2);
+        assert!(script.contains("delay 0.1\n"));
+
+        let script = double_tap_script(10, 20, 0);
+        assert!(script.contains("double click at {10, 20}"));
+        assert_eq!(script.matches("click at").count(), 1);
+    }
+
+    #[test]
+    fn test_interpolate_points() {
+        let pts = interpolate_points((0, 0), (100, 50), 4);
+        assert_eq!(pts, vec![(25, 13), (50, 25), (75, 38), (100, 50)]);
+        assert_eq!(interpolate_points((1, 1), (2, 2), 0), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn test_two_finger_cliclick_args() {
+        let args = two_finger_cliclick_args((50, 50), (60, 70), &[(10, 10), (20, 20)], 30);
+        assert_eq!(args, vec![
+            "m:50,50", "kd:alt,shift", "m:60,70", "ku:shift",
+            "m:10,10", "dd:10,10", "w:30", "dm:20,20", "du:20,20", "ku:alt",
+        ]);
+    }
+
+    #[test]
+    fn test_screen_transform() {
+        // 400x844 window with toolbar, 1200x2400 simulator -> 1/3 scale
+        let t = ScreenTransform::new((100.0, 50.0, 400.0, 844.0), 1200.0, 2400.0);
+        assert_eq!(t.apply(0, 0), (100, 94));
+        assert_eq!(t.apply(1200, 2400), (500, 894));
+        assert_eq!(t.invert(100, 94), (0, 0));
+        assert_eq!(t.invert(500, 894), (1200, 2400));
+    }
+
+    fn solid_png(width: u32, height: u32, rgba: [u8; 4]) -> Vec<u8> {
+        let img = image::RgbaImage::from_pixel(width, height, image::Rgba(rgba));
+        encode_png(&image::DynamicImage::ImageRgba8(img)).unwrap()
+    }
+
+    #[test]
+    fn test_screenshot_diff() {
+        let mut img = image::RgbaImage::from_pixel(10, 10, image::Rgba([0, 0, 0, 255]));
+        let base = encode_png(&image::DynamicImage::ImageRgba8(img.clone())).unwrap();
+        for x in 0..10 {
+            img.put_pixel(x, 0, image::Rgba([255, 0, 0, 255]));
+            img.put_pixel(x, 1, image::Rgba([10, 0, 0, 255]));
+        }
+        let changed = encode_png(&image::DynamicImage::ImageRgba8(img)).unwrap();
+
+        assert_eq!(screenshot_diff(&base, &base, 0.0).unwrap(), 0.0);
+        assert!((screenshot_diff(&base, &changed, 0.0).unwrap() - 0.2).abs() < 1e-9);
+        // Faint row 1 (10/255) falls under a 5% threshold
+        assert!((screenshot_diff(&base, &changed, 0.05).unwrap() - 0.1).abs() < 1e-9);
+
+        let diff = image::load_from_memory(&screenshot_diff_image(&base, &changed).unwrap()).unwrap().to_rgba8();
+        assert_eq!(diff.get_pixel(3, 0), &image::Rgba([255, 0, 0, 255]));
+        assert_ne!(diff.get_pixel(3, 5), &image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_screen_change_watch_waits_for_settle() {
+        let black = solid_png(10, 10, [0, 0, 0, 255]);
+        let grey = solid_png(10, 10, [128, 128, 128, 255]);
+        let white = solid_png(10, 10, [255, 255, 255, 255]);
+
+        let mut watch = ScreenChangeWatch::new(black.clone(), 0.5);
+        assert_eq!(watch.observe(black.clone()), None);
+        // Mid-animation frames do not finish the wait
+        assert_eq!(watch.observe(grey.clone()), None);
+        assert_eq!(watch.observe(white.clone()), None);
+        assert_eq!(watch.observe(white.clone()), None);
+        assert_eq!(watch.observe(white.clone()), Some(1.0));
+
+        // A change below the threshold never counts
+        let mut watch = ScreenChangeWatch::new(black.clone(), 1.0);
+        for _ in 0..5 {
+            assert_eq!(watch.observe(white.clone()), None);
+        }
+        // Rotation (a size change) counts as a full change
+        let mut watch = ScreenChangeWatch::new(black, 0.5);
+        let rotated = solid_png(10, 20, [0, 0, 0, 255]);
+        assert_eq!(watch.observe(rotated.clone()), None);
+        assert_eq!(watch.observe(rotated.clone()), None);
+        assert_eq!(watch.observe(rotated), Some(1.0));
+    }
+
+    #[test]
+    fn test_screenshot_diff_dimension_mismatch() {
+        let a = solid_png(10, 10, [0, 0, 0, 255]);
+        let b = solid_png(10, 11, [0, 0, 0, 255]);
+        assert!(screenshot_diff(&a, &b, 0.1).is_err());
+        assert!(screenshot_diff_image(&a, &b).is_err());
+    }
+
+    #[test]
+    fn test_output_format() {
+        assert_eq!(OutputFormat::Png.extension(), ".png");
+        assert_eq!(OutputFormat::Jpeg { quality: 80 }.extension(), ".jpg");
+        assert!(OutputFormat::Jpeg { quality: 0 }.validate().is_err());
+        assert!(OutputFormat::Jpeg { quality: 101 }.validate().is_err());
+        assert!(OutputFormat::Jpeg { quality: 100 }.validate().is_ok());
+    }
+
+    #[test]
+    fn test_write_jpeg() {
+        let img = image::load_from_memory(&solid_png(8, 8, [200, 10, 10, 255])).unwrap();
+        let mut jpeg = Vec::new();
+        write_jpeg(&img, 70, &mut jpeg).unwrap();
+        assert_eq!(image::guess_format(&jpeg).unwrap(), image::ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn test_validate_video_extension() {
+        assert!(validate_video_extension("/tmp/run.mp4").is_ok());
+        assert!(validate_video_extension("clip.MOV").is_ok());
+        assert!(validate_video_extension("clip.gif").is_err());
+        assert!(validate_video_extension("clip").is_err());
+    }
+
+    #[test]
+    fn test_crop_png() {
+        let png = solid_png(100, 200, [1, 2, 3, 255]);
+        let cropped = image::load_from_memory(&crop_png(&png, 10, 20, 30, 40).unwrap()).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (30, 40));
+
+        assert!(crop_png(&png, 0, 0, 100, 200).is_ok());
+        assert!(crop_png(&png, 90, 0, 20, 10).is_err());
+        assert!(crop_png(&png, -1, 0, 10, 10).is_err());
+        assert!(crop_png(&png, 0, 0, 0, 10).is_err());
+    }
+
+    #[test]
+    fn test_parse_installed_apps() {
+        let stdout = r#"{
+    "com.apple.mobilesafari" =     {
+        ApplicationType = System;
+        Bundle = "file:///Library/Developer/CoreSimulator/Volumes/iOS_21C62/Applications/MobileSafari.app/";
+        CFBundleDisplayName = Safari;
+        CFBundleIdentifier = "com.apple.mobilesafari";
+        CFBundleShortVersionString = "17.2";
+        CFBundleVersion = "8617.1.17.10.9";
+        DataContainer = "file:///Users/me/Library/Developer/CoreSimulator/Devices/ABC/data/Containers/Data/Application/1234/";
+        GroupContainers =         {
+        };
+    };
+    "com.example.app.debug" =     {
+        CFBundleIdentifier = "com.example.app.debug";
+        CFBundleName = Example;
+        Bundle = "file:///Users/me/My%20Apps/%23Build%20%C3%A9/Example.app/";
+    };
+}"#;
+        let apps = parse_installed_apps(stdout).unwrap();
+        assert_eq!(apps.len(), 2);
+
+        let safari = apps.iter().find(|a| a.bundle_id == "com.apple.mobilesafari").unwrap();
+        assert_eq!(safari.display_name, "Safari");
+        assert_eq!(safari.short_version, "17.2");
+        assert_eq!(safari.version, "8617.1.17.10.9");
+        assert!(safari.bundle_path.ends_with("/MobileSafari.app"));
+        assert!(safari.data_container_path.as_deref().unwrap().ends_with("/Application/1234"));
+
+        let example = apps.iter().find(|a| a.bundle_id == "com.example.app.debug").unwrap();
+        assert_eq!(example.display_name, "Example");
+        assert_eq!(example.bundle_path, "/Users/me/My Apps/#Build é/Example.app");
+        assert_eq!(example.data_container_path, None);
+
+        assert!(parse_installed_apps("not a plist {").is_err());
+        assert_eq!(file_url_to_path("/Users/me/Plain/Example.app/"), "/Users/me/Plain/Example.app");
+        assert_eq!(file_url_to_path("file:///"), "/");
+    }
+
+    #[test]
+    fn test_is_affirmative() {
+        assert!(is_affirmative("y\n"));
+        assert!(is_affirmative(" YES "));
+        assert!(!is_affirmative("\n"));
+        assert!(!is_affirmative("no"));
+    }
+
+    #[test]
+    fn test_resolve_container_path() {
+        let root = Path::new("/data/Containers/Data/Application/ABC");
+        assert_eq!(
+            resolve_container_path(root, "Documents/seed.json").unwrap(),
+            root.join("Documents/seed.json")
+        );
+        assert_eq!(
+            resolve_container_path(root, "/Library/./Preferences/x.plist").unwrap(),
+            root.join("Library/Preferences/x.plist")
+        );
+        assert!(resolve_container_path(root, "../other/secret").is_err());
+        assert!(resolve_container_path(root, "Documents/../../x").is_err());
+        assert!(resolve_container_path(root, "/").is_err());
+    }
+
+    #[test]
+    fn test_find_toggle() {
+        let mut switch = element("AXSwitch", "Low Power Mode", 300, 200, 50, 30);
+        switch.value = "0".to_string();
+        let elements = vec![element("AXStaticText", "Low Power Mode", 20, 200, 200, 30), switch];
+
+        let found = find_toggle(&elements, "low power mode").unwrap();
+        assert_eq!(found.role, "AXSwitch");
+        assert_eq!(toggle_state(found), Some(false));
+        assert!(find_toggle(&elements, "Wi-Fi").is_none());
+    }
+
+    #[test]
+    fn test_user_default_args() {
+        assert_eq!(UserDefaultValue::Int(3).defaults_args(), ["-int".to_string(), "3".to_string()]);
+        assert_eq!(UserDefaultValue::Bool(true).defaults_args(), ["-bool".to_string(), "true".to_string()]);
+        assert_eq!(
+            UserDefaultValue::String("a b".into()).defaults_args(),
+            ["-string".to_string(), "a b".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_remote_path() {
+        assert_eq!(
+            parse_remote_path("Documents/a.txt").unwrap(),
+            RemotePath { bundle_id: None, kind: ContainerKind::Data, path: "Documents/a.txt".into() }
+        );
+        assert_eq!(
+            parse_remote_path("@com.example.app:Library/x.plist").unwrap(),
+            RemotePath { bundle_id: Some("com.example.app".into()), kind: ContainerKind::Data, path: "Library/x.plist".into() }
+        );
+        assert_eq!(parse_remote_path("@com.example.app/app:Info.plist").unwrap().kind, ContainerKind::App);
+
+        let group = parse_remote_path("@com.example.app/groups:group.com.example/db.sqlite").unwrap();
+        assert_eq!(group.kind, ContainerKind::Group("group.com.example".into()));
+        assert_eq!(group.path, "db.sqlite");
+
+        assert!(parse_remote_path("@com.example.app").is_err());
+        assert!(parse_remote_path("@:x").is_err());
+        assert!(parse_remote_path("@com.example.app/cache:x").is_err());
+    }
+
+    #[test]
+    fn test_resolve_remote_path_with_mock_containers() {
+        let lookup = |bundle: Option<&str>, kind: &ContainerKind| -> Result<PathBuf> {
+            match (bundle, kind) {
+                (None, _) => Ok(PathBuf::from("/sim/data")),
+                (Some("com.example.app"), ContainerKind::Data) => Ok(PathBuf::from("/containers/Data/APP")),
+                (Some(bundle), kind) => Err(IosError::ContainerNotFound {
+                    bundle_id: bundle.to_string(),
+                    container: kind.simctl_arg().to_string(),
+                    message: "not installed".into(),
+                }.into()),
             }
+        };
+
+        assert_eq!(
+            resolve_remote_path("@com.example.app:Documents/a.txt", lookup).unwrap(),
+            PathBuf::from("/containers/Data/APP/Documents/a.txt")
+        );
+        assert_eq!(resolve_remote_path("tmp/b", lookup).unwrap(), PathBuf::from("/sim/data/tmp/b"));
+
+        let err = resolve_remote_path("@com.missing:x", lookup).unwrap_err();
+        assert!(matches!(err.downcast_ref::<IosError>(), Some(IosError::ContainerNotFound { .. })));
+    }
+
+    #[test]
+    fn test_pull_file_from_booted_simulator() {
+        let booted = simctl_exec(&["list", "devices", "booted"])
+            .map(|o| String::from_utf8_lossy(&o.stdout).contains("Booted"))
+            .unwrap_or(false);
+        if !booted {
+            eprintln!("Skipping: no booted simulator");
+            return;
         }
+
+        let local = temp_file("pull", ".plist").unwrap();
+        let local_path = local.path().to_str().unwrap();
+        assert!(pull_file("Library/Preferences/.GlobalPreferences.plist", local_path, None).is_ok());
+
+        let err = pull_file("@com.example.definitely.missing:x", local_path, None).unwrap_err();
+        assert!(matches!(err.downcast_ref::<IosError>(), Some(IosError::ContainerNotFound { .. })));
+    }
+
+    #[test]
+    fn test_match_element_skips_zero_size() {
+        let elements = vec![
+            element("AXButton", "Continue", 0, 0, 0, 0),
+            element("AXButton", "Continue", 10, 20, 100, 40),
+        ];
+        let found = match_element(&elements, "continue").unwrap();
+        assert_eq!(element_center(found), (60, 40));
+        assert!(match_element(&elements, "Cancel").is_none());
+
+        let mut icon = element("AXButton", "", 0, 0, 44, 44);
+        icon.identifier = "settings_gear".to_string();
+        assert!(match_element(&[icon.clone()], "settings_gear").is_some());
+        assert!(match_element(&[icon], "settings").is_none());
+    }
+
+    #[test]
+    fn test_poll_until() {
+        let mut attempts = 0;
+        let value = poll_until(1000, 1, "value", || {
+            attempts += 1;
+            Ok((attempts == 3).then_some(attempts))
+        }).unwrap();
+        assert_eq!(value, 3);
+
+        let err = poll_until(20, 5, "never", || Ok(None::<()>)).unwrap_err();
+        assert!(err.to_string().starts_with("Timed out waiting for never after "));
+    }
+
+    #[test]
+    fn test_parse_network_services() {
+        let stdout = "An asterisk (*) denotes that a network service is disabled.\nWi-Fi\n*Bluetooth PAN\nThunderbolt Bridge\n";
+        assert_eq!(parse_network_services(stdout), vec!["Wi-Fi", "Thunderbolt Bridge"]);
+    }
+
+    #[test]
+    fn test_restore_proxy_args() {
+        let corporate = parse_proxy_setting("Enabled: Yes\nServer: proxy.corp\nPort: 8080\nAuthenticated Proxy Enabled: 0\n");
+        assert_eq!(corporate, ProxySetting { enabled: true, server: "proxy.corp".into(), port: "8080".into() });
+        assert_eq!(
+            restore_proxy_args("-setwebproxy", "-setwebproxystate", "Wi-Fi", &corporate),
+            vec![
+                vec!["-setwebproxy", "Wi-Fi", "proxy.corp", "8080"],
+                vec!["-setwebproxystate", "Wi-Fi", "on"],
+            ]
+        );
+
+        let unset = parse_proxy_setting("Enabled: No\nServer: \nPort: 0\nAuthenticated Proxy Enabled: 0\n");
+        assert_eq!(
+            restore_proxy_args("-setsecurewebproxy", "-setsecurewebproxystate", "Wi-Fi", &unset),
+            vec![vec!["-setsecurewebproxystate", "Wi-Fi", "off"]]
+        );
+    }
+
+    #[test]
+    fn test_filter_by_role() {
+        let mut cell = element("AXCell", "Inbox", 0, 100, 300, 44);
+        cell.value = "3 unread".to_string();
+        let elements = vec![
+            element("AXButton", "Edit", 10, 10, 40, 20),
+            cell,
+            element("AXCell", "Archive", 0, 144, 300, 44),
+        ];
+
+        assert_eq!(filter_by_role(elements.clone(), "axcell", None).len(), 2);
+        let unread = filter_by_role(elements.clone(), "AXCell", Some("UNREAD"));
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].title, "Inbox");
+        assert!(filter_by_role(elements, "AXSwitch", None).is_empty());
+    }
+
+    #[test]
+    fn test_elements_to_xcui_xml() {
+        let mut field = element("AXTextField", "", 20, 80, 335, 44);
+        field.description = "Email".to_string();
+        field.value = "a&b@example.com".to_string();
+        let xml = elements_to_xcui_xml(&[element("AXButton", "Sign \"In\"", 20, 140, 335, 50), field]);
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<XCUIElementSnapshot type=\"Window\""));
+        assert!(xml.contains(r#"<XCUIElementSnapshot type="Button" label="Sign &quot;In&quot;" value="" frame="{{20, 140}, {335, 50}}"/>"#));
+        assert!(xml.contains(r#"type="TextField" label="Email" value="a&amp;b@example.com" frame="{{20, 80}, {335, 44}}""#));
+        assert!(xml.contains(r#"type="Window" label="Simulator" value="" frame="{{20, 80}, {335, 110}}">"#));
+        assert!(xml.trim_end().ends_with("</XCUIElementSnapshot>"));
+    }
+
+    #[test]
+    fn test_median_duration() {
+        let ms = |v: &[u64]| v.iter().map(|&m| std::time::Duration::from_millis(m)).collect::<Vec<_>>();
+        assert_eq!(median_duration(ms(&[900, 120, 130, 110, 125])), Some(std::time::Duration::from_millis(125)));
+        assert_eq!(median_duration(ms(&[100, 300])), Some(std::time::Duration::from_millis(200)));
+        assert_eq!(median_duration(Vec::new()), None);
+    }
+
+    #[test]
+    fn test_parse_element_line() {
+        let button = parse_element_line("0|AXButton|Log in|missing value|button|20,140|335x50|login_button").unwrap();
+        assert_eq!(button.identifier, "login_button");
+        assert_eq!(button.value, "");
+        assert_eq!(button.description, "button");
+
+        let image = parse_element_line("2|AXImage|||logo|10,10|40x40").unwrap();
+        assert_eq!(image.identifier, "");
+        assert_eq!((image.width, image.height), (40, 40));
+
+        assert!(parse_element_line("garbage").is_none());
+        assert!(parse_element_line("3|AXGroup||||x,y|1x1").is_none());
+    }
+
+    #[test]
+    fn test_parse_accessibility_tree() {
+        let stdout = "0|0|AXGroup||||0,0|390x844|\n\
+                      1|1|AXCell|Inbox|||0,100|390x44|\n\
+                      2|2|AXButton|Delete|||300,100|90x44|\n\
+                      1|3|AXCell|Archive|||0,144|390x44|\n\
+                      2|4|AXButton|Delete|||300,144|90x44|\n\
+                      0|5|AXButton|Done|||300,40|80x30|\n";
+        let tree = parse_accessibility_tree(stdout);
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].children.len(), 2);
+        // "the button inside the second cell"
+        let second_cell = &tree[0].children[1];
+        assert_eq!(second_cell.element.title, "Archive");
+        assert_eq!(second_cell.children[0].element.index, 4);
+
+        let flat = flatten_tree(&tree);
+        assert_eq!(flat.iter().map(|e| e.index).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_format_tree() {
+        let tree = vec![UiElementNode {
+            element: element("AXCell", "Inbox", 0, 100, 390, 44),
+            children: vec![UiElementNode { element: element("AXButton", "Delete", 300, 100, 90, 44), children: vec![] }],
+        }];
+        assert_eq!(
+            format_tree(&tree),
+            "[0] AXCell \"Inbox\" (0,100 390x44)\n  [0] AXButton \"Delete\" (300,100 90x44)\n"
+        );
+    }
+
+    #[test]
+    fn test_capability_report() {
+        let pro = capability_report("iPhone-15-Pro").unwrap();
+        assert!(pro.has_face_id && pro.has_dynamic_island && !pro.has_home_button);
+        assert_eq!(pro.screen_size_inches, 6.1);
+
+        let se = capability_report("iPhone-SE-3rd-generation").unwrap();
+        assert!(se.has_touch_id && se.has_home_button && !se.has_face_id);
+
+        let ipad = capability_report("iPad-Pro-11-inch-4th-generation").unwrap();
+        assert!(ipad.supports_pencil && ipad.has_face_id);
+
+        assert_eq!(lookup_device_spec("iPhone-15-Plus").unwrap().inches, 6.7);
+        assert!(lookup_device_spec("iPhone-150").is_none());
+        assert!(capability_report("Apple-Watch-Series-9-45mm").is_err());
+    }
+
+    #[test]
+    fn test_scroll_swipe() {
+        assert_eq!(scroll_swipe(ScrollDirection::Down, 1000, 2000), (500, 1800, 500, 200));
+        assert_eq!(scroll_swipe(ScrollDirection::Up, 1000, 2000), (500, 200, 500, 1800));
+        assert_eq!(scroll_swipe(ScrollDirection::Left, 1000, 2000), (100, 1000, 900, 1000));
+        assert_eq!(scroll_swipe(ScrollDirection::Right, 1000, 2000), (900, 1000, 100, 1000));
+    }
+
+    #[test]
+    fn test_parse_fixture() {
+        let fixture: Fixture = serde_json::from_str(
+            r#"{"target": "user_defaults", "values": {"onboarded": true, "launches": 3, "ratio": 0.5, "name": "qa"}}"#,
+        ).unwrap();
+        let Fixture::UserDefaults { values } = fixture else { panic!("expected user_defaults") };
+        assert_eq!(user_default_from_json("onboarded", &values["onboarded"]).unwrap(), UserDefaultValue::Bool(true));
+        assert_eq!(user_default_from_json("launches", &values["launches"]).unwrap(), UserDefaultValue::Int(3));
+        assert_eq!(user_default_from_json("ratio", &values["ratio"]).unwrap(), UserDefaultValue::Float(0.5));
+        assert!(user_default_from_json("list", &serde_json::json!([1])).is_err());
+
+        let fixture: Fixture = serde_json::from_str(
+            r#"{"target": "sqlite", "database": "Library/app.sqlite", "statements": ["DELETE FROM items"]}"#,
+        ).unwrap();
+        assert!(matches!(fixture, Fixture::Sqlite { ref statements, .. } if statements.len() == 1));
+
+        assert!(serde_json::from_str::<Fixture>(r#"{"target": "keychain"}"#).is_err());
+    }
+
+    #[test]
+    fn test_dnctl_config_args() {
+        let [out, inbound] = dnctl_config_args(&NetworkProfile::Edge.conditions().unwrap());
+        assert_eq!(out, ["pipe", "1", "config", "delay", "400", "bw", "240Kbit/s"]);
+        assert_eq!(inbound, ["pipe", "2", "config", "bw", "240Kbit/s"]);
+
+        let lossy = NetworkProfile::Custom { latency_ms: 0, bandwidth_kbps: 0, loss_pct: 5.0 };
+        let [out, inbound] = dnctl_config_args(&lossy.conditions().unwrap());
+        assert_eq!(out, ["pipe", "1", "config", "plr", "0.05"]);
+        assert_eq!(inbound, ["pipe", "2", "config"]);
+
+        assert!(NetworkProfile::None.conditions().is_none());
+        assert_eq!(network_pf_rules(), "dummynet out all pipe 1\ndummynet in all pipe 2\n");
+    }
+
+    #[test]
+    fn test_parse_dnctl_list() {
+        let edge = "00001: 240.000 Kbit/s  400 ms   50 sl. 0 queues (1 buckets) droptail\n\
+                    00002: 240.000 Kbit/s    0 ms   50 sl. 0 queues (1 buckets) droptail\n";
+        assert_eq!(NetworkProfile::from_conditions(parse_dnctl_list(edge).unwrap()), NetworkProfile::Edge);
+
+        let custom = "00001:   2.000 Mbit/s   80 ms   50 sl.plr 0.010000 0 queues (1 buckets) droptail\n";
+        assert_eq!(
+            NetworkProfile::from_conditions(parse_dnctl_list(custom).unwrap()),
+            NetworkProfile::Custom { latency_ms: 80, bandwidth_kbps: 2000, loss_pct: 1.0 }
+        );
+
+        let latency = "00001: unlimited  250 ms   50 sl. 0 queues (1 buckets) droptail\n";
+        assert_eq!(NetworkProfile::from_conditions(parse_dnctl_list(latency).unwrap()), NetworkProfile::HighLatency(250));
+        assert!(parse_dnctl_list("").is_none());
+    }
+
+    #[test]
+    fn test_pf_token_and_owned_pipes() {
+        assert_eq!(parse_pf_token("No ALTQ support in kernel\npf enabled\nToken : 13835058055282163223\n").unwrap(), "13835058055282163223");
+        assert!(parse_pf_token("pf already enabled\n").is_none());
+
+        let list = "00001: 240.000 Kbit/s  400 ms   50 sl.plr 0.010000\n00007: unlimited    0 ms   50 sl. 0 queues\n";
+        assert_eq!(owned_pipes(list), vec![NETWORK_PIPE_OUT]);
+        assert!(owned_pipes("").is_empty());
+    }
+
+    #[test]
+    fn test_lane_name() {
+        assert_eq!(lane_name("iPhone 15", 1), "iPhone 15 Lane 1");
+        assert_eq!(lane_name("iPhone 15 Pro", 12), "iPhone 15 Pro Lane 12");
+    }
+
+    #[test]
+    fn test_validate_coordinate() {
+        assert!(validate_coordinate(90.0, -180.0).is_ok());
+        assert!(validate_coordinate(-33.86, 151.21).is_ok());
+        assert!(validate_coordinate(90.5, 0.0).is_err());
+        assert!(validate_coordinate(0.0, 180.1).is_err());
+        assert!(validate_coordinate(f64::NAN, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_route_positions() {
+        // One degree of latitude is ~111.2 km
+        let route = [(0.0, 0.0), (1.0, 0.0)];
+        let positions = route_positions(&route, 50_000.0);
+        assert_eq!(positions.len(), 4);
+        assert_eq!(positions[0], (0.0, 0.0));
+        assert!((haversine_m(positions[0], positions[1]) - 50_000.0).abs() < 1.0);
+        assert!((haversine_m(positions[1], positions[2]) - 50_000.0).abs() < 1.0);
+        assert_eq!(positions[3], (1.0, 0.0));
+
+        // Spacing carries over across waypoints
+        let bent = [(0.0, 0.0), (0.3, 0.0), (0.3, 0.3)];
+        let positions = route_positions(&bent, 25_000.0);
+        assert!(positions[2].0 == 0.3 && positions[2].1 > 0.0);
+        assert_eq!(*positions.last().unwrap(), (0.3, 0.3));
+
+        assert_eq!(route_positions(&[(1.0, 2.0)], 10.0), vec![(1.0, 2.0)]);
+        assert!(route_positions(&[], 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_parse_xcodebuild_test_output() {
+        let stdout = "\
+Test Suite 'LoginTests' started at 2024-01-01 10:00:00.000
+Test Case '-[AppUITests.LoginTests testLogin]' started.
+Test Case '-[AppUITests.LoginTests testLogin]' passed (1.234 seconds).
+Test Case '-[AppUITests.LoginTests testLogout]' started.
+/src/AppUITests/LoginTests.swift:42: error: -[AppUITests.LoginTests testLogout] : XCTAssertTrue failed - button missing
+Test Case '-[AppUITests.LoginTests testLogout]' failed (2.000 seconds).
+Test Case '-[AppUITests.LoginTests testOffline]' skipped (0.001 seconds).
+Test case 'SettingsTests.testToggle()' passed on 'Clone 1 of iPhone 15' (0.5 seconds)
+Test case 'SettingsTests.testReset()' failed on 'Clone 2 of iPhone 15' (0.7 seconds)
+";
+        let result = parse_xcodebuild_test_output(stdout);
+        assert_eq!((result.passed, result.failed, result.skipped), (2, 2, 1));
+        assert_eq!(result.failures.len(), 2);
+        assert_eq!(result.failures[0], TestFailure {
+            test_name: "AppUITests.LoginTests.testLogout".to_string(),
+            message: "XCTAssertTrue failed - button missing".to_string(),
+            file: Some("/src/AppUITests/LoginTests.swift".to_string()),
+            line: Some(42),
+        });
+        assert_eq!(result.failures[1].test_name, "SettingsTests.testReset()");
+        assert!(result.failures[1].file.is_none());
+    }
+
+    #[test]
+    fn test_action_yaml() {
+        let step: Action = serde_yaml::from_str("{action: tap, x: 100, y: 200}").unwrap();
+        assert_eq!(step, Action::Tap { x: 100, y: 200 });
+
+        let step: Action = serde_yaml::from_str("{action: swipe, x1: 0, y1: 500, x2: 0, y2: 100}").unwrap();
+        assert_eq!(step, Action::Swipe { x1: 0, y1: 500, x2: 0, y2: 100, duration_ms: 300 });
+
+        let step: Action = serde_yaml::from_str("{action: wait_ms, ms: 250}").unwrap();
+        assert_eq!(step, Action::Wait { ms: 250 });
+
+        assert!(serde_yaml::from_str::<Action>("{action: fly}").is_err());
+        assert!(serde_yaml::from_str::<Action>("{action: tap, x: 1}").is_err());
+
+        let script = vec![
+            Action::InputText { text: "hello: world".to_string(), clear: false },
+            Action::PressKey { key: "enter".to_string() },
+        ];
+        let yaml = serde_yaml::to_string(&script).unwrap();
+        assert!(!yaml.contains("clear"));
+        assert_eq!(serde_yaml::from_str::<Vec<Action>>(&yaml).unwrap(), script);
+    }
+
+    #[test]
+    fn test_parse_repl_line() {
+        assert_eq!(parse_repl_line("  ").unwrap(), ReplCommand::Empty);
+        assert_eq!(parse_repl_line("# note").unwrap(), ReplCommand::Empty);
+        assert_eq!(
+            parse_repl_line("{action: tap_element, query: Login}").unwrap(),
+            ReplCommand::Step(Action::TapElement { query: "Login".to_string() })
+        );
+        assert_eq!(parse_repl_line("!screenshot /tmp/a b.png").unwrap(), ReplCommand::Screenshot("/tmp/a b.png".to_string()));
+        assert_eq!(parse_repl_line("!save out.yaml").unwrap(), ReplCommand::Save("out.yaml".to_string()));
+        assert_eq!(parse_repl_line("!ui").unwrap(), ReplCommand::Ui);
+        assert_eq!(parse_repl_line("!history").unwrap(), ReplCommand::History);
+        assert_eq!(parse_repl_line("!exit").unwrap(), ReplCommand::Quit);
+        assert!(parse_repl_line("!save").is_err());
+        assert!(parse_repl_line("!bogus").is_err());
+        assert!(parse_repl_line("tap 1 2").is_err());
+    }
+
+    #[test]
+    fn test_output_format_for_path() {
+        assert_eq!(output_format_for_path("shot.JPG"), OutputFormat::Jpeg { quality: 80 });
+        assert_eq!(output_format_for_path("shot.png"), OutputFormat::Png);
+        assert_eq!(output_format_for_path("shot"), OutputFormat::Png);
+    }
+
+    #[test]
+    fn test_notification_payload() {
+        let payload = NotificationPayload::new()
+            .title("Sale")
+            .body("50% off today")
+            .badge(3)
+            .sound("default")
+            .custom_data("deeplink", serde_json::json!("myapp://sale"))
+            .custom_data("aps", serde_json::json!("ignored"))
+            .build();
+        assert_eq!(payload, serde_json::json!({
+            "aps": {
+                "alert": { "title": "Sale", "body": "50% off today" },
+                "badge": 3,
+                "sound": "default",
+            },
+            "deeplink": "myapp://sale",
+        }));
+
+        assert_eq!(NotificationPayload::new().badge(0).build(), serde_json::json!({ "aps": { "badge": 0 } }));
+    }
+
+    #[test]
+    fn test_privacy_args() {
+        let service = PermissionService::MediaLibrary.to_string();
+        assert_eq!(
+            privacy_args("ABC", PermissionAction::Grant, &service, "com.example.app"),
+            ["privacy", "ABC", "grant", "media-library", "com.example.app"]
+        );
+        assert_eq!(privacy_args("booted", PermissionAction::Reset, "camera", "x")[2], "reset");
+        assert_eq!(PermissionService::SpeechRecognition.as_str(), "speech-recognition");
+    }
+
+    #[test]
+    fn test_user_defaults_plist_conversion() {
+        let value = serde_json::json!({"flags": {"beta": true, "limit": 3}, "tags": ["a&b", 1.5]});
+        let xml = json_to_plist_xml(&value).unwrap();
+        assert_eq!(
+            xml,
+            "<dict><key>flags</key><dict><key>beta</key><true/><key>limit</key><integer>3</integer></dict>\
+             <key>tags</key><array><string>a&amp;b</string><real>1.5</real></array></dict>"
+        );
+        assert!(json_to_plist_xml(&serde_json::json!([null])).is_err());
+
+        // What `defaults export` produces round-trips back to the same JSON
+        let exported = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">{}</plist>",
+            xml
+        );
+        let parsed = plist::Value::from_reader_xml(exported.as_bytes()).unwrap();
+        assert_eq!(plist_to_json(parsed), value);
     }
-    if let Some(bundle) = current_bundle {
-        let display = current_display.unwrap_or_default();
-        let entry = if display.is_empty() { bundle } else { format!("{} ({})", bundle, display) };
-        apps.push(entry);
+
+    #[test]
+    fn test_parse_device_types_and_runtimes() {
+        let types = serde_json::json!({"devicetypes": [
+            {"name": "iPhone 15", "identifier": "com.apple.CoreSimulator.SimDeviceType.iPhone-15", "productFamily": "iPhone"},
+            {"identifier": "broken"},
+        ]});
+        assert_eq!(parse_device_types(&types), vec![DeviceType {
+            name: "iPhone 15".to_string(),
+            identifier: "com.apple.CoreSimulator.SimDeviceType.iPhone-15".to_string(),
+        }]);
+
+        let runtimes = serde_json::json!({"runtimes": [
+            {"name": "iOS 17.0", "identifier": "com.apple.CoreSimulator.SimRuntime.iOS-17-0", "version": "17.0", "isAvailable": true},
+            {"name": "iOS 16.4", "identifier": "com.apple.CoreSimulator.SimRuntime.iOS-16-4", "version": "16.4"},
+        ]});
+        let parsed = parse_runtimes(&runtimes);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].version, "17.0");
+        assert!(parsed[0].is_available);
+        assert!(!parsed[1].is_available);
+        assert!(parse_runtimes(&serde_json::json!({})).is_empty());
     }
 
-    if let Some(f) = filter {
-        let f_lower = f.to_lowercase();
-        apps.retain(|a| a.to_lowercase().contains(&f_lower));
+    #[test]
+    fn test_parse_appearance() {
+        assert_eq!(parse_appearance("dark\n").unwrap(), Appearance::Dark);
+        assert_eq!(parse_appearance("light").unwrap().opposite(), Appearance::Dark);
+        assert!(parse_appearance("unsupported").is_err());
     }
 
-    apps.sort();
-    apps.dedup();
+    #[test]
+    fn test_status_bar_args() {
+        let args = StatusBarOverride::new()
+            .time("9:41")
+            .battery_level(100)
+            .battery_state(BatteryState::Full)
+            .cellular_bars(4)
+            .carrier_name("Carrier")
+            .simctl_args()
+            .unwrap();
+        assert_eq!(args, [
+            "--time", "9:41", "--batteryLevel", "100", "--batteryState", "charged",
+            "--cellularMode", "active", "--cellularBars", "4", "--operatorName", "Carrier",
+        ]);
+
+        assert_eq!(StatusBarOverride::new().wifi_bars(2).simctl_args().unwrap(), ["--wifiMode", "active", "--wifiBars", "2"]);
+        assert!(StatusBarOverride::new().battery_level(101).simctl_args().is_err());
+        assert!(StatusBarOverride::new().wifi_bars(4).simctl_args().is_err());
+        assert!(StatusBarOverride::new().simctl_args().is_err());
+    }
 
-    println!("Installed apps ({}):", apps.len());
-    for app in &apps {
-        println!("  {}", app);
+    #[test]
+    fn test_validate_bcp47() {
+        for tag in ["en", "en-US", "en_GB", "zh-Hans-CN", "es-419", "fil"] {
+            assert!(validate_bcp47(tag).is_ok(), "{}", tag);
+        }
+        for tag in ["", "e", "english", "en-", "en-USA", "en US", "en-US;rm -rf"] {
+            assert!(validate_bcp47(tag).is_err(), "{}", tag);
+        }
     }
-    Ok(())
-}
 
-/// Launch an app
-pub fn launch_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_log_filter_args() {
+        assert!(log_filter_args(true, None, None, None).is_empty());
+        assert_eq!(log_filter_args(false, Some("MyApp"), None, None), ["--predicate", "processImagePath CONTAINS 'MyApp'"]);
+        assert_eq!(
+            log_filter_args(true, Some("it's"), Some(LogLevel::Debug), Some("com.example.net")),
+            ["--level", "debug", "--predicate", "processImagePath CONTAINS 'it\\'s' AND subsystem == 'com.example.net'"]
+        );
+        assert_eq!(log_filter_args(true, None, Some("Info".parse().unwrap()), None), ["--level", "info"]);
+        assert_eq!(log_filter_args(false, None, Some(LogLevel::Info), None), ["--info"]);
+        assert_eq!(log_filter_args(false, None, Some(LogLevel::Debug), None), ["--debug"]);
+        assert!(log_filter_args(false, None, Some(LogLevel::Default), None).is_empty());
+        assert!("error".parse::<LogLevel>().is_err());
+    }
 
-    let output = simctl_exec(&["launch", &udid, bundle_id])?;
+    #[test]
+    fn test_parse_crash_metadata() {
+        let ips = "{\"app_name\":\"MyApp\",\"timestamp\":\"2024-01-15 10:23:45.00 +0100\",\"bundleID\":\"com.example.myapp\"}\n{}";
+        let (bundle, timestamp) = parse_crash_metadata(ips).unwrap();
+        assert_eq!(bundle, "com.example.myapp");
+        assert_eq!(timestamp.unwrap().to_rfc3339(), "2024-01-15T09:23:45+00:00");
+
+        let crash = "Process:               MyApp [1234]\n\
+                     Identifier:            com.example.myapp\n\
+                     Date/Time:             2024-01-15 10:23:45.123 -0500\n";
+        let (bundle, timestamp) = parse_crash_metadata(crash).unwrap();
+        assert_eq!(bundle, "com.example.myapp");
+        assert_eq!(timestamp.unwrap().to_rfc3339(), "2024-01-15T15:23:45.123+00:00");
+
+        assert!(parse_crash_metadata("no metadata here").is_none());
+    }
 
-    if !output.status.success() {
-        bail!("Failed to launch {}: {}", bundle_id, String::from_utf8_lossy(&output.stderr));
+    #[test]
+    fn test_crashed_thread_frames() {
+        let body = serde_json::json!({
+            "usedImages": [
+                {"base": 4096, "name": "MyApp", "path": "/apps/MyApp.app/MyApp"},
+                {"base": 65536, "name": "UIKitCore", "path": "/System/UIKitCore"},
+            ],
+            "threads": [
+                {"frames": [{"imageIndex": 1, "imageOffset": 1}]},
+                {"triggered": true, "frames": [
+                    {"imageIndex": 0, "imageOffset": 256},
+                    {"imageIndex": 1, "imageOffset": 16},
+                    {"imageIndex": 7, "imageOffset": 16},
+                ]},
+            ],
+        });
+        let frames = crashed_thread_frames(&body);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], CrashFrame {
+            image_name: "MyApp".to_string(),
+            image_path: "/apps/MyApp.app/MyApp".to_string(),
+            load_address: 4096,
+            address: 4352,
+        });
+        assert_eq!(frames[1].address, 65552);
+        assert!(crashed_thread_frames(&serde_json::json!({})).is_empty());
     }
 
-    println!("Launched: {}", bundle_id);
-    Ok(())
-}
+    #[test]
+    fn test_launch_time_measurement() {
+        let ms = std::time::Duration::from_millis;
+        let result = LaunchTimeMeasurement::from_samples(&[ms(900), ms(410), ms(650)]).unwrap();
+        assert_eq!(result, LaunchTimeMeasurement { min_ms: 410, max_ms: 900, mean_ms: 653, trials: 3 });
+        assert_eq!(result.to_json()["mean_ms"], 653);
+        assert!(LaunchTimeMeasurement::from_samples(&[]).is_none());
+
+        assert!(is_launch_signal("10:00:01.123 Df MyApp[42:99] [app] launch"));
+        assert!(is_launch_signal("Df MyApp[42:99] HomeViewController viewDidAppear"));
+        assert!(!is_launch_signal("Df SpringBoard[1:2] launching"));
+    }
 
-/// Stop an app
-pub fn stop_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_notifyutil_memory_args() {
+        assert_eq!(
+            notifyutil_memory_args(MemoryPressureLevel::Critical),
+            ["notifyutil", "-s", "com.apple.system.memorystatus", "4", "-p", "com.apple.system.memorystatus"]
+        );
+        assert_eq!(notifyutil_memory_args(MemoryPressureLevel::Warning)[3], "2");
+    }
 
-    let output = simctl_exec(&["terminate", &udid, bundle_id])?;
+    #[test]
+    fn test_parse_battery() {
+        let list = "Current Status Bar Overrides:\n=============================\nTime: 9:41\n\
+                    Battery State: 2, Battery Level: 15, Show Not Charging: 0\n";
+        assert_eq!(parse_status_bar_battery(list), Some((15, BatteryState::Discharging)));
+        assert_eq!(parse_status_bar_battery("Current Status Bar Overrides:\n"), None);
+
+        let pmset = "Now drawing from 'AC Power'\n -InternalBattery-0 (id=4653155)\t85%; charging; 1:20 remaining present: true\n";
+        assert_eq!(parse_pmset_battery(pmset), Some((85, BatteryState::Charging)));
+        let pmset = " -InternalBattery-0 (id=1)\t100%; charged; 0:00 remaining present: true";
+        assert_eq!(parse_pmset_battery(pmset), Some((100, BatteryState::Full)));
+        assert_eq!(parse_pmset_battery("Now drawing from 'AC Power'\n"), None);
+
+        assert!(set_battery(101, BatteryState::Full, None).is_err());
+    }
 
-    if !output.status.success() {
-        bail!("Failed to stop {}: {}", bundle_id, String::from_utf8_lossy(&output.stderr));
+    #[test]
+    fn test_biometric_notify_args() {
+        assert_eq!(
+            biometric_notify_args(Biometric::FaceId, Some("match")),
+            ["notifyutil", "-p", "com.apple.BiometricKit_Sim.pearl.match"]
+        );
+        assert_eq!(
+            biometric_notify_args(Biometric::TouchId, Some("nomatch")),
+            ["notifyutil", "-p", "com.apple.BiometricKit_Sim.fingerTouch.nomatch"]
+        );
+        assert_eq!(biometric_notify_args(Biometric::TouchId, None)[1..4], ["-s", "com.apple.BiometricKit.enrollmentChanged", "1"]);
+        assert_eq!(lookup_device_spec("iPhone-SE-3rd-generation").unwrap().biometric.name(), "Touch ID");
     }
 
-    println!("Stopped: {}", bundle_id);
-    Ok(())
-}
+    #[test]
+    fn test_rotation_steps() {
+        use Orientation::*;
+        assert_eq!(rotation_steps(Portrait, Portrait), 0);
+        assert_eq!(rotation_steps(Portrait, LandscapeRight), 1);
+        assert_eq!(rotation_steps(Portrait, LandscapeLeft), -1);
+        assert_eq!(rotation_steps(Portrait, PortraitUpsideDown), 2);
+        assert_eq!(rotation_steps(LandscapeLeft, Portrait), 1);
+        assert_eq!(rotation_steps(LandscapeLeft, LandscapeRight), 2);
+
+        assert_eq!(orientation_from_dimensions(1179, 2556), Portrait);
+        assert_eq!(orientation_from_dimensions(2556, 1179), LandscapeLeft);
+    }
 
-/// Install an app
-pub fn install_app(path: &str, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_resolve_orientation_from_landscape_right() {
+        use Orientation::*;
+        // A recorded LandscapeRight beats the dimension guess, so the turns come out right
+        let current = resolve_orientation(Some(LandscapeRight), 2556, 1179).unwrap();
+        assert_eq!(current, LandscapeRight);
+        assert_eq!(rotation_steps(current, Portrait), -1);
+        assert_eq!(rotation_steps(current, LandscapeLeft), 2);
+
+        assert_eq!(resolve_orientation(None, 2556, 1179), None);
+        assert_eq!(resolve_orientation(Some(Portrait), 2556, 1179), None);
+        assert_eq!(resolve_orientation(Some(LandscapeRight), 1179, 2556), Some(Portrait));
+        assert_eq!(resolve_orientation(Some(PortraitUpsideDown), 1179, 2556), Some(PortraitUpsideDown));
+    }
 
-    println!("Installing {}...", path);
+    #[test]
+    fn test_launch_command_line() {
+        assert_eq!(launch_command_line("booted", "com.example.app", &[], &[]), "xcrun simctl launch booted com.example.app");
+        assert_eq!(
+            launch_command_line("booted", "com.example.app", &["-FeatureX", "YES", "it's on"], &[("API_URL", "https://staging.example.com")]),
+            "SIMCTL_CHILD_API_URL=https://staging.example.com xcrun simctl launch booted com.example.app -FeatureX YES 'it'\\''s on'"
+        );
+        assert!(launch_app_with_args("com.example.app", &[], &[("A=B", "c")], None).is_err());
+    }
 
-    let output = simctl_exec(&["install", &udid, path])?;
+    #[test]
+    fn test_expand_media_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.jpg", "b.jpg", "c.png"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let jpgs = format!("{}/*.jpg", dir.path().display());
+        let gifs = format!("{}/*.gif", dir.path().display());
+
+        let expanded = expand_media_paths(&[&jpgs, "/missing/photo.heic", &gifs]);
+        assert_eq!(expanded.len(), 4);
+        assert!(expanded[0].as_ref().unwrap().ends_with("a.jpg"));
+        assert!(expanded[1].as_ref().unwrap().ends_with("b.jpg"));
+        assert_eq!(expanded[2], Ok("/missing/photo.heic".to_string()));
+        assert_eq!(expanded[3], Err((gifs.clone(), "no files match".to_string())));
+    }
 
-    if !output.status.success() {
-        bail!("Failed to install: {}", String::from_utf8_lossy(&output.stderr));
+    #[test]
+    fn test_parse_coresimulator_version() {
+        assert_eq!(parse_coresimulator_version("PROGRAM:simctl  PROJECT:CoreSimulator-857.14\n"), Some(857));
+        assert_eq!(parse_coresimulator_version("PROGRAM:simctl  PROJECT:CoreSimulator-681\n"), Some(681));
+        assert_eq!(parse_coresimulator_version("simctl 1.0"), None);
+
+        let err = IosError::FeatureNotSupported {
+            feature: "simctl keychain reset".to_string(),
+            requirement: "Xcode 11.4 or later".to_string(),
+            workaround: "erase the simulator".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "simctl keychain reset is not supported by this Xcode (requires Xcode 11.4 or later); workaround: erase the simulator"
+        );
     }
 
-    println!("Installed: {}", path);
-    Ok(())
-}
+    #[test]
+    fn test_element_at_prefers_smallest() {
+        let elements = vec![
+            element("AXWindow", "Main", 0, 0, 400, 800),
+            element("AXButton", "Login", 10, 20, 100, 40),
+            element("AXStaticText", "Hidden", 10, 20, 0, 0),
+        ];
+
+        assert_eq!(element_at(elements.clone(), 50, 30).unwrap().title, "Login");
+        assert_eq!(element_at(elements.clone(), 200, 400).unwrap().title, "Main");
+        // Right/bottom edges are exclusive
+        assert_eq!(element_at(elements.clone(), 110, 30).unwrap().title, "Main");
+        assert!(element_at(elements, 500, 30).is_none());
+    }
 
-/// Uninstall an app
-pub fn uninstall_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_shortcut_script() {
+        let (script, argv) = shortcut_script("Z", &[Modifier::Command, Modifier::Shift]).unwrap();
+        assert!(script.contains("keystroke (item 1 of argv) using {command down, shift down}"));
+        assert_eq!(argv, vec!["z"]);
 
-    println!("Uninstalling {}...", bundle_id);
+        let (script, argv) = shortcut_script("v", &[Modifier::Command]).unwrap();
+        assert!(script.ends_with("using {command down}\nend run"));
+        assert_eq!(argv, vec!["v"]);
 
-    let output = simctl_exec(&["uninstall", &udid, bundle_id])?;
+        let (script, argv) = shortcut_script("left", &[Modifier::Option]).unwrap();
+        assert_eq!(script, "tell application \"System Events\" to key code 123 using {option down}");
+        assert!(argv.is_empty());
 
-    if !output.status.success() {
-        bail!("Failed to uninstall: {}", String::from_utf8_lossy(&output.stderr));
+        let (script, _) = shortcut_script("return", &[]).unwrap();
+        assert!(script.ends_with("key code 36"));
+
+        assert!(shortcut_script("F13x", &[Modifier::Command]).is_err());
     }
 
-    println!("Uninstalled: {}", bundle_id);
-    Ok(())
-}
+    #[test]
+    fn test_refresh_swipe() {
+        assert_eq!(refresh_swipe((0, 0, 1000, 2000)), (500, 600, 500, 1400));
+        assert_eq!(refresh_swipe((100, 200, 400, 1000)), (300, 500, 300, 900));
+    }
 
-/// Find element by text via accessibility tree
-pub fn find_element(query: &str, _simulator: Option<&str>) -> Result<Option<(i32, i32)>> {
-    let elements = get_accessibility_elements()?;
-    let query_lower = query.to_lowercase();
+    #[test]
+    fn test_percent_to_pixels() {
+        assert_eq!(percent_to_pixels(0.5, 0.5, 1206, 2622).unwrap(), (603, 1311));
+        assert_eq!(percent_to_pixels(0.0, 1.0, 1206, 2622).unwrap(), (0, 2622));
+
+        let err = percent_to_pixels(50.0, 0.5, 1206, 2622).unwrap_err().to_string();
+        assert!(err.contains("x percentage 50"), "{}", err);
+        assert!(percent_to_pixels(0.5, -0.1, 1206, 2622).is_err());
+        assert!(percent_to_pixels(f64::NAN, 0.5, 1206, 2622).is_err());
+    }
 
-    for elem in &elements {
-        let matches = elem.title.to_lowercase().contains(&query_lower)
-            || elem.value.to_lowercase().contains(&query_lower)
-            || elem.description.to_lowercase().contains(&query_lower);
+    #[test]
+    fn test_parse_simulator_info() {
+        let json = serde_json::json!({"devices": {
+            "com.apple.CoreSimulator.SimRuntime.iOS-17-2": [{
+                "udid": "ABC-123",
+                "name": "iPhone 15 Pro",
+                "state": "Booted",
+                "isAvailable": true,
+                "deviceTypeIdentifier": "com.apple.CoreSimulator.SimDeviceType.iPhone-15-Pro",
+                "dataPath": "/Users/me/Library/Developer/CoreSimulator/Devices/ABC-123/data"
+            }]
+        }});
+
+        let (info, data_path) = parse_simulator_info(&json, "ABC-123").unwrap();
+        assert_eq!(info.runtime, "iOS-17-2");
+        assert_eq!(info.os_version, "17.2");
+        assert_eq!(info.device_type, "iPhone-15-Pro");
+        assert_eq!(info.state, "Booted");
+        assert!(data_path.unwrap().ends_with("ABC-123/data"));
+        assert!(parse_simulator_info(&json, "missing").is_none());
+
+        assert_eq!(os_version_from_runtime("watchOS-10-0"), "10.0");
+    }
 
-        if matches && elem.width > 0 && elem.height > 0 {
-            let cx = elem.x + elem.width / 2;
-            let cy = elem.y + elem.height / 2;
-            println!("Found: \"{}\" role={} at ({},{}) size={}x{}",
-                if !elem.title.is_empty() { &elem.title }
-                else if !elem.description.is_empty() { &elem.description }
-                else { &elem.value },
-                elem.role, elem.x, elem.y, elem.width, elem.height);
-            return Ok(Some((cx, cy)));
-        }
+    #[test]
+    fn test_parse_df_available_gb() {
+        let df = "Filesystem 1024-blocks Used Available Capacity iused ifree %iused Mounted on\n\
+                  /dev/disk3s5 971350180 512000000 20971520 96% 1 2 0% /System/Volumes/Data\n";
+        assert_eq!(parse_df_available_gb(df), Some(20.0));
+        assert_eq!(parse_df_available_gb(""), None);
     }
 
-    println!("Element '{}' not found", query);
-    Ok(None)
-}
+    #[test]
+    fn test_is_phone_capable() {
+        assert!(is_phone_capable("iPhone-15-Pro"));
+        assert!(is_phone_capable("iPhone-SE-3rd-generation"));
+        assert!(!is_phone_capable("iPad-Pro-11-inch-4th-generation"));
+        assert!(!is_phone_capable(""));
+    }
 
-/// Tap element by text
-pub fn tap_element(query: &str, simulator: Option<&str>) -> Result<()> {
-    if let Some((x, y)) = find_element(query, simulator)? {
-        // These are screen coordinates already (from AppleScript), tap directly
-        let script = format!(
-            r#"tell application "Simulator" to activate
-delay 0.2
-tell application "System Events"
-    click at {{{}, {}}}
-end tell"#,
-            x, y
-        );
-        let _ = Command::new("osascript").args(["-e", &script]).output();
-        println!("Tapped element at ({}, {})", x, y);
-    } else {
-        bail!("Element '{}' not found", query);
+    #[test]
+    fn test_menu_item_checked() {
+        assert!(menu_item_checked("✓\n"));
+        assert!(!menu_item_checked("missing value\n"));
+        assert!(!menu_item_checked(""));
     }
-    Ok(())
-}
 
-/// Clear device logs
-pub fn clear_logs(simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_top_edge_swipe() {
+        assert_eq!(top_edge_swipe(TopEdgePanel::NotificationCenter, false, 1000, 2000), (500, 10, 500, 1200));
+        assert_eq!(top_edge_swipe(TopEdgePanel::NotificationCenter, true, 1000, 2000), (500, 1200, 500, 10));
+        assert_eq!(top_edge_swipe(TopEdgePanel::ControlCenter, false, 1000, 2000), (900, 10, 900, 1200));
+        assert_eq!(top_edge_swipe(TopEdgePanel::ControlCenter, false, 100, 100).1, 1);
+    }
 
-    // Try predicate-based approach: show last 0 seconds effectively clears view
-    let output = simctl_exec(&["spawn", &udid, "log", "erase", "--all"]);
+    #[test]
+    fn test_is_png() {
+        assert!(is_png(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(!is_png(b"GIF89a"));
+        assert!(!is_png(b""));
+    }
 
-    if let Ok(out) = output {
-        if out.status.success() {
-            println!("Logs cleared");
-            return Ok(());
-        }
+    #[test]
+    fn test_recorder_save_load() {
+        let recorder = Recorder::start(None);
+        recorder.push(Action::Tap { x: 10, y: 20 });
+        recorder.clone().push(Action::InputText { text: "hi".to_string(), clear: false });
+        assert_eq!(recorder.stop().len(), 2);
+        recorder.push(Action::Wait { ms: 5 });
+        assert_eq!(recorder.actions().len(), 2, "nothing is recorded after stop");
+
+        let file = temp_file("recording_", ".json").unwrap();
+        recorder.save(file.path()).unwrap();
+        let json = std::fs::read_to_string(file.path()).unwrap();
+        assert!(json.contains("\"action\": \"tap\""));
+
+        let loaded = Recorder::load(file.path()).unwrap();
+        assert_eq!(loaded.actions(), recorder.actions());
     }
 
-    // Fallback: log erase requires root, inform user
-    println!("Note: log erase requires elevated privileges on iOS simulator");
-    println!("Workaround: reboot simulator to clear logs (claude-in-mobile reboot ios)");
-    Ok(())
-}
+    #[test]
+    fn test_edge_swipe() {
+        assert_eq!(edge_swipe(Edge::Left, 0.5, 1000, 2000).unwrap(), (3, 1000, 503, 1000));
+        assert_eq!(edge_swipe(Edge::Right, 0.5, 1000, 2000).unwrap(), (996, 1000, 496, 1000));
+        assert_eq!(edge_swipe(Edge::Bottom, 0.25, 1000, 2000).unwrap(), (500, 1996, 500, 1496));
+        assert_eq!(edge_swipe(Edge::Top, 1.0, 1000, 2000).unwrap(), (500, 3, 500, 1999));
+        assert!(edge_swipe(Edge::Left, 0.0, 1000, 2000).is_err());
+        assert!(edge_swipe(Edge::Left, 1.5, 1000, 2000).is_err());
+    }
 
-/// Get system info
-pub fn get_system_info(simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_draw_element_bounds() {
+        let mut img = image::RgbaImage::from_pixel(100, 100, image::Rgba([255, 255, 255, 255]));
+        let button = element("AXButton", "", 0, 0, 0, 0);
+        let field = element("AXTextField", "", 0, 0, 0, 0);
+        draw_element_bounds(&mut img, &[(&button, (10, 10, 30, 20)), (&field, (50, 50, 20, 20))]).unwrap();
+
+        assert_eq!(*img.get_pixel(10, 10), role_color("AXButton"));
+        assert_eq!(*img.get_pixel(39, 29), role_color("AXButton"));
+        assert_eq!(*img.get_pixel(25, 20), image::Rgba([255, 255, 255, 255]), "interior stays untouched");
+        assert_eq!(*img.get_pixel(50, 60), role_color("AXTextField"));
+        assert_eq!(role_color("AXStaticText"), image::Rgba([52, 199, 89, 255]));
+    }
 
-    let output = simctl_exec(&["list", "devices", "-j"])?;
-    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    #[test]
+    fn test_visible_labels() {
+        let elements = vec![
+            element("AXButton", "Login", 10, 20, 100, 40),
+            element("AXStaticText", "", 10, 80, 100, 20),
+            element("AXButton", "Offscreen", 0, 0, 0, 0),
+            element("AXStaticText", "Welcome", 10, 120, 100, 20),
+        ];
+        assert_eq!(visible_labels(&elements), "\"Login\", \"Welcome\"");
+        assert_eq!(visible_labels(&[]), "none");
+    }
 
-    if let Some(devices) = json["devices"].as_object() {
-        for (runtime, device_list) in devices {
-            if let Some(devices) = device_list.as_array() {
-                for device in devices {
-                    let device_udid = device["udid"].as_str().unwrap_or("");
-                    let is_booted = device["state"].as_str() == Some("Booted");
-
-                    if device_udid == udid || (udid == "booted" && is_booted) {
-                        println!("System Info:");
-                        println!("  Name: {}", device["name"].as_str().unwrap_or("unknown"));
-                        println!("  State: {}", device["state"].as_str().unwrap_or("unknown"));
-                        println!("  Runtime: {}", runtime.replace("com.apple.CoreSimulator.SimRuntime.", ""));
-                        println!("  UDID: {}", device_udid);
-                        return Ok(());
-                    }
-                }
-            }
-        }
+    #[test]
+    fn test_parse_running_apps() {
+        let stdout = "PID\tStatus\tLabel\n\
+            4242\t0\tUIKitApplication:com.example.app[5a1b][rb-legacy]\n\
+            -\t0\tUIKitApplication:com.apple.mobilesafari[11aa][rb-legacy]\n\
+            4300\t0\tUIKitApplication:com.apple.WidgetRenderer[22bb][rb-legacy]\n\
+            4301\t0\tUIKitApplication:com.apple.Preferences[33cc][rb-legacy]\n\
+            123\t0\tcom.apple.backboardd\n";
+        assert_eq!(parse_running_apps(stdout), vec!["com.example.app", "com.apple.Preferences"]);
+        assert!(parse_running_apps("").is_empty());
     }
 
-    println!("Device not found");
-    Ok(())
-}
+    #[test]
+    fn test_divider_drag() {
+        assert_eq!(divider_drag(MultitaskingLayout::OneThirdTwoThird, 2048, 2732), Some((1024, 1366, 682, 1366)));
+        assert_eq!(divider_drag(MultitaskingLayout::HalfHalf, 2048, 2732), None);
+        assert_eq!(divider_drag(MultitaskingLayout::FullScreen, 2048, 2732), None);
+    }
 
-/// Get current activity (foreground app) via launchctl
-pub fn get_current_activity(simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_parse_diagnose_archive() {
+        let output = "Collecting logs from /Users/me/Library/Logs/CoreSimulator\n\
+            Writing to /private/tmp/simctl_diagnose_2024_01_02/\n\
+            Successfully wrote '/private/tmp/simctl_diagnose_2024_01_02.tar.gz'.\n";
+        assert_eq!(
+            parse_diagnose_archive(output),
+            Some(PathBuf::from("/private/tmp/simctl_diagnose_2024_01_02.tar.gz"))
+        );
+        assert_eq!(
+            parse_diagnose_archive("Archive: /tmp/out/sim_diag.zip"),
+            Some(PathBuf::from("/tmp/out/sim_diag.zip"))
+        );
+        assert_eq!(parse_diagnose_archive("Done"), None);
+    }
 
-    let output = Command::new("xcrun")
-        .args(["simctl", "spawn", &udid, "launchctl", "list"])
-        .output()
-        .context("Failed to get running processes")?;
+    #[test]
+    fn test_pixel_in_png() {
+        let mut img = image::RgbaImage::from_pixel(4, 3, image::Rgba([0, 0, 0, 255]));
+        img.put_pixel(2, 1, image::Rgba([10, 200, 30, 255]));
+        let png = encode_png(&image::DynamicImage::ImageRgba8(img)).unwrap();
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let re = regex::Regex::new(r"UIKitApplication:([^\[]+)\[").unwrap();
+        assert_eq!(pixel_in_png(&png, 2, 1).unwrap(), (10, 200, 30, 255));
+        assert!(pixel_in_png(&png, 4, 0).is_err());
+        assert!(pixel_in_png(&png, 0, -1).is_err());
 
-    let mut apps: Vec<String> = Vec::new();
-    for line in stdout.lines() {
-        if let Some(cap) = re.captures(line) {
-            let bundle = cap[1].to_string();
-            // Skip system background services
-            if !bundle.contains("WidgetRenderer")
-                && !bundle.contains("ViewService")
-                && !bundle.contains("Spotlight") {
-                // Check if PID is running (first column is PID, "-" means not running)
-                let pid = line.split_whitespace().next().unwrap_or("-");
-                if pid != "-" {
-                    apps.push(bundle);
-                }
-            }
-        }
+        assert!(color_matches((10, 200, 30, 255), (12, 198, 30, 255), 2));
+        assert!(!color_matches((10, 200, 30, 255), (13, 200, 30, 255), 2));
     }
 
-    if apps.is_empty() {
-        println!("No foreground app detected (SpringBoard/Home Screen)");
-    } else {
-        println!("Foreground app: {}", apps[0]);
-        for app in apps.iter().skip(1) {
-            println!("Background app: {}", app);
-        }
+    #[test]
+    fn test_find_ocr_text() {
+        let hocr = r#"<div class='ocr_page'>
+  <span class='ocr_line' id='line_1_1' title="bbox 36 92 400 116">
+    <span class='ocrx_word' id='word_1_1' title='bbox 36 92 96 116; x_wconf 95'>Sign</span>
+    <span class='ocrx_word' id='word_1_2' title='bbox 104 92 140 116; x_wconf 93'><strong>In</strong></span>
+    <span class='ocrx_word' id='word_1_3' title='bbox 150 92 220 118; x_wconf 90'>&amp;</span>
+  </span>
+  <span class='ocr_line' id='line_1_2' title="bbox 36 150 120 170">
+    <span class='ocrx_word' id='word_1_4' title='bbox 36 150 120 170; x_wconf 91'>Continue.</span>
+  </span>
+</div>"#;
+        let words = parse_hocr_words(hocr);
+        assert_eq!(words.len(), 4);
+        assert_eq!(words[1].text, "In");
+        assert_eq!(words[2].text, "&");
+        assert_eq!(words[3].line, 2);
+
+        assert_eq!(find_ocr_text(&words, "sign in"), Some((36, 92, 104, 24)));
+        assert_eq!(find_ocr_text(&words, "Continue"), Some((36, 150, 84, 20)));
+        // Runs never span lines
+        assert_eq!(find_ocr_text(&words, "& continue"), None);
+        assert_eq!(find_ocr_text(&words, ""), None);
     }
 
-    Ok(())
-}
-
-/// Get device logs
-pub fn get_logs(filter: Option<&str>, lines: usize, simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    /// Deterministic grayscale noise, so every region has distinct texture
+    fn noise_image(width: u32, height: u32) -> image::GrayImage {
+        image::GrayImage::from_fn(width, height, |x, y| {
+            let h = x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503).rotate_left(13);
+            image::Luma([(h ^ (h >> 7)) as u8])
+        })
+    }
 
-    let predicate;
-    let mut args = vec!["spawn", &udid, "log", "show", "--last", "5m", "--style", "compact"];
+    #[test]
+    fn test_locate_template() {
+        let screen = noise_image(200, 160);
+        let tpl = image::imageops::crop_imm(&screen, 70, 45, 12, 10).to_image();
+        let (x, y, score) = locate_template(&screen, &tpl).unwrap();
+        assert_eq!((x, y), (70, 45));
+        assert!(score > 0.999, "{}", score);
+    }
 
-    if let Some(f) = filter {
-        predicate = format!("processImagePath CONTAINS '{}'", f);
-        args.push("--predicate");
-        args.push(&predicate);
+    #[test]
+    fn test_locate_template_coarse_to_fine() {
+        // Smooth gradient blobs survive downscaling, so the coarse pass finds them
+        let screen = image::GrayImage::from_fn(320, 240, |x, y| {
+            let d = ((x as f64 - 211.0).powi(2) + (y as f64 - 97.0).powi(2)).sqrt();
+            image::Luma([(255.0 - d.min(255.0)) as u8])
+        });
+        let tpl = image::imageops::crop_imm(&screen, 181, 67, 60, 60).to_image();
+        let (x, y, score) = locate_template(&screen, &tpl).unwrap();
+        assert_eq!((x, y), (181, 67));
+        assert!(score > 0.999, "{}", score);
     }
 
-    let output = simctl_exec(&args)?;
+    #[test]
+    fn test_parse_app_version_info() {
+        let stdout = r#"{
+    ApplicationType = User;
+    Bundle = "file:///Users/me/Library/Developer/CoreSimulator/Devices/ABC/data/Containers/Bundle/Application/1234/Example.app/";
+    CFBundleIdentifier = "com.example.app";
+    CFBundleShortVersionString = "2.4.1";
+    CFBundleVersion = 318;
+    Path = "/Users/me/Library/Developer/CoreSimulator/Devices/ABC/data/Containers/Bundle/Application/1234/Example.app";
+}"#;
+        let (info, path) = parse_app_version_info(stdout).unwrap();
+        assert_eq!(info.short_version, "2.4.1");
+        assert_eq!(info.bundle_version, "318");
+        assert_eq!(info.minimum_os_version, "");
+        assert!(path.unwrap().ends_with("/1234/Example.app"));
+    }
 
-    if !output.status.success() {
-        let fallback = simctl_exec(&["spawn", &udid, "log", "show", "--last", "1m"])?;
-        let stdout = String::from_utf8_lossy(&fallback.stdout);
-        for line in stdout.lines().take(lines) {
-            println!("{}", line);
-        }
-        return Ok(());
+    #[test]
+    fn test_scheme_probe_url() {
+        assert_eq!(scheme_probe_url("myapp").unwrap().as_str(), "myapp://");
+        assert_eq!(scheme_probe_url("com.example.app-dev").unwrap().scheme(), "com.example.app-dev");
+        assert!(scheme_probe_url("1app").is_err());
+        assert!(scheme_probe_url("my app").is_err());
+        assert!(scheme_probe_url("").is_err());
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    for line in stdout.lines().take(lines) {
-        println!("{}", line);
+    #[test]
+    fn test_parse_screen_profile() {
+        let mut dict = plist::Dictionary::new();
+        dict.insert("mainScreenWidth".to_string(), plist::Value::Integer(1179.into()));
+        dict.insert("mainScreenHeight".to_string(), plist::Value::Integer(2556.into()));
+        dict.insert("mainScreenScale".to_string(), plist::Value::Real(3.0));
+        let profile = parse_screen_profile(&plist::Value::Dictionary(dict)).unwrap();
+        assert_eq!(profile, ScreenProfile { width: 1179, height: 2556, scale: 3.0 });
+        assert!(parse_screen_profile(&plist::Value::Dictionary(plist::Dictionary::new())).is_none());
+
+        assert_eq!(oriented_to_window((1179, 2556), (0.0, 0.0, 400.0, 870.0)), (1179, 2556));
+        assert_eq!(oriented_to_window((1179, 2556), (0.0, 0.0, 870.0, 444.0)), (2556, 1179));
     }
-    Ok(())
-}
 
-/// Reboot simulator
-pub fn reboot(simulator: Option<&str>) -> Result<()> {
-    let udid = get_simulator_udid(simulator)?;
+    #[test]
+    fn test_parse_script() {
+        let json = r#"[
+            {"action": "tap", "x": 100, "y": 200},
+            {"action": "swipe", "x1": 0, "y1": 500, "x2": 0, "y2": 100},
+            {"action": "input_text", "text": "hello"},
+            {"action": "press_key", "key": "return"},
+            {"action": "open_url", "url": "myapp://home"},
+            {"action": "wait_ms", "ms": 500},
+            {"action": "launch_app", "bundle_id": "com.example.app"},
+            {"action": "screenshot", "path": "out.png"},
+            {"action": "assert_element", "query": "Welcome"}
+        ]"#;
+        let actions = parse_script(json, false).unwrap();
+        assert_eq!(actions.len(), 9);
+        assert_eq!(actions[1], Action::Swipe { x1: 0, y1: 500, x2: 0, y2: 100, duration_ms: 300 });
+        assert_eq!(actions[5], Action::Wait { ms: 500 });
+
+        let yaml = "- {action: tap, x: 1, y: 2}\n- {action: press_key, key: home}\n";
+        assert_eq!(parse_script(yaml, true).unwrap().len(), 2);
+
+        let err = format!("{:#}", parse_script(r#"[{"action": "teleport"}]"#, false).unwrap_err());
+        assert!(err.contains("teleport"), "{}", err);
+    }
 
-    println!("Rebooting simulator...");
+    #[test]
+    fn test_validate_env_names() {
+        assert!(validate_env_names(&[("API_URL", "https://example.com"), ("EMPTY", "")]).is_ok());
+        assert!(validate_env_names(&[("", "x")]).is_err());
+        assert!(validate_env_names(&[("A=B", "x")]).is_err());
+        assert!(boot_with_env(&[("A=B", "x")], Some("iPhone 15")).is_err());
+    }
 
-    let _ = simctl_exec(&["shutdown", &udid]);
-    std::thread::sleep(std::time::Duration::from_secs(1));
+    #[test]
+    fn test_with_aps_environment() {
+        let mut existing = plist::Dictionary::new();
+        existing.insert("application-identifier".to_string(), "TEAM.com.example.app".into());
+        existing.insert("aps-environment".to_string(), "development".into());
+
+        let updated = with_aps_environment(Some(plist::Value::Dictionary(existing)), ApnsEnvironment::Production);
+        assert_eq!(updated.get("aps-environment").and_then(|v| v.as_string()), Some("production"));
+        assert_eq!(updated.get("application-identifier").and_then(|v| v.as_string()), Some("TEAM.com.example.app"));
+
+        let fresh = with_aps_environment(None, ApnsEnvironment::Sandbox);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh.get("aps-environment").and_then(|v| v.as_string()), Some("development"));
+    }
 
-    let output = simctl_exec(&["boot", &udid])?;
+    #[test]
+    fn test_screen_changed() {
+        let png = |img: image::RgbaImage| encode_png(&image::DynamicImage::ImageRgba8(img)).unwrap();
+        let base = image::RgbaImage::from_pixel(100, 100, image::Rgba([255, 255, 255, 255]));
+        let mut button = base.clone();
+        for x in 10..30 {
+            for y in 10..30 {
+                button.put_pixel(x, y, image::Rgba([0, 122, 255, 255]));
+            }
+        }
+        let mut speck = base.clone();
+        speck.put_pixel(50, 50, image::Rgba([0, 0, 0, 255]));
 
-    if !output.status.success() {
-        bail!("Failed to reboot: {}", String::from_utf8_lossy(&output.stderr));
+        assert!(screen_changed(&png(base.clone()), &png(button)));
+        assert!(!screen_changed(&png(base.clone()), &png(speck)), "a single pixel is noise");
+        assert!(!screen_changed(&png(base.clone()), &png(base.clone())));
+        assert!(screen_changed(&png(base), &png(image::RgbaImage::new(100, 50))));
     }
 
-    println!("Reboot initiated");
-    Ok(())
-}
+    #[test]
+    fn test_snapshot_manifest_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/snapshots.json");
+        assert!(read_state_map::<String>(&path).unwrap().is_empty());
+
+        let mut manifest = std::collections::BTreeMap::new();
+        manifest.insert("logged-in".to_string(), "ABC-123".to_string());
+        write_state_map(&path, &manifest).unwrap();
+        assert_eq!(read_state_map(&path).unwrap(), manifest);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(read_state_map::<String>(&path).is_err());
+    }
 
-// ============== File Transfer ==============
+    #[test]
+    fn test_text_dump_contains() {
+        let dump = "Settings\nmissing value\nWi-Fi\nConnected to Home\n";
+        assert!(text_dump_contains(dump, "wi-fi"));
+        assert!(text_dump_contains(dump, "Home"));
+        assert!(!text_dump_contains(dump, "missing"));
+        assert!(!text_dump_contains(dump, "Bluetooth"));
+    }
 
-/// Push file to simulator (limited support)
-pub fn push_file(local: &str, remote: &str, simulator: Option<&str>) -> Result<()> {
-    let _udid = get_simulator_udid(simulator)?;
-    println!("Note: File push to iOS simulator is not directly supported via simctl.");
-    println!("Use 'xcrun simctl addmedia' for media files or app container paths.");
-    println!("  Local: {}", local);
-    println!("  Remote: {}", remote);
-    Ok(())
-}
+    #[test]
+    fn test_drag_cliclick_args() {
+        let args = drag_cliclick_args((0, 0), (100, 50), 500);
+        assert_eq!(&args[..3], &["m:0,0", "dd:0,0", "w:600"]);
+        assert_eq!(args.iter().filter(|a| a.starts_with("dm:")).count(), GESTURE_STEPS as usize);
+        assert!(args.contains(&"w:50".to_string()));
+        assert_eq!(args.last().unwrap(), "du:100,50");
+    }
 
-/// Pull file from simulator (limited support)
-pub fn pull_file(remote: &str, local: &str, simulator: Option<&str>) -> Result<()> {
-    let _udid = get_simulator_udid(simulator)?;
-    println!("Note: File pull from iOS simulator is not directly supported via simctl.");
-    println!("Use app container paths: xcrun simctl get_app_container <udid> <bundle_id>");
-    println!("  Remote: {}", remote);
-    println!("  Local: {}", local);
-    Ok(())
-}
+    #[test]
+    fn test_multi_tap_script() {
+        let script = multi_tap_script(10, 20, 3, 100);
+        assert_eq!(script.matches("click at {10, 20}").count(), 3);
+        assert_eq!(script.matches("delay 0.1").count(), 2);
+        assert!(!multi_tap_script(10, 20, 1, 100).contains("delay"));
+        assert!(multi_tap(0, 0, 0, 100, None).is_err());
+        assert!(multi_tap(0, 0, 6, 100, None).is_err());
+    }
 
-// ============== Clipboard ==============
+    #[test]
+    fn test_resolve_logs_path() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(resolve_logs_path(dir.path(), "ABC").is_err());
 
-/// Get clipboard content (host clipboard since simulator shares it)
-pub fn get_clipboard(_simulator: Option<&str>) -> Result<()> {
-    let output = Command::new("pbpaste")
-        .output()
-        .context("Failed to execute pbpaste")?;
-    let text = String::from_utf8_lossy(&output.stdout);
-    println!("{}", text);
-    Ok(())
-}
+        let device_dir = dir.path().join("ABC");
+        std::fs::create_dir(&device_dir).unwrap();
+        assert_eq!(resolve_logs_path(dir.path(), "ABC").unwrap(), device_dir);
 
-/// Set clipboard content (host clipboard since simulator shares it)
-pub fn set_clipboard(text: &str, _simulator: Option<&str>) -> Result<()> {
-    let mut child = Command::new("pbcopy")
-        .stdin(std::process::Stdio::piped())
-        .spawn()
-        .context("Failed to execute pbcopy")?;
-    if let Some(mut stdin) = child.stdin.take() {
-        use std::io::Write;
-        stdin.write_all(text.as_bytes())?;
+        std::fs::write(device_dir.join("system.log"), "").unwrap();
+        assert_eq!(resolve_logs_path(dir.path(), "ABC").unwrap(), device_dir.join("system.log"));
     }
-    child.wait()?;
-    println!("Clipboard set");
-    Ok(())
-}
-
-// ============== Tests ==============
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_get_simulator_udid_booted() {
-        let result = get_simulator_udid(None);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "booted");
+    fn test_format_app_list() {
+        let app = |bundle_id: &str, display_name: &str| AppInfo {
+            bundle_id: bundle_id.to_string(),
+            display_name: display_name.to_string(),
+            version: "1".to_string(),
+            short_version: "1.0".to_string(),
+            bundle_path: format!("/Apps/{}.app", bundle_id),
+            data_container_path: None,
+        };
+        let apps = [app("com.apple.mobilesafari", "Safari"), app("com.example.unnamed", "")];
+        assert_eq!(
+            format_app_list(&apps),
+            "Installed apps (2):\n  com.apple.mobilesafari (Safari)\n  com.example.unnamed\n"
+        );
+        assert_eq!(format_app_list(&[]), "Installed apps (0):\n");
     }
 }
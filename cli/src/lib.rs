@@ -1,10 +1,16 @@
 //! claude-in-mobile library - shared types and utilities
 
+#[cfg(feature = "android")]
 pub mod android;
 pub mod aurora;
 pub mod desktop;
+pub mod device;
+pub mod har;
 pub mod ios;
+#[cfg(feature = "android")]
 pub mod screenshot;
 pub mod platform;
+pub mod tvos;
+pub mod watchos;
 
 pub use platform::Platform;
@@ -2,17 +2,13 @@
 //!
 //! Supports Android (via ADB), iOS (via simctl), Aurora (via audb), Desktop (via companion app)
 
-mod android;
-mod aurora;
-mod desktop;
-mod ios;
-mod screenshot;
-
 use std::process::ExitCode;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+use claude_in_mobile::{android, aurora, desktop, ios, screenshot};
+
 #[derive(Parser)]
 #[command(name = "claude-in-mobile")]
 #[command(about = "Fast CLI for mobile device automation (Android/iOS/Aurora/Desktop)")]
@@ -194,6 +190,28 @@ enum Commands {
         ms: u64,
     },
 
+    /// Wait for a UI element to appear or disappear (iOS only)
+    WaitFor {
+        /// Text to search for (title, value or description)
+        query: String,
+
+        /// Wait for the element to disappear instead
+        #[arg(long)]
+        gone: bool,
+
+        /// Give up after this many milliseconds
+        #[arg(long, default_value = "10000")]
+        timeout: u64,
+
+        /// Milliseconds between checks
+        #[arg(long, default_value = "500")]
+        poll: u64,
+
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
     /// Swipe gesture
     Swipe {
         /// Platform: android, ios, or aurora
@@ -238,6 +256,10 @@ enum Commands {
         /// Text to input
         text: String,
 
+        /// Clear the field before typing (iOS)
+        #[arg(long)]
+        clear: bool,
+
         /// iOS Simulator name
         #[arg(long)]
         simulator: Option<String>,
@@ -279,7 +301,7 @@ enum Commands {
         #[arg(value_parser = ["android", "ios", "desktop"])]
         platform: String,
 
-        /// Output format: json or xml
+        /// Output format: json, xml, or tree (iOS: indented hierarchy)
         #[arg(short, long, default_value = "json")]
         format: String,
 
@@ -388,6 +410,20 @@ enum Commands {
         device: Option<String>,
     },
 
+    /// Uninstall every app matching a filter (iOS only)
+    UninstallMatching {
+        /// Case-insensitive substring of the bundle ID or display name
+        filter: String,
+
+        /// Skip the confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
     /// Install an app
     Install {
         /// Platform: android, ios, or aurora
@@ -456,7 +492,7 @@ enum Commands {
         #[arg(short, long, default_value = "100")]
         lines: usize,
 
-        /// Log level filter (Android: V/D/I/W/E/F)
+        /// Log level filter (Android: V/D/I/W/E/F, iOS: default/info/debug)
         #[arg(long)]
         level: Option<String>,
 
@@ -468,6 +504,10 @@ enum Commands {
         #[arg(long)]
         package: Option<String>,
 
+        /// Filter by os_log subsystem, e.g. com.apple.network (iOS)
+        #[arg(long)]
+        subsystem: Option<String>,
+
         /// iOS Simulator name
         #[arg(long)]
         simulator: Option<String>,
@@ -507,7 +547,7 @@ enum Commands {
         device: Option<String>,
     },
 
-    /// Get current activity/foreground app
+    /// Get current activity (Android) or running apps (iOS)
     CurrentActivity {
         /// Platform: android or ios
         #[arg(value_parser = ["android", "ios"])]
@@ -537,6 +577,71 @@ enum Commands {
         device: Option<String>,
     },
 
+    /// Benchmark cold or warm app launch time (iOS only)
+    LaunchTime {
+        /// App bundle ID
+        bundle_id: String,
+
+        /// Measure warm launches (app already running) instead of cold ones
+        #[arg(long)]
+        warm: bool,
+
+        /// Number of launches to measure
+        #[arg(long, default_value = "5")]
+        trials: u32,
+
+        /// Print the measurement as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
+    /// Print hardware capabilities (Face ID, Dynamic Island, ...) as JSON (iOS only)
+    Capabilities {
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
+    /// Interactive session executing YAML steps one line at a time (iOS only)
+    Repl {
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
+    /// Run a JSON (or REPL-saved YAML) automation script step by step (iOS only)
+    RunScript {
+        /// Path to the script
+        path: String,
+
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
+    /// Factory-reset an iOS simulator (iOS only, requires --confirm)
+    EraseSimulator {
+        /// Confirm that all simulator content and settings will be wiped
+        #[arg(long)]
+        confirm: bool,
+
+        /// Why the simulator is being erased (logged before erasing)
+        #[arg(long)]
+        reason: String,
+
+        /// Shut the simulator down first if it is running
+        #[arg(long)]
+        force: bool,
+
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
     /// Control screen power (Android only)
     Screen {
         /// Turn screen on or off
@@ -588,33 +693,55 @@ enum Commands {
 
     /// Push file to device
     PushFile {
-        /// Platform: android or aurora
-        #[arg(value_parser = ["android", "aurora"])]
+        /// Platform: android, ios, or aurora
+        #[arg(value_parser = ["android", "ios", "aurora"])]
         platform: String,
 
         /// Local file path
         local: String,
 
-        /// Remote file path on device
+        /// Remote file path on device (iOS: relative to the data container)
         remote: String,
 
+        /// iOS app bundle ID whose data container to push into
+        #[arg(long)]
+        bundle_id: Option<String>,
+
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+
         /// Device serial
         #[arg(long)]
         device: Option<String>,
     },
 
+    /// Add photos or videos to the simulator's Photos library (iOS only)
+    PushMedia {
+        /// Local image or video path
+        local: String,
+
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+    },
+
     /// Pull file from device
     PullFile {
-        /// Platform: android or aurora
-        #[arg(value_parser = ["android", "aurora"])]
+        /// Platform: android, ios, or aurora
+        #[arg(value_parser = ["android", "ios", "aurora"])]
         platform: String,
 
-        /// Remote file path on device
+        /// Remote file path on device (iOS: `@<bundle_id>[/app|/groups]:<path>` for app containers)
         remote: String,
 
         /// Local file path
         local: String,
 
+        /// iOS Simulator name
+        #[arg(long)]
+        simulator: Option<String>,
+
         /// Device serial
         #[arg(long)]
         device: Option<String>,
@@ -823,7 +950,11 @@ fn run(cli: Cli) -> Result<()> {
             }
             match platform.as_str() {
                 "android" => android::tap(x, y, device.as_deref()),
-                "ios" => ios::tap(x, y, simulator.as_deref()),
+                "ios" => {
+                    ios::tap(x, y, simulator.as_deref())?;
+                    println!("Tapped at ({}, {})", x, y);
+                    Ok(())
+                }
                 "aurora" => aurora::tap(x, y, device.as_deref()),
                 "desktop" => desktop::tap(x, y, companion_path.as_deref()),
                 _ => unreachable!(),
@@ -856,7 +987,11 @@ fn run(cli: Cli) -> Result<()> {
             }
             match platform.as_str() {
                 "android" => android::swipe(x1, y1, x2, y2, duration, device.as_deref()),
-                "ios" => ios::swipe(x1, y1, x2, y2, duration, simulator.as_deref()),
+                "ios" => {
+                    ios::swipe(x1, y1, x2, y2, duration, simulator.as_deref())?;
+                    println!("Swiped {} from ({}, {}) to ({}, {})", ios::classify_swipe(x1, y1, x2, y2), x1, y1, x2, y2);
+                    Ok(())
+                }
                 "aurora" => aurora::swipe(x1, y1, x2, y2, duration, device.as_deref()),
                 _ => unreachable!(),
             }
@@ -865,13 +1000,18 @@ fn run(cli: Cli) -> Result<()> {
         Commands::Input {
             platform,
             text,
+            clear,
             simulator,
             device,
             companion_path,
         } => {
             match platform.as_str() {
                 "android" => android::input_text(&text, device.as_deref()),
-                "ios" => ios::input_text(&text, simulator.as_deref()),
+                "ios" => {
+                    ios::input_text(&text, clear, simulator.as_deref())?;
+                    println!("Input text: {}", text);
+                    Ok(())
+                }
                 "aurora" => aurora::input_text(&text, device.as_deref()),
                 "desktop" => desktop::input_text(&text, companion_path.as_deref()),
                 _ => unreachable!(),
@@ -887,7 +1027,11 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::press_key(&key, device.as_deref()),
-                "ios" => ios::press_key(&key, simulator.as_deref()),
+                "ios" => {
+                    ios::press_key(&key, simulator.as_deref())?;
+                    println!("Pressed key: {}", key);
+                    Ok(())
+                }
                 "aurora" => aurora::press_key(&key, device.as_deref()),
                 "desktop" => desktop::press_key(&key, companion_path.as_deref()),
                 _ => unreachable!(),
@@ -904,20 +1048,31 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::ui_dump(&format, device.as_deref()),
-                "ios" => ios::ui_dump(&format, simulator.as_deref()),
+                "ios" => {
+                    match ios::ui_dump(&format, simulator.as_deref())? {
+                        Some(dump) => print!("{}", dump),
+                        None => println!("No UI elements found. Ensure Simulator is in foreground."),
+                    }
+                    Ok(())
+                }
                 "desktop" => desktop::get_ui(companion_path.as_deref()),
                 _ => unreachable!(),
             }
         }
 
         Commands::Devices { platform } => {
+            let print_ios_devices = || -> Result<()> {
+                println!("iOS Simulators:");
+                println!("{}", serde_json::to_string_pretty(&ios::list_devices()?)?);
+                Ok(())
+            };
             match platform.as_str() {
                 "android" => android::print_devices(),
-                "ios" => ios::print_devices(),
+                "ios" => print_ios_devices(),
                 "aurora" => aurora::print_devices(),
                 _ => {
                     android::print_devices()?;
-                    ios::print_devices()?;
+                    print_ios_devices()?;
                     aurora::print_devices()
                 }
             }
@@ -931,7 +1086,7 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::list_apps(filter.as_deref(), device.as_deref()),
-                "ios" => ios::list_apps(filter.as_deref(), simulator.as_deref()),
+                "ios" => ios::print_apps(filter.as_deref(), simulator.as_deref()),
                 "aurora" => aurora::list_apps(filter.as_deref(), device.as_deref()),
                 _ => unreachable!(),
             }
@@ -946,7 +1101,11 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::launch_app(&package, device.as_deref()),
-                "ios" => ios::launch_app(&package, simulator.as_deref()),
+                "ios" => {
+                    ios::launch_app(&package, simulator.as_deref())?;
+                    println!("Launched: {}", package);
+                    Ok(())
+                }
                 "aurora" => aurora::launch_app(&package, device.as_deref()),
                 "desktop" => desktop::launch_app(&package, companion_path.as_deref()),
                 _ => unreachable!(),
@@ -962,7 +1121,11 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::stop_app(&package, device.as_deref()),
-                "ios" => ios::stop_app(&package, simulator.as_deref()),
+                "ios" => {
+                    ios::stop_app(&package, simulator.as_deref())?;
+                    println!("Stopped: {}", package);
+                    Ok(())
+                }
                 "aurora" => aurora::stop_app(&package, device.as_deref()),
                 "desktop" => desktop::stop_app(&package, companion_path.as_deref()),
                 _ => unreachable!(),
@@ -977,7 +1140,12 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::install_app(&path, device.as_deref()),
-                "ios" => ios::install_app(&path, simulator.as_deref()),
+                "ios" => {
+                    println!("Installing {}...", path);
+                    ios::install_app(&path, simulator.as_deref())?;
+                    println!("Installed: {}", path);
+                    Ok(())
+                }
                 "aurora" => aurora::install_app(&path, device.as_deref()),
                 _ => unreachable!(),
             }
@@ -992,7 +1160,10 @@ fn run(cli: Cli) -> Result<()> {
             if platform == "android" {
                 android::find_element(&query, device.as_deref())?;
             } else {
-                ios::find_element(&query, simulator.as_deref())?;
+                match ios::find_element(&query, simulator.as_deref())? {
+                    Some((x, y)) => println!("Found '{}' at ({}, {})", query, x, y),
+                    None => println!("Element '{}' not found", query),
+                }
             }
             Ok(())
         }
@@ -1006,7 +1177,9 @@ fn run(cli: Cli) -> Result<()> {
             if platform == "android" {
                 android::tap_element(&query, device.as_deref())
             } else {
-                ios::tap_element(&query, simulator.as_deref())
+                ios::tap_element(&query, simulator.as_deref())?;
+                println!("Tapped element '{}'", query);
+                Ok(())
             }
         }
 
@@ -1014,15 +1187,19 @@ fn run(cli: Cli) -> Result<()> {
             platform,
             filter,
             lines,
-            level: _,
+            level,
             tag: _,
             package: _,
+            subsystem,
             simulator,
             device,
         } => {
             match platform.as_str() {
                 "android" => android::get_logs(filter.as_deref(), lines, device.as_deref()),
-                "ios" => ios::get_logs(filter.as_deref(), lines, simulator.as_deref()),
+                "ios" => {
+                    let level = level.as_deref().map(str::parse).transpose()?;
+                    ios::print_logs(filter.as_deref(), lines, level, subsystem.as_deref(), simulator.as_deref())
+                }
                 "aurora" => aurora::get_logs(filter.as_deref(), lines, device.as_deref()),
                 _ => unreachable!(),
             }
@@ -1046,7 +1223,11 @@ fn run(cli: Cli) -> Result<()> {
             }
             match platform.as_str() {
                 "android" => android::long_press(x, y, duration, device.as_deref()),
-                "ios" => ios::long_press(x, y, duration, simulator.as_deref()),
+                "ios" => {
+                    ios::long_press(x, y, duration, simulator.as_deref())?;
+                    println!("Long pressed at ({}, {}) for {}ms", x, y, duration);
+                    Ok(())
+                }
                 "aurora" => aurora::long_press(x, y, duration, device.as_deref()),
                 _ => unreachable!(),
             }
@@ -1060,7 +1241,11 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::open_url(&url, device.as_deref()),
-                "ios" => ios::open_url(&url, simulator.as_deref()),
+                "ios" => {
+                    ios::open_url(&url, simulator.as_deref())?;
+                    println!("Opened URL: {}", url);
+                    Ok(())
+                }
                 "aurora" => aurora::open_url(&url, device.as_deref()),
                 _ => unreachable!(),
             }
@@ -1074,7 +1259,7 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => { android::shell(&command, device.as_deref())?; }
-                "ios" => { ios::shell(&command, simulator.as_deref())?; }
+                "ios" => print!("{}", ios::shell(&command, simulator.as_deref())?),
                 "aurora" => { aurora::shell(&command, device.as_deref())?; }
                 _ => unreachable!(),
             }
@@ -1087,6 +1272,18 @@ fn run(cli: Cli) -> Result<()> {
             Ok(())
         }
 
+        Commands::WaitFor { query, gone, timeout, poll, simulator } => {
+            let start = std::time::Instant::now();
+            if gone {
+                ios::wait_for_element_gone(&query, timeout, poll, simulator.as_deref())?;
+                println!("Element '{}' gone after {}ms", query, start.elapsed().as_millis());
+            } else {
+                let (x, y) = ios::wait_for_element(&query, timeout, poll, simulator.as_deref())?;
+                println!("Element '{}' appeared at ({}, {}) after {}ms", query, x, y, start.elapsed().as_millis());
+            }
+            Ok(())
+        }
+
         Commands::ClearLogs {
             platform,
             simulator,
@@ -1094,7 +1291,15 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::clear_logs(device.as_deref()),
-                "ios" => ios::clear_logs(simulator.as_deref()),
+                "ios" => {
+                    if ios::clear_logs(simulator.as_deref())? {
+                        println!("Logs cleared");
+                    } else {
+                        println!("Note: log erase requires elevated privileges on iOS simulator");
+                        println!("Workaround: reboot simulator to clear logs (claude-in-mobile reboot ios)");
+                    }
+                    Ok(())
+                }
                 "aurora" => aurora::clear_logs(device.as_deref()),
                 _ => unreachable!(),
             }
@@ -1107,7 +1312,7 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::get_system_info(device.as_deref()),
-                "ios" => ios::get_system_info(simulator.as_deref()),
+                "ios" => ios::print_system_info(simulator.as_deref()),
                 "aurora" => aurora::get_system_info(device.as_deref()),
                 _ => unreachable!(),
             }
@@ -1121,7 +1326,7 @@ fn run(cli: Cli) -> Result<()> {
             if platform == "android" {
                 android::get_current_activity(device.as_deref())
             } else {
-                ios::get_current_activity(simulator.as_deref())
+                ios::print_current_activity(simulator.as_deref())
             }
         }
 
@@ -1133,7 +1338,12 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::uninstall_app(&package, device.as_deref()),
-                "ios" => ios::uninstall_app(&package, simulator.as_deref()),
+                "ios" => {
+                    println!("Uninstalling {}...", package);
+                    ios::uninstall_app(&package, simulator.as_deref())?;
+                    println!("Uninstalled: {}", package);
+                    Ok(())
+                }
                 "aurora" => aurora::uninstall_app(&package, device.as_deref()),
                 _ => unreachable!(),
             }
@@ -1147,8 +1357,68 @@ fn run(cli: Cli) -> Result<()> {
             if platform == "android" {
                 android::reboot(device.as_deref())
             } else {
-                ios::reboot(simulator.as_deref())
+                println!("Rebooting simulator...");
+                ios::reboot(simulator.as_deref())?;
+                println!("Reboot initiated");
+                Ok(())
+            }
+        }
+
+        Commands::UninstallMatching { filter, yes, simulator } => {
+            let mut confirmed = None;
+            let uninstalled = ios::uninstall_matching_apps(&filter, |apps| {
+                println!("Apps matching \"{}\" ({}):", filter, apps.len());
+                for bundle in apps {
+                    println!("  {}", bundle);
+                }
+                let answer = yes || confirm_on_stdin(&format!("Uninstall {} app(s)?", apps.len()))?;
+                confirmed = Some(answer);
+                Ok(answer)
+            }, simulator.as_deref())?;
+            match confirmed {
+                None => println!("No installed apps match \"{}\"", filter),
+                Some(false) => println!("Aborted"),
+                Some(true) => println!("Uninstalled {} app(s)", uninstalled.len()),
+            }
+            Ok(())
+        }
+
+        Commands::LaunchTime { bundle_id, warm, trials, json, simulator } => {
+            let result = ios::measure_launch_time(&bundle_id, warm, trials, simulator.as_deref())?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&result.to_json())?);
+            } else {
+                println!(
+                    "{} launch of {}: min {}ms, mean {}ms, max {}ms",
+                    if warm { "Warm" } else { "Cold" }, bundle_id, result.min_ms, result.mean_ms, result.max_ms
+                );
             }
+            Ok(())
+        }
+
+        Commands::Capabilities { simulator } => {
+            let report = ios::device_capability_report(simulator.as_deref())?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+
+        Commands::Repl { simulator } => {
+            ios::repl(simulator.as_deref(), std::io::stdin().lock(), &mut std::io::stdout())
+        }
+
+        Commands::RunScript { path, simulator } => {
+            let steps = ios::run_script(&path, simulator.as_deref())?;
+            println!("Script completed: {} step(s)", steps);
+            Ok(())
+        }
+
+        Commands::EraseSimulator { confirm, reason, force, simulator } => {
+            if !confirm {
+                anyhow::bail!("Erasing wipes all simulator content and settings; re-run with --confirm");
+            }
+            ios::erase_simulator_confirmed(simulator.as_deref(), &reason, force)?;
+            println!("Simulator erased");
+            Ok(())
         }
 
         Commands::Screen { state, device } => {
@@ -1192,23 +1462,42 @@ fn run(cli: Cli) -> Result<()> {
             platform,
             local,
             remote,
+            bundle_id,
+            simulator,
             device,
         } => {
             match platform.as_str() {
                 "android" => android::push_file(&local, &remote, device.as_deref()),
+                "ios" => {
+                    let bytes = ios::push_file(&local, &remote, bundle_id.as_deref(), simulator.as_deref())?;
+                    println!("Pushed {} -> {} ({} bytes)", local, remote, bytes);
+                    Ok(())
+                }
                 "aurora" => aurora::push_file(&local, &remote, device.as_deref()),
                 _ => unreachable!(),
             }
         }
 
+        Commands::PushMedia { local, simulator } => {
+            ios::push_media(&local, simulator.as_deref())?;
+            println!("Added media: {}", local);
+            Ok(())
+        }
+
         Commands::PullFile {
             platform,
             remote,
             local,
+            simulator,
             device,
         } => {
             match platform.as_str() {
                 "android" => android::pull_file(&remote, &local, device.as_deref()),
+                "ios" => {
+                    let bytes = ios::pull_file(&remote, &local, simulator.as_deref())?;
+                    println!("Pulled {} -> {} ({} bytes)", remote, local, bytes);
+                    Ok(())
+                }
                 "aurora" => aurora::pull_file(&remote, &local, device.as_deref()),
                 _ => unreachable!(),
             }
@@ -1222,7 +1511,7 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::get_clipboard(device.as_deref()),
-                "ios" => ios::get_clipboard(None),
+                "ios" => ios::print_clipboard(None),
                 "desktop" => desktop::get_clipboard(companion_path.as_deref()),
                 _ => unreachable!(),
             }
@@ -1237,7 +1526,11 @@ fn run(cli: Cli) -> Result<()> {
         } => {
             match platform.as_str() {
                 "android" => android::set_clipboard(&text, device.as_deref()),
-                "ios" => ios::set_clipboard(&text, None),
+                "ios" => {
+                    ios::set_clipboard(&text, None)?;
+                    println!("Clipboard set");
+                    Ok(())
+                }
                 "desktop" => desktop::set_clipboard(&text, companion_path.as_deref()),
                 _ => unreachable!(),
             }
@@ -1272,3 +1565,15 @@ fn run(cli: Cli) -> Result<()> {
         }
     }
 }
+
+/// Ask a yes/no question on stdin; anything but "y"/"yes" declines
+fn confirm_on_stdin(prompt: &str) -> Result<bool> {
+    use std::io::Write;
+
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(ios::is_affirmative(&answer))
+}
@@ -85,6 +85,18 @@ pub fn take_annotated_screenshot(
     device: Option<&str>,
     simulator: Option<&str>,
 ) -> Result<()> {
+    if platform == "ios" {
+        let output_data = ios::annotated_screenshot(simulator)?;
+        if let Some(path) = output {
+            std::fs::write(path, &output_data)?;
+            eprintln!("Annotated screenshot saved to: {} ({} bytes)", path, output_data.len());
+        } else {
+            println!("{}", BASE64.encode(&output_data));
+            eprintln!("Annotated screenshot: {} bytes", output_data.len());
+        }
+        return Ok(());
+    }
+
     // Get screenshot
     let png_data = if platform == "android" {
         android::screenshot(device)?
@@ -172,7 +184,7 @@ pub fn analyze_screenshot(data: &[u8]) -> Result<ScreenshotInfo> {
     let brightness = calculate_brightness(&img);
 
     // Detect if mostly text (high contrast)
-    let is_text_heavy = brightness > 200.0 || brightness < 50.0;
+    let is_text_heavy = !(50.0..=200.0).contains(&brightness);
 
     Ok(ScreenshotInfo {
         width,
@@ -0,0 +1,66 @@
+//! tvOS Simulator automation via simctl
+//!
+//! tvOS simulators share the simctl plumbing with iOS, so app, screenshot and
+//! log functions are re-exported from `ios`. Input differs: there is no touch
+//! screen, only the Siri Remote, so coordinate-based gestures are left out.
+
+use anyhow::{Result, bail};
+
+use crate::ios::{self, Modifier};
+
+pub use crate::ios::{get_logs, install_app, launch_app, push_notification, screenshot, stop_app};
+
+/// Siri Remote button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TvRemoteButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    /// TV/Home button: returns to the Home Screen
+    Menu,
+    PlayPause,
+    Back,
+}
+
+/// Simulator keyboard shortcut for a remote button
+///
+/// simctl has no key-injection subcommand, so buttons go through the keys the
+/// Simulator app maps onto the remote: arrows, Return, Space, Escape and
+/// Cmd+Shift+H for Home.
+fn remote_key(button: TvRemoteButton) -> (&'static str, &'static [Modifier]) {
+    match button {
+        TvRemoteButton::Up => ("up", &[]),
+        TvRemoteButton::Down => ("down", &[]),
+        TvRemoteButton::Left => ("left", &[]),
+        TvRemoteButton::Right => ("right", &[]),
+        TvRemoteButton::Select => ("return", &[]),
+        TvRemoteButton::Menu => ("h", &[Modifier::Command, Modifier::Shift]),
+        TvRemoteButton::PlayPause => ("space", &[]),
+        TvRemoteButton::Back => ("escape", &[]),
+    }
+}
+
+/// Press a Siri Remote button on a tvOS simulator
+pub fn remote_press(button: TvRemoteButton, simulator: Option<&str>) -> Result<()> {
+    let info = ios::get_system_info(simulator)?;
+    if !info.runtime.starts_with("tvOS") {
+        bail!("'{}' is not a tvOS simulator (runtime {})", info.name, info.runtime);
+    }
+
+    let (key, modifiers) = remote_key(button);
+    ios::keyboard_shortcut(key, modifiers, simulator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_key() {
+        assert_eq!(remote_key(TvRemoteButton::Select), ("return", &[][..]));
+        assert_eq!(remote_key(TvRemoteButton::Back), ("escape", &[][..]));
+        assert_eq!(remote_key(TvRemoteButton::Menu).1, &[Modifier::Command, Modifier::Shift]);
+    }
+}
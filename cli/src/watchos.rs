@@ -0,0 +1,130 @@
+//! watchOS Simulator automation via simctl
+//!
+//! Watch simulators run alongside a paired iPhone simulator, so "booted" is
+//! ambiguous. When no simulator is given, these functions target the first
+//! booted watchOS simulator instead of whichever device simctl picks.
+
+use std::process::Command;
+use anyhow::{Result, Context, bail};
+
+use crate::ios::{self, Simulator};
+
+/// Lines scrolled per Digital Crown step
+const CROWN_SCROLL_LINES: i32 = 3;
+
+/// Direction to turn the Digital Crown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrownDirection {
+    Up,
+    Down,
+}
+
+fn is_watch_runtime(runtime: &str) -> bool {
+    runtime.starts_with("watchOS")
+}
+
+/// Available watchOS simulators (runtime `com.apple.CoreSimulator.SimRuntime.watchOS-*`), booted first
+pub fn list_watch_simulators() -> Result<Vec<Simulator>> {
+    Ok(ios::list_devices()?
+        .into_iter()
+        .filter(|sim| is_watch_runtime(&sim.runtime))
+        .collect())
+}
+
+/// The simulator to drive: `simulator` when given, otherwise the UDID of the first
+/// booted watch (names repeat across runtimes, so they cannot identify it)
+fn resolve_watch(simulator: Option<&str>) -> Result<String> {
+    if let Some(name) = simulator {
+        return Ok(name.to_string());
+    }
+    list_watch_simulators()?
+        .into_iter()
+        .find(|sim| sim.state == "Booted")
+        .map(|sim| sim.udid)
+        .ok_or_else(|| anyhow::anyhow!("No booted watchOS simulator found; boot one or pass its name"))
+}
+
+/// Take screenshot and return PNG bytes
+pub fn screenshot(simulator: Option<&str>) -> Result<Vec<u8>> {
+    ios::screenshot(Some(&resolve_watch(simulator)?))
+}
+
+/// Launch an app on the watch
+pub fn launch_app(bundle_id: &str, simulator: Option<&str>) -> Result<()> {
+    ios::launch_app(bundle_id, Some(&resolve_watch(simulator)?))
+}
+
+/// Tap at coordinates in watch screen pixels
+pub fn tap(x: i32, y: i32, simulator: Option<&str>) -> Result<()> {
+    ios::tap(x, y, Some(&resolve_watch(simulator)?))
+}
+
+/// Swipe between points in watch screen pixels
+pub fn swipe(x1: i32, y1: i32, x2: i32, y2: i32, duration: u32, simulator: Option<&str>) -> Result<()> {
+    ios::swipe(x1, y1, x2, y2, duration, Some(&resolve_watch(simulator)?))
+}
+
+/// JXA posting a scroll wheel event of `lines` (positive scrolls up)
+fn crown_scroll_script(lines: i32) -> String {
+    format!(
+        "ObjC.import('CoreGraphics');\n\
+         ObjC.bindFunction('CGEventCreateScrollWheelEvent2', ['void*', ['void*', 'int', 'unsigned int', 'int', 'int', 'int']]);\n\
+         $.CGEventPost($.kCGHIDEventTap, $.CGEventCreateScrollWheelEvent2(null, $.kCGScrollEventUnitLine, 1, {}, 0, 0));",
+        lines
+    )
+}
+
+/// Turn the Digital Crown one step
+///
+/// simctl cannot send crown events; the Simulator maps scroll wheel input over
+/// the watch screen to crown rotation, so this moves the pointer there
+/// (requires cliclick) and posts a scroll event.
+pub fn press_digital_crown(direction: CrownDirection, simulator: Option<&str>) -> Result<()> {
+    let name = resolve_watch(simulator)?;
+    if !ios::has_cliclick() {
+        bail!("Digital Crown input requires cliclick to position the pointer (brew install cliclick)");
+    }
+
+    let (width, height) = ios::screen_dimensions(Some(&name))?;
+    let (sx, sy) = ios::sim_to_screen_coords(width as i32 / 2, height as i32 / 2, Some(&name))?;
+    ios::ensure_simulator_focused(Some(&name))?;
+    Command::new("cliclick")
+        .arg(format!("m:{},{}", sx, sy))
+        .output()
+        .context("Failed to move pointer with cliclick")?;
+
+    let lines = match direction {
+        CrownDirection::Up => CROWN_SCROLL_LINES,
+        CrownDirection::Down => -CROWN_SCROLL_LINES,
+    };
+    let output = Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", &crown_scroll_script(lines)])
+        .output()
+        .context("Failed to post scroll event via osascript")?;
+    if !output.status.success() {
+        bail!("Failed to turn Digital Crown: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(())
+}
+
+/// Pair a watch simulator with an iPhone simulator (`simctl pair`); returns the pair UUID
+pub fn pair_watch(watch_udid: &str, phone_udid: &str) -> Result<String> {
+    let output = ios::simctl_exec(&["pair", watch_udid, phone_udid])?;
+    if !output.status.success() {
+        bail!("Failed to pair watch {} with {}: {}", watch_udid, phone_udid, String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crown_scroll_script() {
+        assert!(crown_scroll_script(3).contains("kCGScrollEventUnitLine, 1, 3, 0, 0"));
+        assert!(crown_scroll_script(-3).contains(", 1, -3, 0, 0"));
+        assert!(is_watch_runtime("watchOS-10-2"));
+        assert!(!is_watch_runtime("iOS-17-2"));
+    }
+}